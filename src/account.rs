@@ -0,0 +1,472 @@
+//! Looking at other accounts.
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Account;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::html;
+use crate::offline;
+use crate::output::{self, print_account, print_status};
+use crate::paging;
+use crate::resolve::resolve_account;
+use crate::storage::Storage;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct AccountArgs {
+    #[command(subcommand)]
+    command: AccountCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum AccountCommand {
+    /// Feature an account on your profile
+    Endorse {
+        /// The account, e.g. `@someone@example.com` or a profile URL
+        account: String,
+    },
+    /// List the accounts featured on your profile
+    Endorsements,
+    /// List the accounts you follow that also follow an account
+    FamiliarFollowers {
+        /// The account, e.g. `@someone@example.com` or a profile URL
+        account: String,
+    },
+    /// List the accounts that follow an account
+    Followers(ListArgs),
+    /// List the accounts that an account follows
+    Following(ListArgs),
+    /// Show an account's profile and your relationship with it
+    Show {
+        /// The account, e.g. `@someone@example.com` or a profile URL
+        account: String,
+    },
+    /// Show your relationship with several accounts as a table, e.g. before
+    /// following or unfollowing them from a script
+    Relationships {
+        /// The accounts, e.g. `@someone@example.com` or profile URLs
+        #[arg(required = true)]
+        accounts: Vec<String>,
+    },
+    /// List the statuses posted by an account, newest first
+    Statuses(StatusesArgs),
+    /// Stop featuring an account on your profile
+    Unendorse {
+        /// The account, e.g. `@someone@example.com` or a profile URL
+        account: String,
+    },
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    /// The account, e.g. `@someone@example.com` or a profile URL
+    account: String,
+
+    /// Page through every account instead of showing the first page
+    #[arg(long)]
+    all: bool,
+}
+
+#[derive(Debug, Args)]
+struct StatusesArgs {
+    /// The account, e.g. `@someone@example.com` or a profile URL
+    account: String,
+
+    /// Leave out replies to other accounts
+    #[arg(long)]
+    exclude_replies: bool,
+
+    /// Only list statuses with media attached
+    #[arg(long)]
+    only_media: bool,
+
+    /// Only list the statuses pinned to the profile
+    #[arg(long)]
+    pinned: bool,
+
+    /// Page through every status instead of showing the latest page
+    #[arg(long)]
+    all: bool,
+}
+
+/// The most accounts to ask about in one relationships request.
+const RELATIONSHIPS_BATCH: usize = 40;
+
+/// Your relationship with an account.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Relationship {
+    pub id: String,
+    pub following: bool,
+    pub showing_reblogs: bool,
+    pub notifying: bool,
+    /// The languages shown from a followed account, or all of them if unset.
+    pub languages: Option<Vec<String>>,
+    pub followed_by: bool,
+    pub blocking: bool,
+    pub blocked_by: bool,
+    pub muting: bool,
+    pub muting_notifications: bool,
+    pub requested: bool,
+    pub domain_blocking: bool,
+    pub endorsed: bool,
+    pub note: String,
+}
+
+impl Relationship {
+    /// The names of the flags that are set.
+    fn flags(&self) -> Vec<&'static str> {
+        [
+            (self.following, "following"),
+            (self.following && !self.showing_reblogs, "hiding boosts"),
+            (self.notifying, "notified of posts"),
+            (self.requested, "follow requested"),
+            (self.followed_by, "follows you"),
+            (self.blocking, "blocked"),
+            (self.blocked_by, "blocks you"),
+            (self.muting, "muted"),
+            (self.muting_notifications, "notifications muted"),
+            (self.domain_blocking, "domain blocked"),
+            (self.endorsed, "featured on your profile"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect()
+    }
+}
+
+impl AccountArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            AccountCommand::FamiliarFollowers { .. } => {
+                Some(Requirement::new("familiar followers", Version(3, 5, 0)))
+            }
+            _ => None,
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            AccountCommand::Endorse { .. } | AccountCommand::Unendorse { .. } => {
+                &["read:search", "write:accounts"]
+            }
+            _ => &[
+                "read:search",
+                "read:accounts",
+                "read:follows",
+                "read:statuses",
+            ],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: AccountArgs) -> Result<()> {
+    match args.command {
+        AccountCommand::Endorse { account } => set_endorsed(client, &account, true).await,
+        AccountCommand::Endorsements => endorsements(client).await,
+        AccountCommand::FamiliarFollowers { account } => familiar_followers(client, &account).await,
+        AccountCommand::Followers(args) => followers(client, args).await,
+        AccountCommand::Following(args) => following(client, args).await,
+        AccountCommand::Show { account } => show(client, &account).await,
+        AccountCommand::Relationships { accounts } => relationships_table(client, &accounts).await,
+        AccountCommand::Statuses(args) => statuses(client, args).await,
+        AccountCommand::Unendorse { account } => set_endorsed(client, &account, false).await,
+    }
+}
+
+/// Runs the commands that work from the storage, for `--offline`.
+pub fn run_offline(storage: &Storage, args: AccountArgs) -> Result<()> {
+    match args.command {
+        AccountCommand::Show { account } => {
+            let saved = storage
+                .account(&account)?
+                .with_context(|| format!("{account} isn't saved"))?;
+            offline::print_freshness("account", std::slice::from_ref(&saved));
+            print_profile(&saved.item)
+        }
+        AccountCommand::Statuses(args) => {
+            ensure!(
+                !args.pinned,
+                "which statuses are pinned isn't saved, so --pinned needs the instance"
+            );
+            let account = storage
+                .account(&args.account)?
+                .with_context(|| format!("{} isn't saved", args.account))?
+                .item;
+            let id = account.id.to_string();
+            let mut saved = storage.account_statuses(&id)?;
+            if args.exclude_replies {
+                // replies to themselves are threads, which the instance keeps
+                saved.retain(|saved| {
+                    saved.item.in_reply_to_id.is_none()
+                        || saved
+                            .item
+                            .in_reply_to_account_id
+                            .as_ref()
+                            .map_or(false, |parent| parent.to_string() == id)
+                });
+            }
+            if args.only_media {
+                saved.retain(|saved| !saved.item.media_attachments.is_empty());
+            }
+            if !args.all {
+                saved.truncate(offline::PAGE);
+            }
+            output::set_feed(format!("Statuses by @{}", account.acct), account.url);
+            offline::print_freshness("statuses", &saved);
+            for saved in &saved {
+                print_status(&saved.item);
+            }
+            Ok(())
+        }
+        _ => bail!("only account show and account statuses work offline"),
+    }
+}
+
+/// Features an account on your profile, or stops featuring it. Only accounts
+/// that you follow can be featured. Requires the `write:accounts` scope.
+#[instrument(skip(client), err)]
+async fn set_endorsed(client: &Client, acct_or_url: &str, endorse: bool) -> Result<()> {
+    let account = resolve_account(client, acct_or_url).await?;
+    let action = if endorse { "pin" } else { "unpin" };
+    let relationship: Relationship = client
+        .post(&format!("v1/accounts/{}/{action}", account.id), &())
+        .await
+        .with_context(|| format!("Couldn't change whether {} is featured", account.acct))?;
+    info!(
+        acct = account.acct,
+        endorsed = relationship.endorsed,
+        "done"
+    );
+    if relationship.endorsed {
+        println!("featuring @{}", account.acct);
+    } else {
+        println!("not featuring @{}", account.acct);
+    }
+    Ok(())
+}
+
+/// Lists the accounts featured on your profile. Profiles only show a handful,
+/// so this doesn't page.
+#[instrument(skip(client), err)]
+async fn endorsements(client: &Client) -> Result<()> {
+    let accounts: Vec<Account> = client
+        .get("v1/endorsements", &[("limit", client.page_limit())])
+        .await
+        .context("Couldn't get featured accounts")?;
+    for account in &accounts {
+        print_account(account);
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn show(client: &Client, acct_or_url: &str) -> Result<()> {
+    let account = resolve_account(client, acct_or_url).await?;
+    let relationship = relationships(client, &[account.id.to_string()])
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    print_profile(&account)?;
+    let flags = relationship.flags();
+    if !flags.is_empty() {
+        println!("{}", flags.join(", "));
+    }
+    if !relationship.note.is_empty() {
+        println!("note: {}", html::strip_controls(&relationship.note));
+    }
+    Ok(())
+}
+
+fn print_profile(account: &Account) -> Result<()> {
+    println!(
+        "{} @{}",
+        html::strip_controls(&account.display_name),
+        html::strip_controls(&account.acct)
+    );
+    println!("{}", html::strip_controls(&account.url));
+    println!("{}", output::render_html(&account.note));
+    for (name, value) in fields(account)? {
+        println!(
+            "{}: {}",
+            html::strip_controls(&name),
+            output::render_html(&value)
+        );
+    }
+    println!(
+        "{} posts, {} following, {} followers",
+        account.statuses_count, account.following_count, account.followers_count
+    );
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct FamiliarFollowers {
+    accounts: Vec<Account>,
+}
+
+#[instrument(skip(client), err)]
+async fn familiar_followers(client: &Client, acct_or_url: &str) -> Result<()> {
+    let account = resolve_account(client, acct_or_url).await?;
+    let familiar: Vec<FamiliarFollowers> = client
+        .get(
+            "v1/accounts/familiar_followers",
+            &[("id[]", account.id.to_string())],
+        )
+        .await
+        .with_context(|| format!("Couldn't get the familiar followers of {}", account.acct))?;
+    for follower in familiar.iter().flat_map(|familiar| &familiar.accounts) {
+        print_account(follower);
+    }
+    Ok(())
+}
+
+/// Lists the followers of an account. Instances can hide them, in which case
+/// the list is empty.
+#[instrument(skip(client), err)]
+async fn followers(client: &Client, args: ListArgs) -> Result<()> {
+    let account = resolve_account(client, &args.account).await?;
+    let path = format!("v1/accounts/{}/followers", account.id);
+    paging::for_each_item(client, &path, args.all, |follower| {
+        print_account(&follower);
+    })
+    .await
+    .with_context(|| format!("Couldn't get the followers of {}", account.acct))
+}
+
+/// Lists the accounts that an account follows. Instances can hide them, in
+/// which case the list is empty.
+#[instrument(skip(client), err)]
+async fn following(client: &Client, args: ListArgs) -> Result<()> {
+    let account = resolve_account(client, &args.account).await?;
+    let path = format!("v1/accounts/{}/following", account.id);
+    paging::for_each_item(client, &path, args.all, |followed| {
+        print_account(&followed);
+    })
+    .await
+    .with_context(|| format!("Couldn't get the accounts that {} follows", account.acct))
+}
+
+/// Gets your relationship with each of the accounts, in batches.
+#[instrument(skip(client), err)]
+pub async fn relationships(client: &Client, ids: &[String]) -> Result<Vec<Relationship>> {
+    let mut relationships = Vec::with_capacity(ids.len());
+    for batch in ids.chunks(RELATIONSHIPS_BATCH) {
+        let query: Vec<_> = batch.iter().map(|id| ("id[]", id.as_str())).collect();
+        let page: Vec<Relationship> = client
+            .get("v1/accounts/relationships", &query)
+            .await
+            .context("Couldn't get relationships")?;
+        relationships.extend(page);
+    }
+    Ok(relationships)
+}
+
+#[instrument(skip(client), err)]
+async fn relationships_table(client: &Client, accts: &[String]) -> Result<()> {
+    let mut accounts = Vec::with_capacity(accts.len());
+    for acct in accts {
+        accounts.push(resolve_account(client, acct).await?);
+    }
+    let ids: Vec<String> = accounts
+        .iter()
+        .map(|account| account.id.to_string())
+        .collect();
+    let relationships = relationships(client, &ids).await?;
+    let width = accounts
+        .iter()
+        .map(|account| account.acct.len() + 1)
+        .max()
+        .unwrap_or_default()
+        .max("account".len());
+    println!("{:width$} following followed_by blocking muting", "account");
+    let yes_no = |set: bool| if set { "yes" } else { "no" };
+    for account in &accounts {
+        // the response isn't guaranteed to be in the order that was asked for
+        let relationship = relationships
+            .iter()
+            .find(|relationship| relationship.id == account.id.to_string());
+        let Some(relationship) = relationship else {
+            println!("{:width$} (unknown)", format!("@{}", account.acct));
+            continue;
+        };
+        println!(
+            "{:width$} {:9} {:11} {:8} {}",
+            format!("@{}", account.acct),
+            yes_no(relationship.following),
+            yes_no(relationship.followed_by),
+            yes_no(relationship.blocking),
+            yes_no(relationship.muting),
+        );
+    }
+    Ok(())
+}
+
+/// The domain of the instance that the client is connected to.
+pub fn instance_domain(client: &Client) -> String {
+    let base = client.data.base.trim_end_matches('/');
+    let host = base.split_once("://").map_or(base, |(_, host)| host);
+    host.to_owned()
+}
+
+/// The address of an account including its domain, which is left out for
+/// accounts on the same instance, so that addresses from different instances
+/// can be compared.
+pub fn full_acct(account: &Account, domain: &str) -> String {
+    if account.acct.contains('@') {
+        account.acct.clone()
+    } else {
+        format!("{}@{domain}", account.acct)
+    }
+}
+
+/// The name and value of each profile field.
+fn fields(account: &Account) -> Result<Vec<(String, String)>> {
+    let value = serde_json::to_value(account)?;
+    let fields = value["fields"].as_array().cloned().unwrap_or_default();
+    Ok(fields
+        .iter()
+        .map(|field| {
+            let text = |key: &str| field[key].as_str().map(str::to_owned);
+            (
+                text("name").unwrap_or_default(),
+                text("value").unwrap_or_default(),
+            )
+        })
+        .collect())
+}
+
+#[instrument(skip(client), err)]
+async fn statuses(client: &Client, args: StatusesArgs) -> Result<()> {
+    let account = resolve_account(client, &args.account).await?;
+    let mut filters = Vec::new();
+    if args.exclude_replies {
+        filters.push(("exclude_replies", "true"));
+    }
+    if args.only_media {
+        filters.push(("only_media", "true"));
+    }
+    if args.pinned {
+        filters.push(("pinned", "true"));
+    }
+    output::set_feed(
+        format!("Statuses by @{}", account.acct),
+        account.url.clone(),
+    );
+    paging::for_each_account_status(
+        client,
+        &account.id.to_string(),
+        &filters,
+        args.all,
+        |status| {
+            print_status(&status);
+        },
+    )
+    .await
+}
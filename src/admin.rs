@@ -0,0 +1,67 @@
+//! Moderating the instance, for accounts with the admin scopes.
+//!
+//! Every action asks for confirmation first, unless `--yes` is given, and is
+//! recorded in the journal with the local user who took it.
+
+mod accounts;
+mod reports;
+
+use std::io::{self, BufRead, Write};
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+
+use crate::client::Client;
+use crate::version::Requirement;
+use accounts::AccountsArgs;
+use reports::ReportsArgs;
+
+#[derive(Debug, Args)]
+pub struct AdminArgs {
+    #[command(subcommand)]
+    command: AdminCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum AdminCommand {
+    /// Review accounts and take action against them
+    Accounts(AccountsArgs),
+    /// Triage the reports sent to the instance
+    Reports(ReportsArgs),
+}
+
+impl AdminArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match &self.command {
+            AdminCommand::Accounts(args) => args.requirement(),
+            AdminCommand::Reports(_) => None,
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match &self.command {
+            AdminCommand::Accounts(args) => args.scopes(),
+            AdminCommand::Reports(args) => args.scopes(),
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: AdminArgs) -> Result<()> {
+    match args.command {
+        AdminCommand::Accounts(args) => accounts::run(client, args).await,
+        AdminCommand::Reports(args) => reports::run(client, args).await,
+    }
+}
+
+/// Asks a yes or no question, where anything but yes is no.
+fn confirm(question: &str) -> Result<bool> {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{question} [y/N] ").context("failed to write to stdout")?;
+    stdout.flush().context("failed to flush stdout")?;
+    let mut input = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut input)
+        .context("failed to read input")?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
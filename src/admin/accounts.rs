@@ -0,0 +1,284 @@
+//! Reviewing accounts and taking action against them.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, info, instrument};
+
+use super::confirm;
+use crate::client::Client;
+use crate::journal;
+use crate::output::format_time_text;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct AccountsArgs {
+    #[command(subcommand)]
+    command: AccountsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum AccountsCommand {
+    /// List accounts, newest first
+    List {
+        /// Only list accounts in this state
+        #[arg(long, value_enum)]
+        status: Option<AccountStatus>,
+        /// Only list local or remote accounts
+        #[arg(long, value_enum)]
+        origin: Option<Origin>,
+        /// List every account instead of the first page
+        #[arg(long)]
+        all: bool,
+    },
+    /// Approve a pending sign up
+    Approve(ActionArgs),
+    /// Reject a pending sign up, deleting it
+    Reject(ActionArgs),
+    /// Suspend an account, hiding it and its statuses from everyone
+    Suspend(ActionArgs),
+    /// Limit an account, hiding it from people who don't follow it
+    Silence(ActionArgs),
+}
+
+#[derive(Debug, Args)]
+struct ActionArgs {
+    /// The admin id of the account, as listed
+    id: String,
+
+    /// Don't ask for confirmation
+    #[arg(long)]
+    yes: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum AccountStatus {
+    Active,
+    Pending,
+    Disabled,
+    Silenced,
+    Suspended,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Origin {
+    Local,
+    Remote,
+}
+
+impl AccountStatus {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Pending => "pending",
+            Self::Disabled => "disabled",
+            Self::Silenced => "silenced",
+            Self::Suspended => "suspended",
+        }
+    }
+}
+
+impl Origin {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Remote => "remote",
+        }
+    }
+}
+
+/// An account as the admin API shows it, with its moderation state.
+#[derive(Debug, Deserialize)]
+pub struct AdminAccount {
+    id: String,
+    username: String,
+    /// Missing for local accounts.
+    domain: Option<String>,
+    created_at: String,
+    email: Option<String>,
+    #[serde(default)]
+    approved: bool,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default)]
+    silenced: bool,
+    #[serde(default)]
+    suspended: bool,
+    /// The public side of the account.
+    account: Profile,
+}
+
+#[derive(Debug, Deserialize)]
+struct Profile {
+    url: String,
+}
+
+impl AdminAccount {
+    pub fn acct(&self) -> String {
+        match &self.domain {
+            Some(domain) => format!("{}@{domain}", self.username),
+            None => self.username.clone(),
+        }
+    }
+
+    pub const fn state(&self) -> &'static str {
+        if self.suspended {
+            "suspended"
+        } else if self.silenced {
+            "silenced"
+        } else if self.disabled {
+            "disabled"
+        } else if !self.approved {
+            "pending"
+        } else {
+            "active"
+        }
+    }
+}
+
+impl AccountsArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            AccountsCommand::List { .. } => Some(Requirement::new(
+                "the v2 admin accounts API",
+                Version(3, 5, 0),
+            )),
+            _ => None,
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            AccountsCommand::List { .. } => &["admin:read:accounts"],
+            _ => &["admin:read:accounts", "admin:write:accounts"],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: AccountsArgs) -> Result<()> {
+    match args.command {
+        AccountsCommand::List {
+            status,
+            origin,
+            all,
+        } => list(client, status, origin, all).await,
+        AccountsCommand::Approve(args) => act(client, args, Action::Approve).await,
+        AccountsCommand::Reject(args) => act(client, args, Action::Reject).await,
+        AccountsCommand::Suspend(args) => act(client, args, Action::Suspend).await,
+        AccountsCommand::Silence(args) => act(client, args, Action::Silence).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn list(
+    client: &Client,
+    status: Option<AccountStatus>,
+    origin: Option<Origin>,
+    all: bool,
+) -> Result<()> {
+    let mut query = vec![("limit", client.page_limit())];
+    if let Some(status) = status {
+        query.push(("status", status.as_str()));
+    }
+    if let Some(origin) = origin {
+        query.push(("origin", origin.as_str()));
+    }
+    let (mut accounts, mut next) = client
+        .get_page::<AdminAccount>("v2/admin/accounts", &query)
+        .await
+        .context("Couldn't get accounts")?;
+    while let Some(url) = next.filter(|_| all) {
+        let (page, page_next) = client
+            .get_next_page::<AdminAccount>(&url)
+            .await
+            .context("Couldn't get accounts")?;
+        debug!(count = page.len(), "loaded next page");
+        accounts.extend(page);
+        next = page_next;
+    }
+    for account in &accounts {
+        println!(
+            "{} @{} {} {}{}",
+            account.id,
+            account.acct(),
+            account.state(),
+            format_time_text(&account.created_at),
+            account
+                .email
+                .as_deref()
+                .map_or_else(String::new, |email| format!(" {email}"))
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    Approve,
+    Reject,
+    Suspend,
+    Silence,
+}
+
+impl Action {
+    /// The verb for the confirmation prompt.
+    const fn verb(self) -> &'static str {
+        match self {
+            Self::Approve => "approve",
+            Self::Reject => "reject",
+            Self::Suspend => "suspend",
+            Self::Silence => "silence",
+        }
+    }
+
+    /// The action recorded in the journal.
+    const fn journal_action(self) -> &'static str {
+        match self {
+            Self::Approve => "approved account",
+            Self::Reject => "rejected account",
+            Self::Suspend => "suspended account",
+            Self::Silence => "silenced account",
+        }
+    }
+}
+
+/// Takes the action against the account after confirming it, and records it
+/// in the journal.
+#[instrument(skip(client), err)]
+async fn act(client: &Client, args: ActionArgs, action: Action) -> Result<()> {
+    let id = &args.id;
+    let account: AdminAccount = client
+        .get(&format!("v1/admin/accounts/{id}"), &())
+        .await
+        .with_context(|| format!("Couldn't get account {id}"))?;
+    let acct = account.acct();
+    if !args.yes && !confirm(&format!("{} @{acct} ({})?", action.verb(), account.state()))? {
+        info!("cancelled");
+        return Ok(());
+    }
+    let _: Value = match action {
+        Action::Approve | Action::Reject => {
+            client
+                .post(&format!("v1/admin/accounts/{id}/{}", action.verb()), &())
+                .await
+        }
+        Action::Suspend | Action::Silence => {
+            client
+                .post(
+                    &format!("v1/admin/accounts/{id}/action"),
+                    &[("type", action.verb())],
+                )
+                .await
+        }
+    }
+    .with_context(|| format!("Couldn't {} @{acct}", action.verb()))?;
+    journal::record(
+        action.journal_action(),
+        id,
+        &journal::local_user(),
+        Some(&account.account.url),
+    )?;
+    info!(id, acct, action = action.verb(), "moderated account");
+    Ok(())
+}
@@ -0,0 +1,181 @@
+//! Triaging the reports sent to the instance.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Status;
+use serde::Deserialize;
+use tracing::{debug, info, instrument};
+
+use super::accounts::AdminAccount;
+use super::confirm;
+use crate::client::Client;
+use crate::journal;
+use crate::output::{format_time_text, render_html};
+
+#[derive(Debug, Args)]
+pub struct ReportsArgs {
+    #[command(subcommand)]
+    command: ReportsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ReportsCommand {
+    /// List the unresolved reports, newest first
+    List {
+        /// List the resolved reports instead
+        #[arg(long)]
+        resolved: bool,
+        /// List every report instead of the first page
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show a report with the reported statuses
+    Show {
+        /// The id of the report
+        id: String,
+    },
+    /// Mark a report as resolved
+    Resolve {
+        /// The id of the report
+        id: String,
+        /// Don't ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Report {
+    id: String,
+    #[serde(default)]
+    action_taken: bool,
+    /// Why the account was reported, e.g. `spam` or `violation`.
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    comment: String,
+    created_at: String,
+    /// Who sent the report.
+    account: AdminAccount,
+    target_account: AdminAccount,
+    assigned_account: Option<AdminAccount>,
+    #[serde(default)]
+    statuses: Vec<Status>,
+}
+
+impl ReportsArgs {
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            ReportsCommand::List { .. } | ReportsCommand::Show { .. } => &["admin:read:reports"],
+            ReportsCommand::Resolve { .. } => &["admin:read:reports", "admin:write:reports"],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: ReportsArgs) -> Result<()> {
+    match args.command {
+        ReportsCommand::List { resolved, all } => list(client, resolved, all).await,
+        ReportsCommand::Show { id } => show(client, &id).await,
+        ReportsCommand::Resolve { id, yes } => resolve(client, &id, yes).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn list(client: &Client, resolved: bool, all: bool) -> Result<()> {
+    let resolved = if resolved { "true" } else { "false" };
+    let query = [("limit", client.page_limit()), ("resolved", resolved)];
+    let (mut reports, mut next) = client
+        .get_page::<Report>("v1/admin/reports", &query)
+        .await
+        .context("Couldn't get reports")?;
+    while let Some(url) = next.filter(|_| all) {
+        let (page, page_next) = client
+            .get_next_page::<Report>(&url)
+            .await
+            .context("Couldn't get reports")?;
+        debug!(count = page.len(), "loaded next page");
+        reports.extend(page);
+        next = page_next;
+    }
+    for report in &reports {
+        println!(
+            "{} {} @{} reported @{} for {}, {} statuses",
+            report.id,
+            format_time_text(&report.created_at),
+            report.account.acct(),
+            report.target_account.acct(),
+            report.category,
+            report.statuses.len()
+        );
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn show(client: &Client, id: &str) -> Result<()> {
+    let report = get_report(client, id).await?;
+    println!(
+        "report {} from {}",
+        report.id,
+        format_time_text(&report.created_at)
+    );
+    println!("by: @{}", report.account.acct());
+    println!(
+        "against: @{} ({})",
+        report.target_account.acct(),
+        report.target_account.state()
+    );
+    println!("category: {}", report.category);
+    if let Some(assigned) = &report.assigned_account {
+        println!("assigned to: @{}", assigned.acct());
+    }
+    println!(
+        "status: {}",
+        if report.action_taken {
+            "resolved"
+        } else {
+            "unresolved"
+        }
+    );
+    if !report.comment.is_empty() {
+        println!("comment: {}", report.comment);
+    }
+    for status in &report.statuses {
+        println!();
+        println!(
+            "@{} {}",
+            status.account.acct,
+            status.url.as_deref().unwrap_or(&status.uri)
+        );
+        if !status.spoiler_text.is_empty() {
+            println!("CW: {}", status.spoiler_text);
+        }
+        println!("{}", render_html(&status.content));
+    }
+    Ok(())
+}
+
+/// Resolves the report after confirming it, and records it in the journal.
+#[instrument(skip(client), err)]
+async fn resolve(client: &Client, id: &str, yes: bool) -> Result<()> {
+    let report = get_report(client, id).await?;
+    let target = report.target_account.acct();
+    if !yes && !confirm(&format!("resolve report {id} against @{target}?"))? {
+        info!("cancelled");
+        return Ok(());
+    }
+    let _: Report = client
+        .post(&format!("v1/admin/reports/{id}/resolve"), &())
+        .await
+        .with_context(|| format!("Couldn't resolve report {id}"))?;
+    journal::record("resolved report", id, &journal::local_user(), None)?;
+    info!(id, target, "resolved report");
+    Ok(())
+}
+
+async fn get_report(client: &Client, id: &str) -> Result<Report> {
+    client
+        .get(&format!("v1/admin/reports/{id}"), &())
+        .await
+        .with_context(|| format!("Couldn't get report {id}"))
+}
@@ -0,0 +1,133 @@
+//! Announcements that the instance staff show to everyone on the instance.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::output::{format_time_text, render_html};
+
+#[derive(Debug, Args)]
+pub struct AnnouncementsArgs {
+    #[command(subcommand)]
+    command: AnnouncementsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum AnnouncementsCommand {
+    /// List the current announcements, newest first
+    List {
+        /// Include the announcements that were dismissed
+        #[arg(long)]
+        all: bool,
+    },
+    /// Mark an announcement as read
+    Dismiss {
+        /// The id of the announcement
+        id: String,
+    },
+    /// React to an announcement with an emoji
+    React {
+        /// The id of the announcement
+        id: String,
+        /// A unicode emoji, or the shortcode of a custom emoji, e.g. `blobcat`
+        emoji: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Announcement {
+    id: String,
+    content: String,
+    published_at: String,
+    #[serde(default)]
+    read: bool,
+    #[serde(default)]
+    reactions: Vec<Reaction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Reaction {
+    name: String,
+    count: u64,
+    #[serde(default)]
+    me: bool,
+}
+
+impl AnnouncementsArgs {
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            AnnouncementsCommand::List { .. } => &["read"],
+            AnnouncementsCommand::Dismiss { .. } => &["write:accounts"],
+            AnnouncementsCommand::React { .. } => &["write:favourites"],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: AnnouncementsArgs) -> Result<()> {
+    match args.command {
+        AnnouncementsCommand::List { all } => list(client, all).await,
+        AnnouncementsCommand::Dismiss { id } => dismiss(client, &id).await,
+        AnnouncementsCommand::React { id, emoji } => react(client, &id, &emoji).await,
+    }
+}
+
+/// Prints each announcement with its id, when it was published and its
+/// reactions. Unread announcements are marked with a `*`.
+#[instrument(skip(client), err)]
+async fn list(client: &Client, all: bool) -> Result<()> {
+    let with_dismissed = if all { "true" } else { "false" };
+    let announcements: Vec<Announcement> = client
+        .get("v1/announcements", &[("with_dismissed", with_dismissed)])
+        .await
+        .context("Couldn't get announcements")?;
+    for announcement in &announcements {
+        let unread = if announcement.read { " " } else { "*" };
+        println!(
+            "{unread} {} {}",
+            announcement.id,
+            format_time_text(&announcement.published_at)
+        );
+        let content = render_html(&announcement.content);
+        println!("  {}", content.replace('\n', "\n  "));
+        let reactions: Vec<String> = announcement
+            .reactions
+            .iter()
+            .map(|reaction| {
+                let me = if reaction.me { " (you)" } else { "" };
+                format!("{} {}{me}", reaction.name, reaction.count)
+            })
+            .collect();
+        if !reactions.is_empty() {
+            println!("  {}", reactions.join("  "));
+        }
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn dismiss(client: &Client, id: &str) -> Result<()> {
+    let _: Value = client
+        .post(&format!("v1/announcements/{id}/dismiss"), &())
+        .await
+        .with_context(|| format!("Couldn't dismiss announcement {id}"))?;
+    info!(id, "dismissed announcement");
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn react(client: &Client, id: &str, emoji: &str) -> Result<()> {
+    // custom emoji are given by shortcode, without the colons
+    let name = emoji.trim_matches(':');
+    let _: Value = client
+        .put_json(
+            &format!("v1/announcements/{id}/reactions/{name}"),
+            &serde_json::json!({}),
+        )
+        .await
+        .with_context(|| format!("Couldn't react to announcement {id}"))?;
+    info!(id, name, "reacted to announcement");
+    Ok(())
+}
@@ -0,0 +1,428 @@
+//! A personal backup of an account, independent of the instance.
+//!
+//! `archive create` writes a folder with the account, one JSON file per line
+//! for each kind of entity, and the media of your own statuses:
+//!
+//! ```text
+//! archive/
+//!   account.json
+//!   statuses.ndjson
+//!   favourites.ndjson
+//!   bookmarks.ndjson
+//!   following.ndjson
+//!   followers.ndjson
+//!   lists.json
+//!   media/manifest.json
+//!   media/<sha256>.<extension>
+//!   progress.json
+//! ```
+//!
+//! Each page is appended as soon as it arrives, and `progress.json` records
+//! how far each part got, so running the command again with the same folder
+//! carries on from the last page instead of starting over. Media is stored by
+//! the hash of its content, which `archive verify` checks.
+//!
+//! `--format mastodon` also writes the files of Mastodon's own export, see
+//! [`mastodon`], and `archive browse` reads such an export, see [`browse`].
+
+mod browse;
+mod mastodon;
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use mastodon_async::prelude::{Account, Status};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, warn};
+
+use crate::client::Client;
+use crate::media_store::{Counts, MediaStore};
+use crate::scrub::{self, Scrubber};
+use crate::version::{Requirement, Version};
+use browse::BrowseArgs;
+
+#[derive(Debug, Args)]
+pub struct ArchiveArgs {
+    #[command(subcommand)]
+    command: ArchiveCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ArchiveCommand {
+    /// List and search the posts in an export of a Mastodon account, which
+    /// works without logging in
+    Browse(BrowseArgs),
+    /// Back up your statuses, favourites, bookmarks, follows and lists, with
+    /// the media of your statuses, to a folder
+    Create(CreateArgs),
+    /// Check that the media of an archive hasn't been changed or lost since
+    /// it was downloaded
+    Verify(VerifyArgs),
+}
+
+#[derive(Debug, Args)]
+struct CreateArgs {
+    /// The folder to write the archive to. An interrupted archive carries on
+    /// where it stopped, but the pseudonyms of `--scrub` are only consistent
+    /// within a run, so start over in a new folder if a scrubbed archive is
+    /// interrupted.
+    #[arg(long, default_value = "archive")]
+    dir: PathBuf,
+
+    /// Leave out the media
+    #[arg(long)]
+    no_media: bool,
+
+    /// The layout of the archive, which can't be `mastodon` with `--scrub`
+    #[arg(long, value_enum, default_value_t)]
+    format: ArchiveFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum ArchiveFormat {
+    /// The entities as the API returns them, one per line
+    #[default]
+    Ndjson,
+    /// Also `actor.json`, `outbox.json` and the media as Mastodon exports
+    /// them, for tools that read those
+    Mastodon,
+}
+
+#[derive(Debug, Args)]
+struct VerifyArgs {
+    /// The folder of the archive
+    #[arg(long, default_value = "archive")]
+    dir: PathBuf,
+}
+
+impl ArchiveArgs {
+    /// Whether the command only reads local files, so it can be run without
+    /// an account.
+    pub const fn is_local(&self) -> bool {
+        matches!(
+            self.command,
+            ArchiveCommand::Browse(_) | ArchiveCommand::Verify(_)
+        )
+    }
+
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            ArchiveCommand::Browse(_) | ArchiveCommand::Verify(_) => None,
+            ArchiveCommand::Create(_) => Some(Requirement::new("bookmarks", Version(3, 1, 0))),
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            ArchiveCommand::Create(_) => &[
+                "read:accounts",
+                "read:statuses",
+                "read:favourites",
+                "read:bookmarks",
+                "read:follows",
+                "read:lists",
+            ],
+            ArchiveCommand::Browse(_) | ArchiveCommand::Verify(_) => &[],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: ArchiveArgs) -> Result<()> {
+    match args.command {
+        ArchiveCommand::Create(args) => create(client, args).await,
+        _ => run_local(args),
+    }
+}
+
+/// Runs a command that [`ArchiveArgs::is_local`], without logging in.
+///
+/// # Errors
+///
+/// Returns an error if the command needs an account or fails.
+pub fn run_local(args: ArchiveArgs) -> Result<()> {
+    match args.command {
+        ArchiveCommand::Browse(args) => browse::run(&args),
+        ArchiveCommand::Verify(args) => verify(&args),
+        ArchiveCommand::Create(_) => bail!("archive create needs an account"),
+    }
+}
+
+/// How far each part of an archive got.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Progress {
+    parts: BTreeMap<String, Part>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct Part {
+    /// The number of lines written, so that a page written after the progress
+    /// was last saved can be dropped.
+    items: usize,
+    /// The page to carry on from.
+    next: Option<String>,
+    done: bool,
+}
+
+/// An archive being written, with its progress saved after every page.
+struct Archive {
+    dir: PathBuf,
+    progress: Progress,
+}
+
+impl Archive {
+    fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("cannot create {dir:?}"))?;
+        let path = dir.join("progress.json");
+        let progress = match fs::read_to_string(&path) {
+            Ok(text) => {
+                serde_json::from_str(&text).with_context(|| format!("cannot parse {path:?}"))?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Progress::default(),
+            Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+        };
+        Ok(Self {
+            dir: dir.to_owned(),
+            progress,
+        })
+    }
+
+    fn save_progress(&self) -> Result<()> {
+        let path = self.dir.join("progress.json");
+        let json = serde_json::to_string_pretty(&self.progress)?;
+        fs::write(&path, json).with_context(|| format!("cannot write file {path:?}"))
+    }
+
+    fn write_json(&self, name: &str, value: &impl Serialize) -> Result<()> {
+        let path = self.dir.join(name);
+        let json = serde_json::to_string_pretty(value)?;
+        fs::write(&path, json).with_context(|| format!("cannot write file {path:?}"))
+    }
+
+    /// Writes every page of an endpoint that is paged with the `Link` header
+    /// to `<name>.ndjson`, starting from the page the last run stopped at.
+    async fn part<T>(
+        &mut self,
+        client: &Client,
+        name: &str,
+        path: &str,
+        scrub: fn(&mut Scrubber, &mut T),
+    ) -> Result<()>
+    where
+        T: DeserializeOwned + Serialize,
+    {
+        let mut part = self.progress.parts.get(name).cloned().unwrap_or_default();
+        if part.done {
+            debug!(name, "already archived");
+            return Ok(());
+        }
+        let file = self.dir.join(format!("{name}.ndjson"));
+        // start over unless there is a page to carry on from
+        if part.next.is_none() {
+            part.items = 0;
+        }
+        keep_lines(&file, part.items)?;
+        let (mut items, mut next) = match &part.next {
+            Some(url) => client.get_next_page::<T>(url).await,
+            None => {
+                client
+                    .get_page::<T>(path, &[("limit", client.page_limit())])
+                    .await
+            }
+        }
+        .with_context(|| format!("Couldn't get {name}"))?;
+        loop {
+            scrub::with(|scrubber| items.iter_mut().for_each(|item| scrub(scrubber, item)));
+            append_lines(&file, &items)?;
+            part.items += items.len();
+            part.done = next.is_none() || items.is_empty();
+            part.next = next.clone();
+            self.progress.parts.insert(name.to_owned(), part.clone());
+            self.save_progress()?;
+            debug!(name, count = part.items, "archived page");
+            let Some(url) = next.filter(|_| !part.done) else {
+                break;
+            };
+            (items, next) = client
+                .get_next_page::<T>(&url)
+                .await
+                .with_context(|| format!("Couldn't get {name}"))?;
+        }
+        info!(name, count = part.items, "archived");
+        Ok(())
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn create(client: &Client, args: CreateArgs) -> Result<()> {
+    let account = client
+        .verify_credentials()
+        .await
+        .context("Couldn't get account")?;
+    ensure!(
+        !(scrub::is_enabled() && args.format == ArchiveFormat::Mastodon),
+        "--scrub can't be used with --format mastodon"
+    );
+    let mut archive = Archive::open(&args.dir)?;
+    archive.write_json("account.json", &account)?;
+
+    let id = &account.id;
+    let statuses = format!("v1/accounts/{id}/statuses");
+    archive
+        .part(client, "statuses", &statuses, Scrubber::scrub_status)
+        .await?;
+    archive
+        .part(
+            client,
+            "favourites",
+            "v1/favourites",
+            Scrubber::scrub_status,
+        )
+        .await?;
+    archive
+        .part(client, "bookmarks", "v1/bookmarks", Scrubber::scrub_status)
+        .await?;
+    let following = format!("v1/accounts/{id}/following");
+    archive
+        .part(client, "following", &following, Scrubber::scrub_account)
+        .await?;
+    let followers = format!("v1/accounts/{id}/followers");
+    archive
+        .part(client, "followers", &followers, Scrubber::scrub_account)
+        .await?;
+    lists(client, &mut archive).await?;
+    // scrubbed statuses have no media
+    if !args.no_media && !scrub::is_enabled() {
+        media(client, &args.dir).await?;
+    }
+    if args.format == ArchiveFormat::Mastodon {
+        mastodon::write(client, &args.dir, !args.no_media).await?;
+    }
+    info!(dir = ?args.dir, "archive complete");
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct List {
+    id: String,
+    title: String,
+    #[serde(default)]
+    accounts: Vec<Account>,
+}
+
+/// Writes the lists with their accounts to `lists.json`. There are few enough
+/// to fetch in one go.
+#[instrument(skip_all, err)]
+async fn lists(client: &Client, archive: &mut Archive) -> Result<()> {
+    if archive
+        .progress
+        .parts
+        .get("lists")
+        .map_or(false, |part| part.done)
+    {
+        debug!("lists already archived");
+        return Ok(());
+    }
+    let mut lists: Vec<List> = client
+        .get("v1/lists", &())
+        .await
+        .context("Couldn't get lists")?;
+    for list in &mut lists {
+        // a limit of 0 returns every account
+        list.accounts = client
+            .get(&format!("v1/lists/{}/accounts", list.id), &[("limit", "0")])
+            .await
+            .with_context(|| format!("Couldn't get the accounts in list {}", list.title))?;
+        scrub::with(|scrubber| {
+            list.accounts
+                .iter_mut()
+                .for_each(|account| scrubber.scrub_account(account));
+        });
+    }
+    archive.write_json("lists.json", &lists)?;
+    let part = Part {
+        items: lists.len(),
+        next: None,
+        done: true,
+    };
+    archive.progress.parts.insert("lists".to_owned(), part);
+    archive.save_progress()?;
+    info!(count = lists.len(), "archived lists");
+    Ok(())
+}
+
+/// Downloads the media attached to the archived statuses to `media/`, see
+/// [`crate::media_store`]. Boosts are left out, as their media belongs to
+/// someone else.
+#[instrument(skip(client), err)]
+async fn media(client: &Client, dir: &Path) -> Result<()> {
+    let mut store = MediaStore::open(&dir.join("media"))?;
+    let path = dir.join("statuses.ndjson");
+    let text = fs::read_to_string(&path).with_context(|| format!("cannot read file {path:?}"))?;
+    let mut counts = Counts::default();
+    for line in text.lines() {
+        let status: Status =
+            serde_json::from_str(line).with_context(|| format!("cannot parse {path:?}"))?;
+        store.store_status(client, &status, &mut counts).await?;
+    }
+    store.save()?;
+    info!(
+        downloaded = counts.downloaded,
+        deduplicated = counts.deduplicated,
+        skipped = counts.skipped,
+        failed = counts.failed,
+        "archived media"
+    );
+    Ok(())
+}
+
+/// Checks the media of an archive against the hashes in its manifest.
+#[instrument(err)]
+fn verify(args: &VerifyArgs) -> Result<()> {
+    let dir = args.dir.join("media");
+    let problems = MediaStore::open(&dir)?.verify()?;
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if !problems.is_empty() {
+        bail!(
+            "{} media files in {dir:?} failed verification",
+            problems.len()
+        );
+    }
+    println!("all media in {} verified", dir.display());
+    Ok(())
+}
+
+/// Drops any lines after the first `count`, creating the file if needed.
+fn keep_lines(path: &Path, count: usize) -> Result<()> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+    };
+    let kept: String = text
+        .lines()
+        .take(count)
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    fs::write(path, kept).with_context(|| format!("cannot write file {path:?}"))
+}
+
+fn append_lines(path: &Path, items: &[impl Serialize]) -> Result<()> {
+    let mut lines = String::new();
+    for item in items {
+        lines.push_str(&serde_json::to_string(item)?);
+        lines.push('\n');
+    }
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .with_context(|| format!("cannot write file {path:?}"))
+}
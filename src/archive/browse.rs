@@ -0,0 +1,256 @@
+//! Reading the posts in an export of a Mastodon account, without the account
+//! or even the instance still being around.
+//!
+//! An export is a `.tar.gz` file with `actor.json` and `outbox.json` at the top,
+//! or a folder with them, such as one written by `archive create --format
+//! mastodon`. The outbox is an ActivityPub collection of `Create` activities
+//! for posts and `Announce` activities for boosts, oldest first.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, instrument, warn};
+
+use crate::html;
+use crate::output::{format_time_text, print_data, render_html};
+
+#[derive(Debug, Args)]
+pub struct BrowseArgs {
+    /// The export, a `.tar.gz` file as Mastodon sends it or a folder with its
+    /// files
+    path: PathBuf,
+
+    /// Only show posts whose text, content warning or hashtags contain this,
+    /// ignoring case
+    #[arg(long)]
+    search: Option<String>,
+
+    /// Leave out boosts
+    #[arg(long)]
+    no_boosts: bool,
+
+    /// How many posts to show, newest first
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+
+    /// Show every post, instead of `--limit`
+    #[arg(long, conflicts_with = "limit")]
+    all: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Actor {
+    preferred_username: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Outbox {
+    #[serde(default)]
+    ordered_items: Vec<Value>,
+}
+
+/// A post or boost from the outbox, which is what `--output json` prints.
+#[derive(Debug, Serialize)]
+struct Post {
+    id: String,
+    url: Option<String>,
+    published: String,
+    /// The URI of the boosted post, for boosts.
+    boosted: Option<String>,
+    in_reply_to: Option<String>,
+    summary: Option<String>,
+    content: String,
+    attachments: Vec<Attachment>,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Attachment {
+    url: String,
+    description: Option<String>,
+}
+
+#[instrument(err)]
+pub fn run(args: &BrowseArgs) -> Result<()> {
+    let (actor, outbox) = read_export(&args.path)?;
+    let actor: Actor = serde_json::from_str(&actor).context("cannot parse actor.json")?;
+    let outbox: Outbox = serde_json::from_str(&outbox).context("cannot parse outbox.json")?;
+    let search = args.search.as_deref().map(str::to_lowercase);
+    let limit = if args.all { usize::MAX } else { args.limit };
+    let posts = outbox
+        .ordered_items
+        .iter()
+        .rev()
+        .filter_map(post)
+        .filter(|post| !(args.no_boosts && post.boosted.is_some()))
+        .filter(|post| {
+            search
+                .as_deref()
+                .map_or(true, |search| matches(post, search))
+        })
+        .take(limit);
+    for post in posts {
+        print_post(&actor.preferred_username, &post);
+    }
+    Ok(())
+}
+
+/// Reads `actor.json` and `outbox.json` from an export.
+fn read_export(path: &Path) -> Result<(String, String)> {
+    if path.is_dir() {
+        let read = |name: &str| {
+            let path = path.join(name);
+            fs::read_to_string(&path).with_context(|| format!("cannot read file {path:?}"))
+        };
+        return Ok((read("actor.json")?, read("outbox.json")?));
+    }
+    let file = File::open(path).with_context(|| format!("cannot read file {path:?}"))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let (mut actor, mut outbox) = (None, None);
+    for entry in archive
+        .entries()
+        .with_context(|| format!("cannot read {path:?} as a .tar.gz file"))?
+    {
+        let mut entry = entry.with_context(|| format!("cannot read {path:?}"))?;
+        let name = entry
+            .path()?
+            .to_string_lossy()
+            .trim_start_matches("./")
+            .to_owned();
+        let slot = match name.as_str() {
+            "actor.json" => &mut actor,
+            "outbox.json" => &mut outbox,
+            _ => continue,
+        };
+        let mut text = String::new();
+        entry
+            .read_to_string(&mut text)
+            .with_context(|| format!("cannot read {name} in {path:?}"))?;
+        debug!(name, "read from the export");
+        *slot = Some(text);
+        // the media that follows can be large, so stop as soon as possible
+        if actor.is_some() && outbox.is_some() {
+            break;
+        }
+    }
+    match (actor, outbox) {
+        (Some(actor), Some(outbox)) => Ok((actor, outbox)),
+        _ => bail!("{path:?} has no actor.json and outbox.json, is it a Mastodon export?"),
+    }
+}
+
+/// The post or boost of an activity in the outbox, or `None` for any other
+/// kind of activity.
+fn post(activity: &Value) -> Option<Post> {
+    let published = text(activity, "published").unwrap_or_default();
+    match activity.get("type").and_then(Value::as_str) {
+        Some("Announce") => Some(Post {
+            id: text(activity, "id")?,
+            url: None,
+            published,
+            boosted: text(activity, "object"),
+            in_reply_to: None,
+            summary: None,
+            content: String::new(),
+            attachments: Vec::new(),
+            tags: Vec::new(),
+        }),
+        Some("Create") => {
+            let note = activity.get("object")?;
+            let attachments = array(note, "attachment")
+                .filter_map(|attachment| {
+                    Some(Attachment {
+                        url: text(attachment, "url")?,
+                        description: text(attachment, "name"),
+                    })
+                })
+                .collect();
+            let tags = array(note, "tag")
+                .filter(|tag| tag.get("type").and_then(Value::as_str) == Some("Hashtag"))
+                .filter_map(|tag| text(tag, "name"))
+                .collect();
+            Some(Post {
+                id: text(note, "id")?,
+                url: text(note, "url"),
+                published: text(note, "published").unwrap_or(published),
+                boosted: None,
+                in_reply_to: text(note, "inReplyTo"),
+                summary: text(note, "summary").filter(|summary| !summary.is_empty()),
+                content: text(note, "content").unwrap_or_default(),
+                attachments,
+                tags,
+            })
+        }
+        kind => {
+            warn!(?kind, "skipping activity");
+            None
+        }
+    }
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_owned)
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// Whether the text, content warning or hashtags of a post contain a
+/// lowercase search.
+fn matches(post: &Post, search: &str) -> bool {
+    html::to_text(&post.content).to_lowercase().contains(search)
+        || post
+            .summary
+            .as_ref()
+            .map_or(false, |summary| summary.to_lowercase().contains(search))
+        || post
+            .tags
+            .iter()
+            .any(|tag| tag.to_lowercase().contains(search))
+}
+
+/// Prints a post like a status, with the author, URL and time, content
+/// warning, content and attachments, followed by a blank line.
+fn print_post(username: &str, post: &Post) {
+    if print_data(post) {
+        return;
+    }
+    let time = format_time_text(&post.published);
+    if let Some(boosted) = &post.boosted {
+        println!("@{username} boosted {boosted} {time}");
+        println!();
+        return;
+    }
+    let url = post.url.as_deref().unwrap_or(&post.id);
+    println!("@{username} {url} {time}");
+    if let Some(in_reply_to) = &post.in_reply_to {
+        println!("in reply to {in_reply_to}");
+    }
+    if let Some(summary) = &post.summary {
+        println!("CW: {summary}");
+    }
+    let content = render_html(&post.content);
+    if !content.is_empty() {
+        println!("{content}");
+    }
+    for attachment in &post.attachments {
+        match &attachment.description {
+            Some(description) => println!("[{}] {description}", attachment.url),
+            None => println!("[{}]", attachment.url),
+        }
+    }
+    println!();
+}
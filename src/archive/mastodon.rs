@@ -0,0 +1,347 @@
+//! The files of Mastodon's own account export, written from an archive so that
+//! tools that read those exports can read it too:
+//!
+//! ```text
+//! actor.json
+//! outbox.json
+//! likes.json
+//! bookmarks.json
+//! avatar.<extension>
+//! header.<extension>
+//! media_attachments/files/...
+//! ```
+//!
+//! The API doesn't return everything ActivityPub has, so a few things are
+//! rebuilt the way Mastodon builds them: the actor's id is `/users/<username>`
+//! on the instance, mentions are addressed to the profile URL, and a reply is
+//! only linked to its parent when the parent is in the archive too.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use tracing::{info, instrument, warn};
+
+use crate::client::Client;
+use crate::media_store::{extension, MediaStore};
+
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// Writes the Mastodon export files to the folder of an archive, from its
+/// `account.json` and `.ndjson` files and its media.
+#[instrument(skip(client), err)]
+pub async fn write(client: &Client, dir: &Path, media: bool) -> Result<()> {
+    let account = read_json(&dir.join("account.json"))?;
+    let actor = format!(
+        "{}/users/{}",
+        client.data.base.trim_end_matches('/'),
+        text(&account, "username")
+    );
+    let mut person = person(&account, &actor);
+    if media {
+        for (key, field) in [("icon", "avatar"), ("image", "header")] {
+            if let Some(url) = account.get(field).and_then(Value::as_str) {
+                match image(client, dir, url, field).await {
+                    Ok(image) => person[key] = image,
+                    Err(err) => warn!(?err, field, "couldn't download image"),
+                }
+            }
+        }
+    }
+    write_json(&dir.join("actor.json"), &person)?;
+
+    let statuses = read_lines(&dir.join("statuses.ndjson"))?;
+    let store_dir = dir.join("media");
+    let store = if media && store_dir.exists() {
+        Some(MediaStore::open(&store_dir)?)
+    } else {
+        None
+    };
+    let uris: HashMap<&str, &str> = statuses
+        .iter()
+        .map(|status| (text(status, "id"), text(status, "uri")))
+        .collect();
+    let mut items = Vec::with_capacity(statuses.len());
+    // the archive is newest first, and the outbox of an export oldest first
+    for status in statuses.iter().rev() {
+        items.push(activity(status, &actor, &uris, store.as_ref(), dir)?);
+    }
+    let outbox = json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": "outbox.json",
+        "type": "OrderedCollection",
+        "totalItems": items.len(),
+        "orderedItems": items,
+    });
+    write_json(&dir.join("outbox.json"), &outbox)?;
+
+    for (part, name) in [
+        ("favourites", "likes.json"),
+        ("bookmarks", "bookmarks.json"),
+    ] {
+        let statuses = read_lines(&dir.join(format!("{part}.ndjson")))?;
+        let uris: Vec<&str> = statuses.iter().map(|status| text(status, "uri")).collect();
+        let collection = json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "id": name,
+            "type": "OrderedCollection",
+            "orderedItems": uris,
+        });
+        write_json(&dir.join(name), &collection)?;
+    }
+    info!(statuses = statuses.len(), "wrote the Mastodon export files");
+    Ok(())
+}
+
+fn person(account: &Value, actor: &str) -> Value {
+    let fields: Vec<Value> = array(account, "fields")
+        .map(|field| {
+            json!({
+                "type": "PropertyValue",
+                "name": text(field, "name"),
+                "value": text(field, "value"),
+            })
+        })
+        .collect();
+    let bot = account.get("bot").and_then(Value::as_bool) == Some(true);
+    json!({
+        "@context": [
+            "https://www.w3.org/ns/activitystreams",
+            {
+                "toot": "http://joinmastodon.org/ns#",
+                "schema": "http://schema.org#",
+                "manuallyApprovesFollowers": "as:manuallyApprovesFollowers",
+                "discoverable": "toot:discoverable",
+                "sensitive": "as:sensitive",
+                "Hashtag": "as:Hashtag",
+                "blurhash": "toot:blurhash",
+                "PropertyValue": "schema:PropertyValue",
+                "value": "schema:value",
+            },
+        ],
+        "id": actor,
+        "type": if bot { "Service" } else { "Person" },
+        "following": format!("{actor}/following"),
+        "followers": format!("{actor}/followers"),
+        "inbox": format!("{actor}/inbox"),
+        "outbox": "outbox.json",
+        "likes": "likes.json",
+        "bookmarks": "bookmarks.json",
+        "preferredUsername": text(account, "username"),
+        "name": text(account, "display_name"),
+        "summary": text(account, "note"),
+        "url": text(account, "url"),
+        "manuallyApprovesFollowers": account.get("locked").and_then(Value::as_bool) == Some(true),
+        "discoverable": account.get("discoverable").and_then(Value::as_bool) == Some(true),
+        "published": text(account, "created_at"),
+        "attachment": fields,
+    })
+}
+
+/// Downloads the avatar or header next to `actor.json`, as an export has them.
+async fn image(client: &Client, dir: &Path, url: &str, name: &str) -> Result<Value> {
+    let file = format!("{name}.{}", extension(url));
+    let path = dir.join(&file);
+    if !path.exists() {
+        let (bytes, _) = client.download(url).await?;
+        fs::write(&path, bytes).with_context(|| format!("cannot write file {path:?}"))?;
+    }
+    Ok(json!({
+        "type": "Image",
+        "mediaType": media_type(extension(url)),
+        "url": file,
+    }))
+}
+
+/// The `Create` activity of a status, or the `Announce` of a boost.
+fn activity(
+    status: &Value,
+    actor: &str,
+    uris: &HashMap<&str, &str>,
+    store: Option<&MediaStore>,
+    dir: &Path,
+) -> Result<Value> {
+    let (to, cc) = addressing(status, actor);
+    let published = text(status, "created_at");
+    if let Some(reblog) = status.get("reblog").filter(|reblog| !reblog.is_null()) {
+        return Ok(json!({
+            "id": text(status, "uri"),
+            "type": "Announce",
+            "actor": actor,
+            "published": published,
+            "to": to,
+            "cc": cc,
+            "object": text(reblog, "uri"),
+        }));
+    }
+    let uri = text(status, "uri");
+    let in_reply_to = status
+        .get("in_reply_to_id")
+        .and_then(Value::as_str)
+        .and_then(|id| uris.get(id));
+    let spoiler = text(status, "spoiler_text");
+    let content = text(status, "content");
+    let content_map = status
+        .get("language")
+        .and_then(Value::as_str)
+        .map(|language| json!({ language: content }));
+    let mut tags: Vec<Value> = array(status, "mentions")
+        .map(|mention| {
+            json!({
+                "type": "Mention",
+                "href": text(mention, "url"),
+                "name": format!("@{}", text(mention, "acct")),
+            })
+        })
+        .collect();
+    tags.extend(array(status, "tags").map(|tag| {
+        json!({
+            "type": "Hashtag",
+            "href": text(tag, "url"),
+            "name": format!("#{}", text(tag, "name")),
+        })
+    }));
+    let mut attachments = Vec::new();
+    for attachment in array(status, "media_attachments") {
+        attachments.push(document(text(status, "id"), attachment, store, dir)?);
+    }
+    Ok(json!({
+        "id": format!("{uri}/activity"),
+        "type": "Create",
+        "actor": actor,
+        "published": published,
+        "to": to,
+        "cc": cc,
+        "object": {
+            "id": uri,
+            "type": "Note",
+            "summary": (!spoiler.is_empty()).then_some(spoiler),
+            "inReplyTo": in_reply_to,
+            "published": published,
+            "url": status.get("url"),
+            "attributedTo": actor,
+            "to": to,
+            "cc": cc,
+            "sensitive": status.get("sensitive").and_then(Value::as_bool) == Some(true),
+            "content": content,
+            "contentMap": content_map,
+            "attachment": attachments,
+            "tag": tags,
+        },
+    }))
+}
+
+/// Who a status is addressed to and copied to, from its visibility, as
+/// Mastodon delivers it.
+fn addressing(status: &Value, actor: &str) -> (Vec<String>, Vec<String>) {
+    let followers = format!("{actor}/followers");
+    let mentioned = array(status, "mentions").map(|mention| text(mention, "url").to_owned());
+    match text(status, "visibility") {
+        "public" => (
+            vec![PUBLIC.to_owned()],
+            [followers].into_iter().chain(mentioned).collect(),
+        ),
+        "unlisted" => (
+            vec![followers],
+            [PUBLIC.to_owned()].into_iter().chain(mentioned).collect(),
+        ),
+        "private" => (vec![followers], mentioned.collect()),
+        _ => (mentioned.collect(), Vec::new()),
+    }
+}
+
+/// The `Document` of an attachment, copying its file from the media of the
+/// archive to where an export keeps it.
+fn document(
+    status_id: &str,
+    attachment: &Value,
+    store: Option<&MediaStore>,
+    dir: &Path,
+) -> Result<Value> {
+    let id = text(attachment, "id");
+    let url = text(attachment, "url");
+    let path = export_path(id, url);
+    if let Some(stored) = store.and_then(|store| store.file(status_id, id)) {
+        let target = dir.join(&path);
+        if !target.exists() {
+            if let Some(folder) = target.parent() {
+                fs::create_dir_all(folder).with_context(|| format!("cannot create {folder:?}"))?;
+            }
+            fs::copy(&stored, &target).with_context(|| format!("cannot write file {target:?}"))?;
+        }
+    }
+    let original = attachment.pointer("/meta/original");
+    Ok(json!({
+        "type": "Document",
+        "mediaType": media_type(extension(url)),
+        "url": format!("/{path}"),
+        "name": attachment.get("description"),
+        "blurhash": attachment.get("blurhash"),
+        "width": original.and_then(|original| original.get("width")),
+        "height": original.and_then(|original| original.get("height")),
+    }))
+}
+
+/// Where an export keeps an attachment, which is the path of its URL from
+/// `media_attachments/` on.
+fn export_path(id: &str, url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.find("media_attachments/").map_or_else(
+        || format!("media_attachments/files/{id}.{}", extension(url)),
+        |start| path[start..].to_owned(),
+    )
+}
+
+fn media_type(extension: &str) -> &'static str {
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "ogg" | "oga" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
+
+fn text<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let text = fs::read_to_string(path).with_context(|| format!("cannot read file {path:?}"))?;
+    serde_json::from_str(&text).with_context(|| format!("cannot parse {path:?}"))
+}
+
+/// Reads an `.ndjson` part, which is empty if it wasn't archived.
+fn read_lines(path: &Path) -> Result<Vec<Value>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+    };
+    text.lines()
+        .map(|line| serde_json::from_str(line).with_context(|| format!("cannot parse {path:?}")))
+        .collect()
+}
+
+fn write_json(path: &Path, value: &Value) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    fs::write(path, json).with_context(|| format!("cannot write file {path:?}"))
+}
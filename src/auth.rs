@@ -0,0 +1,277 @@
+//! Registering the app with an instance and storing the resulting credentials.
+//!
+//! Credentials are saved in the config folder as `credentials.toml`, or as
+//! `credentials-<profile>.toml` for named profiles. The scopes that the login
+//! was granted are saved next to them, in `credentials.scopes`, so that
+//! commands can be checked before anything is sent. Logins saved before the
+//! scopes were recorded aren't checked.
+
+use std::collections::BTreeSet;
+use std::fs::{self, create_dir_all};
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::helpers::toml;
+use mastodon_async::prelude::Account;
+use mastodon_async::registration::Registered;
+use mastodon_async::{scopes::Scopes, Registration};
+use mastodon_async::{Data, Mastodon};
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use crate::config::config_folder;
+
+/// The scopes that `auth login` asks for by default, the same as the first
+/// login. Posting, uploading media and saving markers all need write scopes.
+const DEFAULT_SCOPES: &str = "read write";
+
+#[derive(Debug, Args)]
+pub struct AuthArgs {
+    #[command(subcommand)]
+    command: AuthCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum AuthCommand {
+    /// Log in again, replacing the saved credentials
+    Login {
+        /// The OAuth scopes to ask for, separated by spaces, e.g.
+        /// `"read write:statuses"`
+        #[arg(long, default_value = DEFAULT_SCOPES, value_parser = parse_scopes)]
+        scopes: Scopes,
+
+        /// Save the login as a named profile instead, e.g. for approving
+        /// queued posts with your own account
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+}
+
+fn parse_scopes(text: &str) -> Result<Scopes, String> {
+    text.parse().map_err(|err| format!("{err}"))
+}
+
+pub async fn run(args: AuthArgs) -> Result<()> {
+    match args.command {
+        AuthCommand::Login { scopes, profile } => {
+            let mastodon = register_and_authenticate(profile.as_deref(), scopes).await?;
+            let account = verify_credentials(&mastodon).await?;
+            println!("logged in as @{}", account.acct);
+            Ok(())
+        }
+    }
+}
+
+/// Loads the saved credentials, registering the app and authenticating
+/// interactively if there aren't any yet.
+#[instrument(err)]
+pub async fn login(profile: Option<&str>) -> Result<Mastodon> {
+    match load_credentials(profile) {
+        Ok(data) => Ok(Mastodon::from(data)),
+        Err(reason) => {
+            info!(%reason, "No credentials found. This is fine if you're running this for the first time.");
+            register_and_authenticate(profile, Scopes::read_all() | Scopes::write_all()).await
+        }
+    }
+}
+
+async fn register_and_authenticate(profile: Option<&str>, scopes: Scopes) -> Result<Mastodon> {
+    let server_name = get_server_name()?;
+    let registration = register(server_name, scopes.clone()).await?;
+    let (mastodon, granted) = authenticate(registration).await?;
+    save_credentials(&mastodon, profile)?;
+    // the instance says which scopes it granted, which can be fewer than were
+    // asked for; old instances don't, so assume that they granted them all
+    let granted = granted
+        .filter(|granted| !granted.trim().is_empty())
+        .unwrap_or_else(|| scopes.to_string());
+    save_scopes(&granted, profile)?;
+    Ok(mastodon)
+}
+
+#[instrument(err, ret)]
+fn credentials_path(profile: Option<&str>) -> Result<PathBuf> {
+    let file_name = profile.map_or_else(
+        || "credentials.toml".to_owned(),
+        |profile| format!("credentials-{profile}.toml"),
+    );
+    Ok(config_folder()?.join(file_name))
+}
+
+fn scopes_path(profile: Option<&str>) -> Result<PathBuf> {
+    Ok(credentials_path(profile)?.with_extension("scopes"))
+}
+
+#[instrument(err)]
+fn save_scopes(scopes: &str, profile: Option<&str>) -> Result<()> {
+    let path = scopes_path(profile)?;
+    fs::write(&path, scopes).with_context(|| format!("cannot save file {path:?}"))
+}
+
+/// The scopes that the saved login was granted, if they were recorded.
+#[instrument(err)]
+fn granted_scopes(profile: Option<&str>) -> Result<Option<BTreeSet<String>>> {
+    let path = scopes_path(profile)?;
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(Some(text.split_whitespace().map(str::to_owned).collect())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("cannot read file {path:?}")),
+    }
+}
+
+/// Checks that the saved login was granted the scopes that a command needs,
+/// so that a missing scope is reported before anything is sent rather than as
+/// a 403 from the server halfway through.
+pub fn check_scopes(profile: Option<&str>, required: &[&str]) -> Result<()> {
+    let Some(granted) = granted_scopes(profile)? else {
+        return Ok(());
+    };
+    // `write` grants every `write:...` scope
+    let allows = |scope: &str| {
+        granted.contains(scope)
+            || scope
+                .split_once(':')
+                .map_or(false, |(group, _)| granted.contains(group))
+    };
+    let missing: Vec<&str> = required
+        .iter()
+        .copied()
+        .filter(|scope| !allows(scope))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let mut scopes: Vec<&str> = granted.iter().map(String::as_str).collect();
+    scopes.extend(&missing);
+    bail!(
+        "this needs the {} scope, which the saved login wasn't granted; re-run `auth login --scopes \"{}\"`",
+        missing.join(" and "),
+        scopes.join(" ")
+    )
+}
+
+#[instrument(err)]
+pub fn load_credentials(profile: Option<&str>) -> Result<Data> {
+    let path = credentials_path(profile)?;
+    let data = toml::from_file(&path).with_context(|| format!("cannot load file {path:?}"))?;
+    Ok(data)
+}
+
+#[instrument(skip(client), err)]
+fn save_credentials(client: &Mastodon, profile: Option<&str>) -> Result<()> {
+    let folder = config_folder()?;
+    create_dir_all(folder).context("Can't create config folder")?;
+    let path = credentials_path(profile)?;
+    toml::to_file(&client.data, &path).with_context(|| format!("cannot save file {path:?}"))?;
+    Ok(())
+}
+
+#[instrument(err, ret)]
+fn get_server_name() -> Result<String> {
+    let mut stdout = io::stdout().lock();
+    let mut stdin = io::stdin().lock();
+
+    writeln!(&mut stdout, "Enter server name:").context("failed to write to stdout")?;
+    stdout.flush().context("failed to flush stdout")?;
+
+    let mut input = String::new();
+    stdin
+        .read_line(&mut input)
+        .context("failed to read input")?;
+
+    Ok(input.trim().to_owned())
+}
+
+#[instrument(err)]
+async fn register(server_name: String, scopes: Scopes) -> Result<Registered> {
+    let registered = Registration::new(server_name)
+        .client_name("joshka-mastodon-async")
+        .redirect_uris("urn:ietf:wg:oauth:2.0:oob")
+        .scopes(scopes)
+        .website("https://github.com/joshka/mastodon-async")
+        .build()
+        .await
+        .context("Couldn't register app")?;
+    let (base, client_id, _client_secret, _redirect, scopes, _force_login) =
+        registered.clone().into_parts();
+    info!(base, client_id, %scopes, "registration complete");
+    Ok(registered)
+}
+
+/// The response to exchanging an authorization code for a token.
+#[derive(Debug, Deserialize)]
+struct Token {
+    access_token: String,
+    /// The scopes that the token was granted, separated by spaces.
+    scope: Option<String>,
+}
+
+/// Has the user authorize the app in the browser, and exchanges the code they
+/// paste back for a token, returning the client and the scopes it was
+/// granted. This doesn't use mastodon-async's helper, as it drops the scopes.
+#[instrument(skip_all, err)]
+async fn authenticate(registration: Registered) -> Result<(Mastodon, Option<String>)> {
+    let url = registration
+        .authorize_url()
+        .context("Couldn't get authorize URL")?;
+    webbrowser::open(&url).context("opening browser")?;
+    let code = get_authorization_code(&url)?;
+    let (base, client_id, client_secret, redirect, _scopes, _force_login) =
+        registration.into_parts();
+    let token: Token = reqwest::Client::new()
+        .post(format!("{}/oauth/token", base.trim_end_matches('/')))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+            ("redirect_uri", redirect.as_str()),
+            ("code", code.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .context("Couldn't authenticate")?
+        .json()
+        .await
+        .context("Couldn't parse the token")?;
+    info!(scope = token.scope, "authentication succeeded");
+    let data = Data {
+        base: base.into(),
+        client_id: client_id.into(),
+        client_secret: client_secret.into(),
+        redirect: redirect.into(),
+        token: token.access_token.into(),
+    };
+    Ok((Mastodon::from(data), token.scope))
+}
+
+#[instrument(skip_all, err)]
+fn get_authorization_code(url: &str) -> Result<String> {
+    let mut stdout = io::stdout().lock();
+    let mut stdin = io::stdin().lock();
+
+    writeln!(&mut stdout, "Click this link to authorize: {url}")
+        .context("failed to write to stdout")?;
+    write!(&mut stdout, "Paste the returned authorization code: ")
+        .context("failed to write to stdout")?;
+    stdout.flush().context("failed to flush stdout")?;
+
+    let mut input = String::new();
+    stdin
+        .read_line(&mut input)
+        .context("failed to read input")?;
+
+    Ok(input.trim().to_owned())
+}
+
+#[instrument(skip_all, err)]
+pub async fn verify_credentials(client: &Mastodon) -> Result<Account> {
+    let account = client
+        .verify_credentials()
+        .await
+        .context("Couldn't get account")?;
+    info!(acct = account.acct,  id = %account.id, name = account.display_name, "verified credentials");
+    Ok(account)
+}
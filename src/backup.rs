@@ -0,0 +1,230 @@
+//! Backing up bookmarks and favourites to a file, and restoring them on another
+//! account, e.g. after moving to another instance.
+//!
+//! A backup lists the URI of each status, which is the same on every instance,
+//! most recently bookmarked or favourited first. Restoring resolves each one on
+//! the target account's instance, which fetches it if it hasn't seen it yet,
+//! and goes from the oldest to the newest so that the order is kept. Statuses
+//! that are already bookmarked or favourited are skipped, so a restore that
+//! failed partway can simply be run again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{info, instrument, warn};
+
+use crate::client::Client;
+use crate::duration::parse_duration;
+use crate::import::with_rate_limit_retries;
+use crate::mark::{self, Kind};
+use crate::resolve::resolve_status;
+use crate::timestamp::format_timestamp;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct BackupArgs {
+    #[command(subcommand)]
+    command: BackupCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum BackupCommand {
+    /// Back up your bookmarks
+    Bookmarks(OutArgs),
+    /// Back up your favourites
+    Favs(OutArgs),
+}
+
+#[derive(Debug, Args)]
+struct OutArgs {
+    /// The file to write [default: stdout]
+    #[arg(long, value_name = "FILE")]
+    out: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    #[command(subcommand)]
+    command: RestoreCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum RestoreCommand {
+    /// Bookmark the statuses in a backup of bookmarks
+    Bookmarks(InArgs),
+    /// Favourite the statuses in a backup of favourites
+    Favs(InArgs),
+}
+
+#[derive(Debug, Args)]
+struct InArgs {
+    /// The backup to restore
+    #[arg(long = "in", value_name = "FILE")]
+    input: PathBuf,
+
+    /// How long to pause between statuses
+    #[arg(long, value_parser = parse_duration, default_value = "2s")]
+    interval: Duration,
+}
+
+/// The file that a backup is written to.
+#[derive(Debug, Deserialize, Serialize)]
+struct Backup {
+    kind: Kind,
+    created_at: String,
+    statuses: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Entry {
+    /// The ActivityPub id of the status, which can be resolved anywhere.
+    uri: String,
+    /// The web page of the status, for people reading the file.
+    url: Option<String>,
+    acct: String,
+}
+
+/// Whether a status is already bookmarked or favourited by the account.
+#[derive(Debug, Default, Deserialize)]
+struct Flags {
+    bookmarked: Option<bool>,
+    favourited: Option<bool>,
+}
+
+impl BackupArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            BackupCommand::Bookmarks(_) => Some(Requirement::new("bookmarks", Version(3, 1, 0))),
+            BackupCommand::Favs(_) => None,
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            BackupCommand::Bookmarks(_) => &["read:bookmarks"],
+            BackupCommand::Favs(_) => &["read:favourites"],
+        }
+    }
+}
+
+impl RestoreArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            RestoreCommand::Bookmarks(_) => Some(Requirement::new("bookmarks", Version(3, 1, 0))),
+            RestoreCommand::Favs(_) => None,
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            RestoreCommand::Bookmarks(_) => &["read:search", "write:bookmarks"],
+            RestoreCommand::Favs(_) => &["read:search", "write:favourites"],
+        }
+    }
+}
+
+pub async fn backup(client: &Client, args: BackupArgs) -> Result<()> {
+    let (kind, out) = match args.command {
+        BackupCommand::Bookmarks(args) => (Kind::Bookmarks, args.out),
+        BackupCommand::Favs(args) => (Kind::Favourites, args.out),
+    };
+    let json = serde_json::to_string_pretty(&create(client, kind).await?)?;
+    match out {
+        Some(path) => {
+            fs::write(&path, json).with_context(|| format!("cannot write file {path:?}"))?;
+            info!(?path, "backed up");
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+pub async fn restore(client: &Client, args: RestoreArgs) -> Result<()> {
+    let (kind, args) = match args.command {
+        RestoreCommand::Bookmarks(args) => (Kind::Bookmarks, args),
+        RestoreCommand::Favs(args) => (Kind::Favourites, args),
+    };
+    restore_file(client, kind, &args.input, args.interval).await
+}
+
+#[instrument(skip(client), err)]
+async fn create(client: &Client, kind: Kind) -> Result<Backup> {
+    let statuses = mark::collect(client, kind).await?;
+    info!(count = statuses.len(), "collected statuses");
+    Ok(Backup {
+        kind,
+        created_at: format_timestamp(OffsetDateTime::now_utc()),
+        statuses: statuses
+            .into_iter()
+            .map(|status| Entry {
+                uri: status.uri,
+                url: status.url,
+                acct: status.account.acct,
+            })
+            .collect(),
+    })
+}
+
+#[instrument(skip(client), err)]
+async fn restore_file(client: &Client, kind: Kind, path: &Path, interval: Duration) -> Result<()> {
+    let text = fs::read_to_string(path).with_context(|| format!("cannot read file {path:?}"))?;
+    let backup: Backup =
+        serde_json::from_str(&text).with_context(|| format!("cannot parse {path:?}"))?;
+    if backup.kind != kind {
+        bail!(
+            "{path:?} is a backup of {}s, not {}s",
+            backup.kind.action(),
+            kind.action()
+        );
+    }
+    let (mut done, mut skipped, mut failed) = (0, 0, Vec::new());
+    // the backup is newest first, so restore from the end to keep the order
+    for (index, entry) in backup.statuses.iter().rev().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(interval).await;
+        }
+        match with_rate_limit_retries(|| restore_entry(client, kind, entry)).await {
+            Ok(true) => done += 1,
+            Ok(false) => skipped += 1,
+            Err(err) => {
+                warn!(?err, uri = entry.uri, "couldn't restore status");
+                failed.push((entry, err));
+            }
+        }
+    }
+    let action = kind.done();
+    println!(
+        "{action} {done}, skipped {skipped} already {action}, failed {}",
+        failed.len()
+    );
+    for (entry, err) in &failed {
+        println!("  {}: {err:#}", entry.url.as_deref().unwrap_or(&entry.uri));
+    }
+    Ok(())
+}
+
+/// Bookmarks or favourites a status from a backup, returning whether it had
+/// to.
+async fn restore_entry(client: &Client, kind: Kind, entry: &Entry) -> Result<bool> {
+    let status = resolve_status(client, &entry.uri).await?;
+    let flags: Flags = serde_json::to_value(&status)
+        .and_then(serde_json::from_value)
+        .unwrap_or_default();
+    let already = match kind {
+        Kind::Bookmarks => flags.bookmarked,
+        Kind::Favourites => flags.favourited,
+    };
+    if already == Some(true) {
+        return Ok(false);
+    }
+    mark::mark(client, kind, &status.id.to_string())
+        .await
+        .with_context(|| format!("Couldn't restore {}", entry.uri))?;
+    info!(uri = entry.uri, "restored");
+    Ok(true)
+}
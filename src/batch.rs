@@ -0,0 +1,91 @@
+//! Running many commands within one process and one authenticated session.
+//!
+//! A batch file contains one command per line, written the same way as on the
+//! command line but without the binary name. Blank lines and lines starting
+//! with `#` are ignored. Global options such as `--output` apply to the whole
+//! batch and go before `batch`, not on a line.
+//!
+//! ```text
+//! # what did I miss?
+//! timeline home --since-last-read
+//! ```
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches};
+use tracing::{error, info, instrument};
+
+use crate::cli::Cli;
+use crate::client::Client;
+use crate::config::{split_words, Config};
+
+#[derive(Debug, Args)]
+pub struct BatchArgs {
+    /// The file containing the commands
+    file: PathBuf,
+
+    /// Stop at the first command that fails instead of running the rest
+    #[arg(long)]
+    stop_on_error: bool,
+}
+
+#[instrument(skip(client, config), err)]
+pub async fn run(client: &Client, config: &Config, args: BatchArgs) -> Result<()> {
+    let path = &args.file;
+    let script = fs::read_to_string(path).with_context(|| format!("cannot read file {path:?}"))?;
+
+    let mut succeeded = 0;
+    let mut failures = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        info!(line_number, line, "running");
+        match run_line(client, config, line).await {
+            Ok(()) => succeeded += 1,
+            Err(err) => {
+                error!(line_number, line, ?err, "command failed");
+                failures.push((line_number, line, err));
+                if args.stop_on_error {
+                    break;
+                }
+            }
+        }
+    }
+
+    println!("{succeeded} succeeded, {} failed", failures.len());
+    for (line_number, line, err) in &failures {
+        println!("  line {line_number}: {line}: {err:#}");
+    }
+    if !failures.is_empty() {
+        bail!("{} command(s) in {path:?} failed", failures.len());
+    }
+    Ok(())
+}
+
+async fn run_line(client: &Client, config: &Config, line: &str) -> Result<()> {
+    let args = std::iter::once(OsString::from(env!("CARGO_PKG_NAME")))
+        .chain(split_words(line).into_iter().map(OsString::from));
+    let matches = Cli::command().try_get_matches_from(config.expand_aliases(args))?;
+    if let Some(option) = global_option(&matches) {
+        bail!("--{option} applies to the whole batch, put it before `batch` instead");
+    }
+    let cli = Cli::from_arg_matches(&matches)?;
+    crate::dispatch(client, config, cli.command).await
+}
+
+/// The first global option given on a line, which would otherwise be ignored
+/// as the output and session of the batch are already set up.
+fn global_option(matches: &ArgMatches) -> Option<String> {
+    Cli::command()
+        .get_arguments()
+        .filter(|arg| arg.is_global_set())
+        .find(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .and_then(|arg| arg.get_long().map(str::to_owned))
+}
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::output::print_account;
+use crate::paging;
+use crate::resolve::resolve_account;
+
+#[derive(Debug, Args)]
+pub struct BlockArgs {
+    /// The account, e.g. `@someone@example.com` or a profile URL
+    account: String,
+}
+
+#[derive(Debug, Args)]
+pub struct BlocksArgs {
+    #[command(subcommand)]
+    command: BlocksCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum BlocksCommand {
+    /// List blocked accounts
+    List(ListArgs),
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    /// Page through every blocked account instead of showing the first page
+    #[arg(long)]
+    all: bool,
+}
+
+/// The parts of the relationship that are checked after blocking.
+#[derive(Debug, Deserialize)]
+struct Relationship {
+    blocking: bool,
+}
+
+/// Blocks an account. Requires the `write:blocks` scope.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: BlockArgs) -> Result<()> {
+    set(client, &args.account, true).await
+}
+
+/// Unblocks an account. Requires the `write:blocks` scope.
+#[instrument(skip(client), err)]
+pub async fn undo(client: &Client, args: BlockArgs) -> Result<()> {
+    set(client, &args.account, false).await
+}
+
+async fn set(client: &Client, acct_or_url: &str, block: bool) -> Result<()> {
+    let account = resolve_account(client, acct_or_url).await?;
+    let action = if block { "block" } else { "unblock" };
+    let relationship: Relationship = client
+        .post(&format!("v1/accounts/{}/{action}", account.id), &())
+        .await
+        .with_context(|| format!("Couldn't {action} {}", account.acct))?;
+    info!(
+        acct = account.acct,
+        blocking = relationship.blocking,
+        "done"
+    );
+    println!("{action}ed @{}", account.acct);
+    Ok(())
+}
+
+pub async fn list(client: &Client, args: BlocksArgs) -> Result<()> {
+    match args.command {
+        BlocksCommand::List(args) => {
+            paging::for_each_item(client, "v1/blocks", args.all, |account| {
+                print_account(&account);
+            })
+            .await
+            .context("Couldn't get blocks")
+        }
+    }
+}
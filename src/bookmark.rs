@@ -0,0 +1,49 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use tracing::instrument;
+
+use crate::client::Client;
+use crate::mark::{self, Kind};
+
+#[derive(Debug, Args)]
+pub struct BookmarkArgs {
+    /// The id or URL of the status
+    status: String,
+}
+
+#[derive(Debug, Args)]
+pub struct BookmarksArgs {
+    #[command(subcommand)]
+    command: BookmarksCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum BookmarksCommand {
+    /// List bookmarked statuses, most recently bookmarked first
+    List(ListArgs),
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    /// Page through every bookmark instead of showing the latest page
+    #[arg(long)]
+    all: bool,
+}
+
+/// Bookmarks a status. Requires the `write:bookmarks` scope.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: BookmarkArgs) -> Result<()> {
+    mark::set(client, Kind::Bookmarks, &args.status, false).await
+}
+
+/// Removes a bookmark. Requires the `write:bookmarks` scope.
+#[instrument(skip(client), err)]
+pub async fn undo(client: &Client, args: BookmarkArgs) -> Result<()> {
+    mark::set(client, Kind::Bookmarks, &args.status, true).await
+}
+
+pub async fn list(client: &Client, args: BookmarksArgs) -> Result<()> {
+    match args.command {
+        BookmarksCommand::List(args) => mark::list(client, Kind::Bookmarks, args.all).await,
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use mastodon_async::prelude::Status;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::resolve::resolve_status;
+
+#[derive(Debug, Args)]
+pub struct BoostArgs {
+    /// The id or URL of the status to boost
+    status: String,
+
+    /// Who can see the boost [default: public]
+    #[arg(long, value_enum)]
+    visibility: Option<BoostVisibility>,
+}
+
+#[derive(Debug, Args)]
+pub struct UnboostArgs {
+    /// The id or URL of the status to stop boosting
+    status: String,
+}
+
+/// Boosts can't be direct, so this is a subset of the post visibilities.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BoostVisibility {
+    /// Visible to everyone, shown in public timelines
+    Public,
+    /// Visible to everyone, but not shown in public timelines
+    Unlisted,
+    /// Visible to followers only
+    Followers,
+}
+
+impl BoostVisibility {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Unlisted => "unlisted",
+            Self::Followers => "private",
+        }
+    }
+}
+
+/// Boosts a status. Requires the `write:statuses` scope.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: BoostArgs) -> Result<()> {
+    let status = resolve_status(client, &args.status).await?;
+    let form: Vec<_> = args
+        .visibility
+        .map(|visibility| ("visibility", visibility.as_str()))
+        .into_iter()
+        .collect();
+    let path = format!("v1/statuses/{}/reblog", status.id);
+    let _: Status = client
+        .post(&path, &form)
+        .await
+        .with_context(|| format!("Couldn't boost status {}", status.id))?;
+    info!(id = %status.id, "boosted status");
+    println!("{}", status.url.as_deref().unwrap_or(&status.uri));
+    Ok(())
+}
+
+/// Undoes a boost. Requires the `write:statuses` scope.
+#[instrument(skip(client), err)]
+pub async fn undo(client: &Client, args: UnboostArgs) -> Result<()> {
+    let status = resolve_status(client, &args.status).await?;
+    let path = format!("v1/statuses/{}/unreblog", status.id);
+    let _: Status = client
+        .post(&path, &())
+        .await
+        .with_context(|| format!("Couldn't unboost status {}", status.id))?;
+    info!(id = %status.id, "unboosted status");
+    println!("{}", status.url.as_deref().unwrap_or(&status.uri));
+    Ok(())
+}
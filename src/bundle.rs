@@ -0,0 +1,288 @@
+//! Moving the setup to another machine.
+//!
+//! A bundle is a single JSON file holding every text file in the config
+//! folder: the config with its aliases, the retention policy, and anything
+//! else kept there. The rules kept in the data folder, such as the limited
+//! domains, go with them. Credentials are only included when asked for, and
+//! are then encrypted with a passphrase using
+//! [age](https://age-encryption.org). With `--scrub`, the accounts in the
+//! notification rules are replaced by pseudonyms.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::Secret;
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument, warn};
+
+use crate::config::{config_folder, data_folder, write_private};
+use crate::domain::DomainLimits;
+use crate::notifications::NotificationRules;
+use crate::scrub;
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Save the config folder to a bundle file
+    Export {
+        /// The bundle file to write
+        #[arg(long, value_name = "FILE")]
+        bundle: PathBuf,
+
+        /// Also include the credentials, encrypted with a passphrase
+        #[arg(long)]
+        credentials: bool,
+    },
+    /// Restore the config folder from a bundle file
+    Import {
+        /// The bundle file to read
+        bundle: PathBuf,
+
+        /// Replace files that already exist
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+const FORMAT: &str = "spike-mastodon-bundle";
+const VERSION: u32 = 1;
+
+/// The files in the data folder that are part of the setup rather than state.
+const DATA_FILES: [&str; 1] = [DomainLimits::FILE_NAME];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Bundle {
+    format: String,
+    version: u32,
+    /// The contents of each file, by its path relative to the config folder.
+    files: BTreeMap<String, String>,
+    /// The contents of the [`DATA_FILES`] that exist, by their name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    data_files: BTreeMap<String, String>,
+    /// The credential files in the same form, as JSON encrypted with age.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credentials: Option<String>,
+}
+
+pub fn run(args: ConfigArgs) -> Result<()> {
+    match args.command {
+        ConfigCommand::Export {
+            bundle,
+            credentials,
+        } => export(&bundle, credentials),
+        ConfigCommand::Import { bundle, force } => import(&bundle, force),
+    }
+}
+
+/// Whether the file is one of the credential files written by [`crate::auth`].
+fn is_credentials(name: &str) -> bool {
+    // the granted scopes go with the credentials they belong to
+    name.starts_with("credentials")
+        && (name.ends_with(".toml") || name.ends_with(".scopes"))
+        && !name.contains('/')
+}
+
+#[instrument(err)]
+fn export(path: &Path, with_credentials: bool) -> Result<()> {
+    let folder = config_folder()?;
+    let mut files = BTreeMap::new();
+    read_folder(&folder, &folder, &mut files)?;
+    let (credentials, mut files): (BTreeMap<_, _>, BTreeMap<_, _>) = files
+        .into_iter()
+        .partition(|(name, _)| is_credentials(name));
+    if scrub::is_enabled() {
+        if let Some(rules) = files.get_mut(NotificationRules::FILE_NAME) {
+            *rules = scrub_rules(rules)?;
+        }
+    }
+    let credentials = if with_credentials {
+        ensure!(
+            !credentials.is_empty(),
+            "there are no credentials to export"
+        );
+        let passphrase = rpassword::prompt_password("Passphrase for the credentials: ")?;
+        let confirmation = rpassword::prompt_password("Passphrase again: ")?;
+        ensure!(passphrase == confirmation, "the passphrases don't match");
+        ensure!(!passphrase.is_empty(), "the passphrase is empty");
+        Some(encrypt(&serde_json::to_vec(&credentials)?, passphrase)?)
+    } else {
+        None
+    };
+    let data_files = read_data_files(&data_folder()?)?;
+    for name in files.keys().chain(data_files.keys()) {
+        info!(name, "exporting");
+    }
+    let bundle = Bundle {
+        format: FORMAT.to_owned(),
+        version: VERSION,
+        files,
+        data_files,
+        credentials,
+    };
+    let json = serde_json::to_string_pretty(&bundle)?;
+    fs::write(path, json).with_context(|| format!("cannot write file {path:?}"))
+}
+
+/// The notification rules with the accounts they hide replaced by pseudonyms.
+fn scrub_rules(text: &str) -> Result<String> {
+    let mut rules: toml::Table =
+        toml::from_str(text).context("cannot parse the notification rules")?;
+    if let Some(toml::Value::Array(accounts)) = rules.get_mut("accounts") {
+        for account in accounts {
+            if let toml::Value::String(acct) = account {
+                scrub::with(|scrubber| *acct = scrubber.pseudonym(acct));
+            }
+        }
+    }
+    Ok(toml::to_string(&rules)?)
+}
+
+/// Reads every text file below the folder, recursing into subfolders.
+fn read_folder(root: &Path, folder: &Path, files: &mut BTreeMap<String, String>) -> Result<()> {
+    let entries = fs::read_dir(folder).with_context(|| format!("cannot read {folder:?}"))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            read_folder(root, &path, files)?;
+            continue;
+        }
+        let name = path
+            .strip_prefix(root)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let bytes = fs::read(&path).with_context(|| format!("cannot read file {path:?}"))?;
+        match String::from_utf8(bytes) {
+            Ok(text) => {
+                files.insert(name, text);
+            }
+            Err(_) => warn!(?path, "skipping a file that isn't text"),
+        }
+    }
+    Ok(())
+}
+
+/// Reads the [`DATA_FILES`] that exist in the data folder.
+fn read_data_files(folder: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for name in DATA_FILES {
+        let path = folder.join(name);
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                files.insert(name.to_owned(), text);
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+        }
+    }
+    Ok(files)
+}
+
+#[instrument(err)]
+fn import(path: &Path, force: bool) -> Result<()> {
+    let json = fs::read_to_string(path).with_context(|| format!("cannot read file {path:?}"))?;
+    let bundle: Bundle =
+        serde_json::from_str(&json).with_context(|| format!("cannot parse file {path:?}"))?;
+    ensure!(bundle.format == FORMAT, "{path:?} is not a bundle");
+    ensure!(
+        bundle.version <= VERSION,
+        "{path:?} was made by a newer version (bundle version {})",
+        bundle.version
+    );
+    let config_folder = config_folder()?;
+    let data_folder = data_folder()?;
+    // the target of each file, its contents and whether only the owner may
+    // read it
+    let mut writes = Vec::new();
+    for (name, text) in bundle.files {
+        writes.push((target_path(&config_folder, &name)?, text, false));
+    }
+    for (name, text) in bundle.data_files {
+        ensure!(
+            DATA_FILES.contains(&name.as_str()),
+            "the bundle contains an unknown data file {name:?}"
+        );
+        writes.push((data_folder.join(name), text, false));
+    }
+    if let Some(credentials) = bundle.credentials {
+        let passphrase = rpassword::prompt_password("Passphrase for the credentials: ")?;
+        let credentials: BTreeMap<String, String> =
+            serde_json::from_slice(&decrypt(&credentials, passphrase)?)?;
+        for (name, text) in credentials {
+            writes.push((target_path(&config_folder, &name)?, text, true));
+        }
+    }
+
+    if !force {
+        let existing: Vec<_> = writes
+            .iter()
+            .map(|(target, _, _)| target)
+            .filter(|target| target.exists())
+            .collect();
+        ensure!(
+            existing.is_empty(),
+            "these files already exist, use --force to replace them: {existing:?}"
+        );
+    }
+    for (target, text, private) in &writes {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("cannot create {parent:?}"))?;
+        }
+        if *private {
+            write_private(target, text)?;
+        } else {
+            fs::write(target, text).with_context(|| format!("cannot write file {target:?}"))?;
+        }
+        println!("{}", target.display());
+    }
+    Ok(())
+}
+
+/// The path to import a file to, refusing names that would end up outside the
+/// config folder.
+fn target_path(folder: &Path, name: &str) -> Result<PathBuf> {
+    let safe = !name.is_empty()
+        && name
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != ".." && !part.contains('\\'));
+    if !safe || Path::new(name).is_absolute() {
+        bail!("the bundle contains an unsafe file name {name:?}");
+    }
+    Ok(folder.join(name))
+}
+
+fn encrypt(plaintext: &[u8], passphrase: String) -> Result<String> {
+    let encryptor = age::Encryptor::with_user_passphrase(Secret::new(passphrase));
+    let mut encrypted = Vec::new();
+    let armor = ArmoredWriter::wrap_output(&mut encrypted, Format::AsciiArmor)?;
+    let mut writer = encryptor.wrap_output(armor)?;
+    writer.write_all(plaintext)?;
+    writer.finish()?.finish()?;
+    Ok(String::from_utf8(encrypted)?)
+}
+
+fn decrypt(encrypted: &str, passphrase: String) -> Result<Vec<u8>> {
+    let decryptor = match age::Decryptor::new(ArmoredReader::new(encrypted.as_bytes()))? {
+        age::Decryptor::Passphrase(decryptor) => decryptor,
+        age::Decryptor::Recipients(_) => {
+            bail!("the credentials aren't encrypted with a passphrase")
+        }
+    };
+    let mut reader = decryptor
+        .decrypt(&Secret::new(passphrase), None)
+        .context("Couldn't decrypt the credentials, is the passphrase right?")?;
+    let mut plaintext = Vec::new();
+    reader.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
@@ -0,0 +1,323 @@
+use clap::{Parser, Subcommand};
+
+use crate::account::AccountArgs;
+use crate::admin::AdminArgs;
+use crate::announcements::AnnouncementsArgs;
+use crate::archive::ArchiveArgs;
+use crate::auth::AuthArgs;
+use crate::backup::{BackupArgs, RestoreArgs};
+use crate::batch::BatchArgs;
+use crate::block::{BlockArgs, BlocksArgs};
+use crate::bookmark::{BookmarkArgs, BookmarksArgs};
+use crate::boost::{BoostArgs, UnboostArgs};
+use crate::bundle::ConfigArgs;
+use crate::domain::DomainArgs;
+use crate::draft::DraftArgs;
+use crate::emoji::EmojiArgs;
+use crate::export::ExportArgs;
+use crate::favourite::{FavArgs, FavsArgs};
+use crate::follow::{FollowArgs, UnfollowArgs};
+use crate::follow_requests::FollowRequestsArgs;
+use crate::import::ImportArgs;
+use crate::instance::InstanceArgs;
+use crate::lists::ListsArgs;
+use crate::mail::MailArgs;
+use crate::mute::{MuteArgs, MutesArgs, UnmuteArgs};
+use crate::notifications::NotificationsArgs;
+use crate::output::{ColorChoice, EmojiMode, OutputFormat, TimeFormat};
+use crate::plugin::PluginArgs;
+use crate::post::PostArgs;
+use crate::profile::ProfileArgs;
+use crate::push::PushArgs;
+use crate::queue::QueueArgs;
+use crate::relationships::RelationshipsArgs;
+use crate::reply::ReplyArgs;
+use crate::report::ReportArgs;
+use crate::resolve::ResolveArgs;
+use crate::retention::RetentionArgs;
+use crate::schedule::ScheduleArgs;
+use crate::search::SearchArgs;
+use crate::selftest::SelftestArgs;
+use crate::serve::ServeArgs;
+use crate::status::StatusArgs;
+use crate::stream::StreamArgs;
+use crate::suggestions::SuggestionsArgs;
+use crate::sync::SyncArgs;
+use crate::tags::TagsArgs;
+use crate::timeline::TimelineArgs;
+use crate::trends::TrendsArgs;
+use crate::version::{Requirement, Version};
+
+/// A small CLI to exercise the mastodon-async library.
+///
+/// Running without a subcommand exercises the paging of the home timeline.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+
+    /// Use less data, for metered or slow connections: smaller pages, no
+    /// downloads that aren't needed, and instance information and custom emoji
+    /// from the cache when it is less than a day old
+    #[arg(long, global = true)]
+    pub(crate) low_bandwidth: bool,
+
+    /// Read timelines, threads and accounts from the local storage instead of
+    /// the instance, as they were when last fetched
+    #[arg(long, global = true)]
+    pub(crate) offline: bool,
+
+    /// Replace everyone else's handles with pseudonyms and leave out links and
+    /// media in what is printed, exported, archived or saved to the storage,
+    /// for sharing it. The pseudonyms are only consistent within a run.
+    #[arg(long, global = true)]
+    pub(crate) scrub: bool,
+
+    /// How to print statuses and other entities
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub(crate) output: OutputFormat,
+
+    /// The columns of `--output csv`, e.g. `id,acct,favourites_count`
+    #[arg(
+        long,
+        global = true,
+        value_delimiter = ',',
+        default_value = "id,created_at,acct,content,url"
+    )]
+    pub(crate) columns: Vec<String>,
+
+    /// Print each status, account or notification with a template instead,
+    /// e.g. `'{{ acct }}: {{ content_text }} ({{ url }})'`
+    #[arg(long, global = true)]
+    pub(crate) template: Option<String>,
+
+    /// When to color the output
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub(crate) color: ColorChoice,
+
+    /// How to print times, `iso` unless the config file sets `time`
+    #[arg(long, global = true, value_enum)]
+    pub(crate) time: Option<TimeFormat>,
+
+    /// What to print custom emoji as, instead of the `emoji` section of the
+    /// config file for the output format
+    #[arg(long, global = true, value_enum)]
+    pub(crate) emoji: Option<EmojiMode>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Look at other accounts
+    Account(AccountArgs),
+    /// Moderate the instance, with an admin account
+    Admin(AdminArgs),
+    /// Read the announcements of the instance staff
+    Announcements(AnnouncementsArgs),
+    /// Back up your account to a folder
+    Archive(ArchiveArgs),
+    /// Log in again, e.g. with different scopes
+    Auth(AuthArgs),
+    /// Back up bookmarks or favourites, for restoring on another account
+    Backup(BackupArgs),
+    /// Run the commands in a file, one per line, with a single session
+    Batch(BatchArgs),
+    /// Block an account
+    Block(BlockArgs),
+    /// Manage blocked accounts
+    Blocks(BlocksArgs),
+    /// Bookmark a status to read later
+    Bookmark(BookmarkArgs),
+    /// Manage bookmarked statuses
+    Bookmarks(BookmarksArgs),
+    /// Boost a status
+    Boost(BoostArgs),
+    /// Move the configuration to another machine
+    Config(ConfigArgs),
+    /// Block domains on the instance, or limit them in this tool
+    Domain(DomainArgs),
+    /// Manage statuses saved locally to post later
+    Draft(DraftArgs),
+    /// List and download the custom emoji of the instance
+    Emoji(EmojiArgs),
+    /// Export follows, blocks and mutes for importing on another instance
+    Export(ExportArgs),
+    /// Favourite a status
+    Fav(FavArgs),
+    /// Manage favourited statuses
+    Favs(FavsArgs),
+    /// Follow an account
+    Follow(FollowArgs),
+    /// Answer requests to follow your locked account
+    FollowRequests(FollowRequestsArgs),
+    /// Import follows, blocks and mutes exported from another instance
+    Import(ImportArgs),
+    /// See the version and limits of an instance
+    Instance(InstanceArgs),
+    /// Manage lists of accounts
+    Lists(ListsArgs),
+    /// Send Mastodon activity by email
+    Mail(MailArgs),
+    /// Mute an account
+    Mute(MuteArgs),
+    /// Manage muted accounts
+    Mutes(MutesArgs),
+    /// Read notifications
+    Notifications(NotificationsArgs),
+    /// Use WebAssembly plugins (experimental)
+    Plugin(PluginArgs),
+    /// Post a new status
+    Post(PostArgs),
+    /// Manage your profile
+    Profile(ProfileArgs),
+    /// Manage the Web Push subscription
+    Push(PushArgs),
+    /// Review posts waiting for approval
+    Queue(QueueArgs),
+    /// Compare the relationships of your own accounts
+    Relationships(RelationshipsArgs),
+    /// Reply to a status
+    Reply(ReplyArgs),
+    /// Report an account and its statuses to the moderators
+    Report(ReportArgs),
+    /// Find the local id of a status or account on another instance
+    Resolve(ResolveArgs),
+    /// Restore bookmarks or favourites from a backup, e.g. after moving
+    /// instances
+    Restore(RestoreArgs),
+    /// Remove old statuses, favourites and bookmarks according to a policy
+    Retention(RetentionArgs),
+    /// Manage statuses that are posted later
+    Schedule(ScheduleArgs),
+    /// Search for accounts, statuses and hashtags
+    Search(SearchArgs),
+    /// Check that the instance and the saved login work
+    Selftest(SelftestArgs),
+    /// Run in the background, accepting commands over a control socket
+    Serve(ServeArgs),
+    /// Manage individual statuses
+    Status(StatusArgs),
+    /// Follow new statuses and notifications as they arrive
+    Stream(StreamArgs),
+    /// See and dismiss the accounts suggested for you to follow
+    Suggestions(SuggestionsArgs),
+    /// Mirror a timeline to the local storage, fetching only what is new
+    Sync(SyncArgs),
+    /// Follow hashtags and see how much they are used
+    Tags(TagsArgs),
+    /// Read timelines
+    Timeline(TimelineArgs),
+    /// See what is trending on the instance
+    Trends(TrendsArgs),
+    /// Unblock an account
+    Unblock(BlockArgs),
+    /// Remove a bookmark
+    Unbookmark(BookmarkArgs),
+    /// Undo a boost
+    Unboost(UnboostArgs),
+    /// Undo a favourite
+    Unfav(FavArgs),
+    /// Unfollow an account
+    Unfollow(UnfollowArgs),
+    /// Unmute an account
+    Unmute(UnmuteArgs),
+}
+
+impl Command {
+    /// The Mastodon feature that the command needs, if it is newer than what
+    /// most instances run.
+    pub(crate) const fn requirement(&self) -> Option<Requirement> {
+        match self {
+            Self::Bookmark(_) | Self::Bookmarks(_) | Self::Unbookmark(_) => {
+                Some(Requirement::new("bookmarks", Version(3, 1, 0)))
+            }
+            Self::Announcements(_) => Some(Requirement::new("announcements", Version(3, 1, 0))),
+            Self::Schedule(_) => Some(Requirement::new("scheduled statuses", Version(2, 7, 0))),
+            Self::Suggestions(_) => Some(Requirement::new("suggestion sources", Version(3, 4, 0))),
+            Self::Account(args) => args.requirement(),
+            Self::Admin(args) => args.requirement(),
+            Self::Archive(args) => args.requirement(),
+            Self::Backup(args) => args.requirement(),
+            Self::Follow(args) => args.requirement(),
+            Self::Instance(args) => args.requirement(),
+            Self::Mute(args) => args.requirement(),
+            Self::Notifications(args) => args.requirement(),
+            Self::Report(args) => args.requirement(),
+            Self::Restore(args) => args.requirement(),
+            Self::Status(args) => args.requirement(),
+            Self::Tags(args) => args.requirement(),
+            Self::Timeline(args) => args.requirement(),
+            Self::Trends(args) => args.requirement(),
+            _ => None,
+        }
+    }
+
+    /// The OAuth scopes that the command needs, checked against the scopes
+    /// that the saved login was granted before anything is sent.
+    pub(crate) const fn scopes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Account(args) => args.scopes(),
+            Self::Admin(args) => args.scopes(),
+            Self::Announcements(args) => args.scopes(),
+            Self::Archive(args) => args.scopes(),
+            Self::Backup(args) => args.scopes(),
+            // logging in, batch files (checked line by line) and local files
+            Self::Auth(_) | Self::Batch(_) | Self::Config(_) => &[],
+            // the commands that take a URL or an address resolve it with a
+            // search, see `crate::resolve`
+            Self::Block(_) | Self::Unblock(_) => &["read:search", "write:blocks"],
+            Self::Blocks(_) => &["read:blocks"],
+            Self::Bookmark(_) | Self::Unbookmark(_) => &["read:search", "write:bookmarks"],
+            Self::Bookmarks(_) => &["read:bookmarks"],
+            Self::Boost(_) | Self::Reply(_) | Self::Unboost(_) => {
+                &["read:search", "write:statuses"]
+            }
+            Self::Plugin(_) | Self::Queue(_) => &["write:statuses"],
+            Self::Domain(args) => args.scopes(),
+            Self::Draft(_) | Self::Post(_) | Self::Schedule(_) => {
+                &["write:statuses", "write:media"]
+            }
+            Self::Export(_) => &["read:follows", "read:blocks", "read:mutes"],
+            Self::Fav(_) | Self::Unfav(_) => &["read:search", "write:favourites"],
+            Self::Favs(_) => &["read:favourites"],
+            Self::Follow(_) | Self::Unfollow(_) => &["read:search", "write:follows"],
+            Self::FollowRequests(_) => &["read:follows", "write:follows"],
+            Self::Import(_) => &[
+                "read:search",
+                "read:follows",
+                "write:follows",
+                "write:blocks",
+                "write:mutes",
+            ],
+            Self::Lists(args) => args.scopes(),
+            Self::Mail(_) => &["read:statuses", "read:notifications"],
+            Self::Mute(_) | Self::Unmute(_) => &["read:search", "write:mutes"],
+            Self::Mutes(_) => &["read:mutes"],
+            Self::Notifications(args) => args.scopes(),
+            Self::Profile(args) => args.scopes(),
+            Self::Push(_) => &["push"],
+            Self::Relationships(_) => &["read:search", "read:follows", "write:follows"],
+            Self::Report(_) => &["write:reports"],
+            Self::Resolve(_) | Self::Search(_) => &["read:search"],
+            Self::Restore(args) => args.scopes(),
+            Self::Retention(_) => &[
+                "read:statuses",
+                "read:favourites",
+                "read:bookmarks",
+                "write:statuses",
+                "write:favourites",
+                "write:bookmarks",
+            ],
+            Self::Selftest(args) => args.scopes(),
+            Self::Serve(_) => &["read", "write"],
+            Self::Status(args) => args.scopes(),
+            Self::Stream(_) => &["read:statuses", "read:notifications"],
+            Self::Suggestions(_) => &["read"],
+            Self::Sync(_) => &["read:statuses"],
+            Self::Tags(args) => args.scopes(),
+            Self::Timeline(args) => args.scopes(),
+            // custom emoji, instance information and trends are public
+            Self::Emoji(_) | Self::Instance(_) | Self::Trends(_) => &[],
+        }
+    }
+}
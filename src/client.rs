@@ -0,0 +1,432 @@
+use std::fmt;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use mastodon_async::Mastodon;
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{debug, instrument, warn};
+
+use crate::{metrics, storage};
+
+/// An authenticated client, created with the
+/// [`SpikeMastodonBuilder`](crate::SpikeMastodonBuilder).
+///
+/// Derefs to [`Mastodon`] for the endpoints that mastodon-async wraps, and
+/// provides raw access to the endpoints that it doesn't wrap yet.
+#[derive(Clone)]
+pub struct Client {
+    mastodon: Mastodon,
+    http: reqwest::Client,
+    /// Like `http`, but without the request timeout, which would otherwise
+    /// cut off streams that stay open for hours.
+    stream_http: reqwest::Client,
+    cache_dir: PathBuf,
+    low_bandwidth: bool,
+}
+
+/// How long a cached response is used for in low bandwidth mode.
+const CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The largest file that [`Client::download`] downloads, a little more than
+/// the largest video that Mastodon accepts by default.
+pub const MAX_DOWNLOAD_SIZE: usize = 100 * 1024 * 1024;
+
+/// The largest page size that Mastodon allows for most lists.
+const PAGE_LIMIT: &str = "40";
+
+/// The page size in low bandwidth mode, small enough to show something quickly
+/// on a slow connection.
+const LOW_BANDWIDTH_PAGE_LIMIT: &str = "10";
+
+impl Client {
+    pub(crate) const fn new(
+        mastodon: Mastodon,
+        http: reqwest::Client,
+        stream_http: reqwest::Client,
+        cache_dir: PathBuf,
+        low_bandwidth: bool,
+    ) -> Self {
+        Self {
+            mastodon,
+            http,
+            stream_http,
+            cache_dir,
+            low_bandwidth,
+        }
+    }
+
+    /// Whether to avoid downloading anything that isn't needed, such as media,
+    /// preview cards and avatars.
+    #[must_use]
+    pub const fn low_bandwidth(&self) -> bool {
+        self.low_bandwidth
+    }
+
+    /// The `limit` to request for lists that are paged through.
+    #[must_use]
+    pub const fn page_limit(&self) -> &'static str {
+        if self.low_bandwidth {
+            LOW_BANDWIDTH_PAGE_LIMIT
+        } else {
+            PAGE_LIMIT
+        }
+    }
+
+    /// The folder for cached data.
+    #[must_use]
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// The URL of an API endpoint, e.g. `v1/markers`.
+    fn url(&self, path: &str) -> String {
+        let base = self.mastodon.data.base.trim_end_matches('/');
+        format!("{base}/api/{path}")
+    }
+
+    /// Sends a GET request to an API endpoint, e.g. `v1/markers`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &(impl Serialize + ?Sized),
+    ) -> Result<T> {
+        self.send(self.http.get(self.url(path)).query(query)).await
+    }
+
+    /// Sends a GET request to an API endpoint whose response rarely changes,
+    /// such as `v2/instance`. Every response is cached, and in low bandwidth
+    /// mode a response cached in the last day is used instead of sending the
+    /// request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn get_cached<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let file = self
+            .cache_dir
+            .join("responses")
+            .join(storage::instance(&self.mastodon.data.base))
+            .join(format!("{}.json", path.replace('/', "-")));
+        if self.low_bandwidth {
+            if let Some(value) = read_fresh(&file) {
+                debug!(path, "using the cached response");
+                return serde_json::from_value(value)
+                    .with_context(|| format!("couldn't parse the cached response {file:?}"));
+            }
+        }
+        let value: serde_json::Value = self.get(path, &()).await?;
+        if let Err(err) = write_cache(&file, &value) {
+            warn!(?err, "couldn't cache the response");
+        }
+        serde_json::from_value(value)
+            .with_context(|| format!("couldn't parse response from {path}"))
+    }
+
+    /// Sends a GET request to an endpoint that is paged with the `Link` header,
+    /// returning the items and the URL of the next page, if there is one. Some
+    /// endpoints page by ids that aren't in the items, so this is the only way
+    /// to page through them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn get_page<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &(impl Serialize + ?Sized),
+    ) -> Result<(Vec<T>, Option<String>)> {
+        self.send_paged(self.http.get(self.url(path)).query(query))
+            .await
+    }
+
+    /// Gets the page after one returned by [`Self::get_page`], given its URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn get_next_page<T: DeserializeOwned>(
+        &self,
+        url: &str,
+    ) -> Result<(Vec<T>, Option<String>)> {
+        self.send_paged(self.http.get(url)).await
+    }
+
+    /// Sends a POST request with a form body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        form: &(impl Serialize + ?Sized),
+    ) -> Result<T> {
+        self.send(self.http.post(self.url(path)).form(form)).await
+    }
+
+    /// Sends a POST request with a JSON body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn post_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &(impl Serialize + ?Sized),
+    ) -> Result<T> {
+        self.send(self.http.post(self.url(path)).json(body)).await
+    }
+
+    /// Sends a PUT request with a JSON body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn put_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &(impl Serialize + ?Sized),
+    ) -> Result<T> {
+        self.send(self.http.put(self.url(path)).json(body)).await
+    }
+
+    /// Sends a PATCH request with a form body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn patch<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        form: &(impl Serialize + ?Sized),
+    ) -> Result<T> {
+        self.send(self.http.patch(self.url(path)).form(form)).await
+    }
+
+    /// Sends a PATCH request with a JSON body.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn patch_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &(impl Serialize + ?Sized),
+    ) -> Result<T> {
+        self.send(self.http.patch(self.url(path)).json(body)).await
+    }
+
+    /// Sends a PATCH request with a multipart body, for uploading files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn patch_multipart<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        form: reqwest::multipart::Form,
+    ) -> Result<T> {
+        self.send(self.http.patch(self.url(path)).multipart(form))
+            .await
+    }
+
+    /// Sends a DELETE request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    pub async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.http.delete(self.url(path))).await
+    }
+
+    /// Downloads a file that isn't part of the API, such as a media
+    /// attachment, returning its bytes and content type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the file is larger than
+    /// [`MAX_DOWNLOAD_SIZE`].
+    #[instrument(skip(self), err)]
+    pub async fn download(&self, url: &str) -> Result<(Vec<u8>, Option<String>)> {
+        let mut response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("Couldn't download {url}"))?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let too_large = || anyhow!("{url} is larger than {MAX_DOWNLOAD_SIZE} bytes");
+        let length = response.content_length().unwrap_or_default();
+        if length > MAX_DOWNLOAD_SIZE as u64 {
+            return Err(too_large());
+        }
+        // the length can be missing or wrong, so count the bytes as they come
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .with_context(|| format!("Couldn't download {url}"))?
+        {
+            if bytes.len() + chunk.len() > MAX_DOWNLOAD_SIZE {
+                return Err(too_large());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok((bytes, content_type))
+    }
+
+    /// Sends a GET request without the credentials, for the public endpoints
+    /// of other instances.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response can't be parsed.
+    #[instrument(skip(self), err)]
+    pub async fn get_public<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        metrics::API_REQUESTS.inc();
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("request to {url} failed"))?;
+        response
+            .json()
+            .await
+            .with_context(|| format!("couldn't parse response from {url}"))
+    }
+
+    /// Opens a stream of server-sent events, e.g. from the streaming API. The
+    /// events are read from the body of the returned response as they arrive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    #[instrument(skip(self), err)]
+    pub async fn open_stream(&self, url: &str) -> Result<reqwest::Response> {
+        self.stream_http
+            .get(url)
+            .bearer_auth(&self.mastodon.data.token)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("Couldn't open stream {url}"))
+    }
+
+    #[instrument(skip_all, err)]
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = self.send_request(request).await?;
+        let url = response.url().clone();
+        response
+            .json()
+            .await
+            .with_context(|| format!("couldn't parse response from {url}"))
+    }
+
+    #[instrument(skip_all, err)]
+    async fn send_paged<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<(Vec<T>, Option<String>)> {
+        let response = self.send_request(request).await?;
+        let url = response.url().clone();
+        let next = response
+            .headers()
+            .get(reqwest::header::LINK)
+            .and_then(|value| value.to_str().ok())
+            .and_then(next_link);
+        let items = response
+            .json()
+            .await
+            .with_context(|| format!("couldn't parse response from {url}"))?;
+        Ok((items, next))
+    }
+
+    async fn send_request(&self, request: RequestBuilder) -> Result<reqwest::Response> {
+        metrics::API_REQUESTS.inc();
+        let response = request
+            .bearer_auth(&self.mastodon.data.token)
+            .send()
+            .await
+            .context("request failed")?;
+        let url = response.url().clone();
+        debug!(%url, status = %response.status(), "response");
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            metrics::API_RATE_LIMITED.inc();
+        }
+        response
+            .error_for_status()
+            .with_context(|| format!("request to {url} failed"))
+    }
+}
+
+/// The URL of the next page in a `Link` header, e.g.
+/// `<https://example.com/api/v1/followed_tags?max_id=7>; rel="next"`.
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| url.trim().trim_start_matches('<').trim_end_matches('>'))
+            .map(str::to_owned)
+    })
+}
+
+/// A cached response, unless it is missing, unreadable or older than
+/// [`CACHE_MAX_AGE`].
+fn read_fresh(file: &Path) -> Option<serde_json::Value> {
+    let age = fs::metadata(file).ok()?.modified().ok()?.elapsed().ok()?;
+    if age > CACHE_MAX_AGE {
+        return None;
+    }
+    serde_json::from_slice(&fs::read(file).ok()?).ok()
+}
+
+fn write_cache(file: &Path, value: &serde_json::Value) -> Result<()> {
+    if let Some(folder) = file.parent() {
+        fs::create_dir_all(folder).with_context(|| format!("cannot create {folder:?}"))?;
+    }
+    fs::write(file, serde_json::to_vec(value)?)
+        .with_context(|| format!("cannot write file {file:?}"))
+}
+
+/// The HTTP status of a failed request made with [`Client`], if the server
+/// responded at all.
+pub fn error_status(err: &anyhow::Error) -> Option<StatusCode> {
+    err.chain()
+        .find_map(|err| err.downcast_ref::<reqwest::Error>())
+        .and_then(reqwest::Error::status)
+}
+
+impl Deref for Client {
+    type Target = Mastodon;
+
+    fn deref(&self) -> &Self::Target {
+        &self.mastodon
+    }
+}
+
+// implemented by hand so that the token never ends up in the logs
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("base", &self.mastodon.data.base)
+            .field("cache_dir", &self.cache_dir)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,91 @@
+//! Content warnings suggested from the text of a post.
+//!
+//! The config maps each content warning to the keywords that should trigger
+//! it, so nothing is suggested until some are configured:
+//!
+//! ```toml
+//! [content_warnings]
+//! "US politics" = ["election", "senate"]
+//! "eye contact" = ["selfie"]
+//! ```
+
+use std::collections::HashMap;
+
+/// Suggests a content warning for the text, naming every configured topic that
+/// one of its keywords matches. Keywords match whole words, ignoring case.
+pub fn suggest(content_warnings: &HashMap<String, Vec<String>>, text: &str) -> Option<String> {
+    let text = text.to_lowercase();
+    let mut topics: Vec<&str> = content_warnings
+        .iter()
+        .filter(|(_, keywords)| {
+            keywords
+                .iter()
+                .any(|keyword| contains_word(&text, &keyword.to_lowercase()))
+        })
+        .map(|(topic, _)| topic.as_str())
+        .collect();
+    if topics.is_empty() {
+        return None;
+    }
+    // the config is a map, so sort to make the suggestion stable
+    topics.sort_unstable();
+    Some(topics.join(", "))
+}
+
+/// Whether the text contains the word or phrase, not just as part of a longer
+/// word.
+fn contains_word(text: &str, word: &str) -> bool {
+    if word.is_empty() {
+        return false;
+    }
+    text.match_indices(word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.map_or(false, char::is_alphanumeric) && !after.map_or(false, char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content_warnings() -> HashMap<String, Vec<String>> {
+        HashMap::from([
+            ("politics".to_string(), vec!["election".to_string()]),
+            (
+                "food".to_string(),
+                vec!["Pizza".to_string(), "ice cream".to_string()],
+            ),
+        ])
+    }
+
+    #[test]
+    fn keywords_suggest_their_topic_ignoring_case() {
+        let suggestion = suggest(&content_warnings(), "PIZZA for dinner");
+        assert_eq!(suggestion.as_deref(), Some("food"));
+    }
+
+    #[test]
+    fn every_matching_topic_is_suggested_in_order() {
+        let suggestion = suggest(&content_warnings(), "ice cream after the election");
+        assert_eq!(suggestion.as_deref(), Some("food, politics"));
+    }
+
+    #[test]
+    fn keywords_only_match_whole_words() {
+        assert_eq!(
+            suggest(&content_warnings(), "pizzas and re-elections"),
+            None
+        );
+        assert_eq!(
+            suggest(&content_warnings(), "pizza!").as_deref(),
+            Some("food")
+        );
+    }
+
+    #[test]
+    fn empty_keywords_match_nothing() {
+        let content_warnings = HashMap::from([("everything".to_string(), vec![String::new()])]);
+        assert_eq!(suggest(&content_warnings, "anything at all"), None);
+    }
+}
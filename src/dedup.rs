@@ -0,0 +1,28 @@
+use std::collections::HashSet;
+
+use mastodon_async::prelude::{Status, StatusId};
+
+/// Tracks which statuses have already been emitted during this invocation.
+///
+/// Walking back and forth between pages (see `show_timeline`) can return the
+/// same status on both sides of a page boundary, so every page of items is
+/// passed through this before being emitted.
+#[derive(Debug, Default)]
+pub struct SeenStatuses {
+    ids: HashSet<StatusId>,
+}
+
+impl SeenStatuses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns only the statuses that have not been seen before, remembering
+    /// them so that later calls will skip them.
+    pub fn retain_unseen(&mut self, items: Vec<Status>) -> Vec<Status> {
+        items
+            .into_iter()
+            .filter(|item| self.ids.insert(item.id.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,225 @@
+//! Rules for whole domains.
+//!
+//! Blocking a domain is done by the instance, and hides everything from the
+//! accounts on it, removes your followers from it and stops notifications from
+//! it.
+//!
+//! Limiting a domain hides the statuses from it that are boosted into a
+//! timeline, while still showing statuses from the accounts on it that are
+//! followed directly. Mastodon doesn't let users limit domains themselves, so
+//! this is only applied to the timelines shown by this tool. The limited
+//! domains are kept in `limited-domains.txt` in the data folder, one per line.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Status;
+use serde_json::Value;
+use tracing::{debug, info, instrument};
+
+use crate::client::Client;
+use crate::config::data_folder;
+
+#[derive(Debug, Args)]
+pub struct DomainArgs {
+    #[command(subcommand)]
+    command: DomainCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum DomainCommand {
+    /// Block a domain on the instance, hiding everything from it
+    Block {
+        /// The domain, e.g. `example.com`
+        domain: String,
+    },
+    /// Unblock a domain on the instance
+    Unblock {
+        /// The domain, e.g. `example.com`
+        domain: String,
+    },
+    /// List the blocked domains
+    Blocks {
+        /// List every blocked domain instead of the first page
+        #[arg(long)]
+        all: bool,
+    },
+    /// Hide boosts of statuses from a domain in timelines
+    Limit {
+        /// The domain, e.g. `example.com`
+        domain: String,
+    },
+    /// Stop hiding boosts of statuses from a domain
+    Unlimit {
+        /// The domain, e.g. `example.com`
+        domain: String,
+    },
+    /// List the limited domains
+    Limited,
+}
+
+#[derive(Debug, Default)]
+pub struct DomainLimits {
+    domains: BTreeSet<String>,
+}
+
+impl DomainLimits {
+    /// The name of the file in the data folder.
+    pub const FILE_NAME: &'static str = "limited-domains.txt";
+
+    fn path() -> Result<PathBuf> {
+        Ok(data_folder()?.join(Self::FILE_NAME))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+        };
+        let domains = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        Ok(Self { domains })
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder).with_context(|| format!("cannot create {folder:?}"))?;
+        }
+        let mut text = String::new();
+        for domain in &self.domains {
+            text.push_str(domain);
+            text.push('\n');
+        }
+        fs::write(&path, text).with_context(|| format!("cannot write file {path:?}"))
+    }
+
+    /// Whether the status is a boost of a status from a limited domain.
+    fn hides(&self, status: &Status) -> bool {
+        let Some(boosted) = &status.reblog else {
+            return false;
+        };
+        // local accounts don't have a domain in their acct
+        boosted
+            .account
+            .acct
+            .split_once('@')
+            .map_or(false, |(_, domain)| {
+                self.domains.contains(&domain.to_lowercase())
+            })
+    }
+
+    /// Removes the boosts of statuses from limited domains.
+    pub fn retain(&self, mut statuses: Vec<Status>) -> Vec<Status> {
+        if self.domains.is_empty() {
+            return statuses;
+        }
+        let count = statuses.len();
+        statuses.retain(|status| !self.hides(status));
+        debug!(hidden = count - statuses.len(), "applied domain limits");
+        statuses
+    }
+}
+
+impl DomainArgs {
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            DomainCommand::Block { .. } | DomainCommand::Unblock { .. } => &["write:blocks"],
+            DomainCommand::Blocks { .. } => &["read:blocks"],
+            // limits are kept in a local file
+            DomainCommand::Limit { .. }
+            | DomainCommand::Unlimit { .. }
+            | DomainCommand::Limited => &[],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: DomainArgs) -> Result<()> {
+    match args.command {
+        DomainCommand::Block { domain } => block(client, &domain, true).await,
+        DomainCommand::Unblock { domain } => block(client, &domain, false).await,
+        DomainCommand::Blocks { all } => blocks(client, all).await,
+        DomainCommand::Limit { domain } => limit(&domain, true),
+        DomainCommand::Unlimit { domain } => limit(&domain, false),
+        DomainCommand::Limited => {
+            for domain in DomainLimits::load()?.domains {
+                println!("{domain}");
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The domain in lower case, checking that it isn't an address or URL.
+fn parse_domain(domain: &str) -> Result<String> {
+    let domain = domain.trim().to_lowercase();
+    ensure!(
+        !domain.is_empty() && !domain.contains(['/', '@', ' ']),
+        "{domain} is not a domain like example.com"
+    );
+    Ok(domain)
+}
+
+#[instrument(skip(client), err)]
+async fn block(client: &Client, domain: &str, blocked: bool) -> Result<()> {
+    let domain = parse_domain(domain)?;
+    let form = [("domain", domain.as_str())];
+    let _: Value = if blocked {
+        client.post("v1/domain_blocks", &form).await
+    } else {
+        // the domain is sent in the query, as DELETE requests have no form
+        client
+            .delete(&format!("v1/domain_blocks?domain={domain}"))
+            .await
+    }
+    .with_context(|| format!("Couldn't update the block of {domain}"))?;
+    info!(domain, blocked, "updated domain blocks");
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn blocks(client: &Client, all: bool) -> Result<()> {
+    let (mut domains, mut next) = client
+        .get_page::<String>("v1/domain_blocks", &[("limit", client.page_limit())])
+        .await
+        .context("Couldn't get blocked domains")?;
+    while let Some(url) = next.filter(|_| all) {
+        let (page, page_next) = client
+            .get_next_page::<String>(&url)
+            .await
+            .context("Couldn't get blocked domains")?;
+        debug!(count = page.len(), "loaded next page");
+        domains.extend(page);
+        next = page_next;
+    }
+    for domain in &domains {
+        println!("{domain}");
+    }
+    Ok(())
+}
+
+#[instrument(err)]
+fn limit(domain: &str, limited: bool) -> Result<()> {
+    let domain = parse_domain(domain)?;
+    let mut limits = DomainLimits::load()?;
+    let changed = if limited {
+        limits.domains.insert(domain.clone())
+    } else {
+        limits.domains.remove(&domain)
+    };
+    if changed {
+        limits.save()?;
+    }
+    info!(domain, limited, "updated domain limits");
+    Ok(())
+}
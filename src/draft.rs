@@ -0,0 +1,218 @@
+//! Statuses that are saved locally to be finished and posted later.
+//!
+//! Each draft is a JSON file in the `drafts` folder of the data folder,
+//! holding the text and every status option, including the paths of the media
+//! to attach.
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process;
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::config::{data_folder, split_words, Config};
+use crate::output::format_time_text;
+use crate::post::{self, StatusOptions};
+use crate::timestamp::format_timestamp;
+
+#[derive(Debug, Args)]
+pub struct DraftArgs {
+    #[command(subcommand)]
+    command: DraftCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum DraftCommand {
+    /// Save a new draft and print its id
+    Save {
+        /// The text of the status
+        text: String,
+
+        #[command(flatten)]
+        options: StatusOptions,
+    },
+    /// List the drafts, oldest first
+    List,
+    /// Change the text of a draft
+    Edit {
+        /// The id of the draft
+        id: String,
+
+        /// The new text [default: edit the text in $VISUAL or $EDITOR]
+        text: Option<String>,
+    },
+    /// Post a draft and delete it
+    Post {
+        /// The id of the draft
+        id: String,
+    },
+    /// Delete a draft without posting it
+    Delete {
+        /// The id of the draft
+        id: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Draft {
+    id: String,
+    saved_at: String,
+    text: String,
+    options: StatusOptions,
+}
+
+impl Draft {
+    fn path(id: &str) -> Result<PathBuf> {
+        ensure!(
+            !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()),
+            "{id} is not a draft id"
+        );
+        Ok(drafts_folder()?.join(format!("{id}.json")))
+    }
+
+    fn load(id: &str) -> Result<Self> {
+        let path = Self::path(id)?;
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == ErrorKind::NotFound => bail!("no draft {id}"),
+            Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+        };
+        serde_json::from_str(&json).with_context(|| format!("cannot parse file {path:?}"))
+    }
+
+    /// Saves the draft by writing a new file and renaming it over the old one,
+    /// so that a crash can't leave half a draft behind.
+    fn save(&self) -> Result<()> {
+        let folder = drafts_folder()?;
+        fs::create_dir_all(&folder).with_context(|| format!("cannot create {folder:?}"))?;
+        let path = Self::path(&self.id)?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("cannot write file {temp:?}"))?;
+        fs::rename(&temp, &path).with_context(|| format!("cannot write file {path:?}"))
+    }
+
+    fn remove(id: &str) -> Result<()> {
+        let path = Self::path(id)?;
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => bail!("no draft {id}"),
+            Err(err) => Err(err).with_context(|| format!("cannot remove file {path:?}")),
+        }
+    }
+}
+
+fn drafts_folder() -> Result<PathBuf> {
+    Ok(data_folder()?.join("drafts"))
+}
+
+pub async fn run(client: &Client, config: &Config, args: DraftArgs) -> Result<()> {
+    match args.command {
+        DraftCommand::Save { text, options } => save(text, options),
+        DraftCommand::List => list(),
+        DraftCommand::Edit { id, text } => edit(&id, text),
+        DraftCommand::Post { id } => post(client, config, &id).await,
+        DraftCommand::Delete { id } => Draft::remove(&id),
+    }
+}
+
+#[instrument(skip(options), err)]
+fn save(text: String, mut options: StatusOptions) -> Result<()> {
+    // the draft may be posted from a different folder
+    options.make_media_paths_absolute()?;
+    let now = OffsetDateTime::now_utc();
+    let draft = Draft {
+        id: now.unix_timestamp_nanos().to_string(),
+        saved_at: format_timestamp(now),
+        text,
+        options,
+    };
+    draft.save()?;
+    info!(id = draft.id, "saved draft");
+    println!("{}", draft.id);
+    Ok(())
+}
+
+#[instrument(err)]
+fn list() -> Result<()> {
+    let folder = drafts_folder()?;
+    let entries = match fs::read_dir(&folder) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("cannot read {folder:?}")),
+    };
+    let mut ids = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "json")
+        {
+            if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                ids.push(id.to_owned());
+            }
+        }
+    }
+    // the ids are timestamps, so this lists the oldest first
+    ids.sort();
+    for id in ids {
+        let draft = Draft::load(&id)?;
+        let first_line = draft.text.lines().next().unwrap_or_default();
+        println!(
+            "{} {} {first_line}",
+            draft.id,
+            format_time_text(&draft.saved_at)
+        );
+    }
+    Ok(())
+}
+
+#[instrument(err)]
+fn edit(id: &str, text: Option<String>) -> Result<()> {
+    let mut draft = Draft::load(id)?;
+    draft.text = match text {
+        Some(text) => text,
+        None => edit_in_editor(&draft)?,
+    };
+    draft.saved_at = format_timestamp(OffsetDateTime::now_utc());
+    draft.save()?;
+    info!(id, "saved draft");
+    Ok(())
+}
+
+/// Opens the text of the draft in the user's editor, returning the edited text.
+fn edit_in_editor(draft: &Draft) -> Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .context("set $VISUAL or $EDITOR, or give the new text")?;
+    // the editor can include arguments, e.g. `code --wait`
+    let mut words = split_words(&editor).into_iter();
+    let program = words.next().context("$VISUAL or $EDITOR is empty")?;
+    let path = drafts_folder()?.join(format!("{}.txt", draft.id));
+    fs::write(&path, &draft.text).with_context(|| format!("cannot write file {path:?}"))?;
+    let status = process::Command::new(&program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("cannot run {editor}"));
+    let text = fs::read_to_string(&path).with_context(|| format!("cannot read file {path:?}"));
+    fs::remove_file(&path).with_context(|| format!("cannot remove file {path:?}"))?;
+    ensure!(status?.success(), "{editor} failed, the draft is unchanged");
+    Ok(text?.trim_end().to_owned())
+}
+
+#[instrument(skip(client, config), err)]
+async fn post(client: &Client, config: &Config, id: &str) -> Result<()> {
+    let draft = Draft::load(id)?;
+    post::send(client, config, draft.text, draft.options).await?;
+    Draft::remove(id)?;
+    info!(id, "posted draft");
+    Ok(())
+}
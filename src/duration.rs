@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+/// Parses a duration such as `90s`, `30m`, `12h`, `7d` or `2w`. A number
+/// without a unit is a number of seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("{text} is not a duration like 30m, 12h or 7d"))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown duration unit {unit}, expected s, m, h, d or w"
+            ))
+        }
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("{text} is too long a duration"))
+}
+
+/// The time a duration before another, or `None` if that is further back
+/// than a time can go.
+pub fn checked_before(at: OffsetDateTime, duration: Duration) -> Option<OffsetDateTime> {
+    time::Duration::try_from(duration)
+        .ok()
+        .and_then(|duration| at.checked_sub(duration))
+}
+
+/// Formats a duration in its largest whole unit, rounding down, e.g. `5m` or
+/// `30d`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60)]
+        .iter()
+        .find(|(_, size)| seconds >= *size)
+        .map_or_else(
+            || format!("{seconds}s"),
+            |(unit, size)| format!("{}{unit}", seconds / size),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_counted_in_their_unit() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(
+            parse_duration("7d"),
+            Ok(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("2w"),
+            Ok(Duration::from_secs(14 * 24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn numbers_without_a_unit_are_seconds() {
+        assert_eq!(parse_duration(" 45 "), Ok(Duration::from_secs(45)));
+    }
+
+    #[test]
+    fn bad_durations_are_errors() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("5y").is_err());
+        assert!(parse_duration("5mm").is_err());
+    }
+
+    #[test]
+    fn durations_too_long_to_count_are_errors() {
+        assert!(parse_duration("99999999999999999999").is_err());
+        assert!(parse_duration("99999999999999999w").is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! The custom emoji of the instance.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use tracing::{debug, info, instrument, warn};
+
+use crate::client::Client;
+
+#[derive(Debug, Args)]
+pub struct EmojiArgs {
+    #[command(subcommand)]
+    command: EmojiCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum EmojiCommand {
+    /// List the custom emoji, grouped by category
+    List,
+    /// Download the image of every custom emoji, named after its shortcode
+    Download {
+        /// The folder to save the images in
+        #[arg(long, default_value = "emoji")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct CustomEmoji {
+    shortcode: String,
+    url: String,
+    #[serde(default)]
+    visible_in_picker: bool,
+    category: Option<String>,
+}
+
+pub async fn run(client: &Client, args: EmojiArgs) -> Result<()> {
+    match args.command {
+        EmojiCommand::List => list(client).await,
+        EmojiCommand::Download { dir } => download(client, &dir).await,
+    }
+}
+
+async fn custom_emoji(client: &Client) -> Result<Vec<CustomEmoji>> {
+    client
+        .get_cached("v1/custom_emojis")
+        .await
+        .context("Couldn't get custom emoji")
+}
+
+#[instrument(skip(client), err)]
+async fn list(client: &Client) -> Result<()> {
+    let mut categories: BTreeMap<Option<String>, Vec<CustomEmoji>> = BTreeMap::new();
+    for emoji in custom_emoji(client).await? {
+        categories
+            .entry(emoji.category.clone())
+            .or_default()
+            .push(emoji);
+    }
+    for (category, emoji) in categories {
+        println!("{}:", category.as_deref().unwrap_or("uncategorized"));
+        for emoji in emoji {
+            let hidden = if emoji.visible_in_picker {
+                ""
+            } else {
+                " (hidden)"
+            };
+            println!("  :{}: {}{hidden}", emoji.shortcode, emoji.url);
+        }
+    }
+    Ok(())
+}
+
+/// Downloads every custom emoji to `<shortcode>.<extension>` in the folder,
+/// skipping the ones that were already downloaded.
+#[instrument(skip(client), err)]
+async fn download(client: &Client, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("cannot create {dir:?}"))?;
+    let (mut downloaded, mut skipped, mut failed) = (0, 0, 0);
+    for emoji in custom_emoji(client).await? {
+        // the extension of the URL, ignoring any query
+        let extension = emoji
+            .url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit_once('.'))
+            .map_or("png", |(_, extension)| extension);
+        let path = dir.join(format!("{}.{extension}", emoji.shortcode));
+        if path.exists() {
+            debug!(?path, "already downloaded");
+            skipped += 1;
+            continue;
+        }
+        match client.download(&emoji.url).await {
+            Ok((bytes, _)) => {
+                fs::write(&path, bytes).with_context(|| format!("cannot write file {path:?}"))?;
+                downloaded += 1;
+            }
+            Err(err) => {
+                warn!(?err, shortcode = emoji.shortcode, "couldn't download emoji");
+                failed += 1;
+            }
+        }
+    }
+    info!(downloaded, skipped, failed, "downloaded custom emoji");
+    Ok(())
+}
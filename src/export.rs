@@ -0,0 +1,150 @@
+//! Exporting follows, blocks and mutes as the CSV files that Mastodon's
+//! import settings page accepts, e.g. for moving to another instance.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Account;
+use tracing::{info, instrument};
+
+use crate::account::{full_acct, instance_domain, relationships, Relationship};
+use crate::client::Client;
+use crate::output::csv_escape;
+use crate::paging;
+use crate::scrub;
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    #[command(subcommand)]
+    command: ExportCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ExportCommand {
+    /// Export the accounts you follow, with their boost, notification and
+    /// language settings
+    Follows(OutArgs),
+    /// Export the accounts you block
+    Blocks(OutArgs),
+    /// Export the accounts you mute
+    Mutes(OutArgs),
+}
+
+#[derive(Debug, Args)]
+struct OutArgs {
+    /// The file to write [default: stdout]
+    #[arg(long, value_name = "FILE")]
+    out: Option<PathBuf>,
+}
+
+pub async fn run(client: &Client, args: ExportArgs) -> Result<()> {
+    let (csv, out) = match args.command {
+        ExportCommand::Follows(args) => (follows(client).await?, args.out),
+        ExportCommand::Blocks(args) => (blocks(client).await?, args.out),
+        ExportCommand::Mutes(args) => (mutes(client).await?, args.out),
+    };
+    match out {
+        Some(path) => {
+            fs::write(&path, csv).with_context(|| format!("cannot write file {path:?}"))?;
+            info!(?path, "exported");
+        }
+        None => print!("{csv}"),
+    }
+    Ok(())
+}
+
+/// `following_accounts.csv`
+#[instrument(skip_all, err)]
+async fn follows(client: &Client) -> Result<String> {
+    let me = client
+        .verify_credentials()
+        .await
+        .context("Couldn't get account")?;
+    let accounts: Vec<Account> =
+        paging::collect_items(client, &format!("v1/accounts/{}/following", me.id), true)
+            .await
+            .context("Couldn't get the accounts you follow")?;
+    let domain = instance_domain(client);
+    let mut csv = String::from("Account address,Show boosts,Notify on new posts,Languages\n");
+    for (account, relationship) in with_relationships(client, accounts).await? {
+        let languages = relationship.languages.unwrap_or_default().join(",");
+        // writing to a String can't fail
+        let _ = writeln!(
+            csv,
+            "{},{},{},{}",
+            csv_escape(&address(&account, &domain)),
+            relationship.showing_reblogs,
+            relationship.notifying,
+            csv_escape(&languages)
+        );
+    }
+    Ok(csv)
+}
+
+/// `blocked_accounts.csv`, which has no header.
+#[instrument(skip_all, err)]
+async fn blocks(client: &Client) -> Result<String> {
+    let accounts: Vec<Account> = paging::collect_items(client, "v1/blocks", true)
+        .await
+        .context("Couldn't get blocks")?;
+    let domain = instance_domain(client);
+    let mut csv = String::new();
+    for account in &accounts {
+        let _ = writeln!(csv, "{}", csv_escape(&address(account, &domain)));
+    }
+    Ok(csv)
+}
+
+/// `muted_accounts.csv`
+#[instrument(skip_all, err)]
+async fn mutes(client: &Client) -> Result<String> {
+    let accounts: Vec<Account> = paging::collect_items(client, "v1/mutes", true)
+        .await
+        .context("Couldn't get mutes")?;
+    let domain = instance_domain(client);
+    let mut csv = String::from("Account address,Hide notifications\n");
+    for (account, relationship) in with_relationships(client, accounts).await? {
+        let _ = writeln!(
+            csv,
+            "{},{}",
+            csv_escape(&address(&account, &domain)),
+            relationship.muting_notifications
+        );
+    }
+    Ok(csv)
+}
+
+/// Pairs each account with your relationship with it, as the lists leave out
+/// the settings of each follow and mute.
+async fn with_relationships(
+    client: &Client,
+    accounts: Vec<Account>,
+) -> Result<Vec<(Account, Relationship)>> {
+    let ids: Vec<String> = accounts
+        .iter()
+        .map(|account| account.id.to_string())
+        .collect();
+    let mut relationships = relationships(client, &ids).await?;
+    Ok(accounts
+        .into_iter()
+        .map(|account| {
+            let id = account.id.to_string();
+            let relationship = relationships
+                .iter()
+                .position(|relationship| relationship.id == id)
+                .map(|index| relationships.swap_remove(index))
+                .unwrap_or_default();
+            (account, relationship)
+        })
+        .collect())
+}
+
+/// The address of an account in the CSV files, or its pseudonym with
+/// `--scrub`.
+fn address(account: &Account, domain: &str) -> String {
+    scrub::with(|scrubber| scrubber.pseudonym(&account.acct))
+        .unwrap_or_else(|| full_acct(account, domain))
+}
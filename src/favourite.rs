@@ -0,0 +1,49 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use tracing::instrument;
+
+use crate::client::Client;
+use crate::mark::{self, Kind};
+
+#[derive(Debug, Args)]
+pub struct FavArgs {
+    /// The id or URL of the status
+    status: String,
+}
+
+#[derive(Debug, Args)]
+pub struct FavsArgs {
+    #[command(subcommand)]
+    command: FavsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum FavsCommand {
+    /// List favourited statuses, newest first
+    List(ListArgs),
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    /// Page through every favourite instead of showing the latest page
+    #[arg(long)]
+    all: bool,
+}
+
+/// Favourites a status. Requires the `write:favourites` scope.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: FavArgs) -> Result<()> {
+    mark::set(client, Kind::Favourites, &args.status, false).await
+}
+
+/// Undoes a favourite. Requires the `write:favourites` scope.
+#[instrument(skip(client), err)]
+pub async fn undo(client: &Client, args: FavArgs) -> Result<()> {
+    mark::set(client, Kind::Favourites, &args.status, true).await
+}
+
+pub async fn list(client: &Client, args: FavsArgs) -> Result<()> {
+    match args.command {
+        FavsCommand::List(args) => mark::list(client, Kind::Favourites, args.all).await,
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use clap::{ArgAction, Args};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::resolve::resolve_account;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct FollowArgs {
+    /// The account, e.g. `@someone@example.com` or a profile URL
+    account: String,
+
+    /// Get a notification whenever the account posts
+    #[arg(long)]
+    notify: bool,
+
+    /// Show the account's boosts in the home timeline. Use `--reblogs=false`
+    /// to hide them.
+    #[arg(long, action = ArgAction::Set, default_value_t = true, value_name = "BOOL")]
+    reblogs: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UnfollowArgs {
+    /// The account, e.g. `@someone@example.com` or a profile URL
+    account: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FollowForm {
+    reblogs: bool,
+    notify: bool,
+}
+
+/// The parts of the relationship with an account that following changes.
+#[derive(Debug, Deserialize)]
+struct Relationship {
+    following: bool,
+    requested: bool,
+}
+
+impl FollowArgs {
+    /// Follows an account with the default settings.
+    pub const fn new(account: String) -> Self {
+        Self {
+            account,
+            notify: false,
+            reblogs: true,
+        }
+    }
+
+    pub const fn requirement(&self) -> Option<Requirement> {
+        if self.notify {
+            Some(Requirement::new(
+                "notifications of new posts",
+                Version(3, 3, 0),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Follows an account, or changes the settings of an existing follow. Requires
+/// the `write:follows` scope.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: FollowArgs) -> Result<()> {
+    let account = resolve_account(client, &args.account).await?;
+    let form = FollowForm {
+        reblogs: args.reblogs,
+        notify: args.notify,
+    };
+    let relationship: Relationship = client
+        .post(&format!("v1/accounts/{}/follow", account.id), &form)
+        .await
+        .with_context(|| format!("Couldn't follow {}", account.acct))?;
+    info!(acct = account.acct, ?relationship, "followed");
+    if relationship.requested {
+        println!("requested to follow @{}", account.acct);
+    } else if relationship.following {
+        println!("following @{}", account.acct);
+    }
+    Ok(())
+}
+
+/// Unfollows an account, or withdraws a follow request. Requires the
+/// `write:follows` scope.
+#[instrument(skip(client), err)]
+pub async fn undo(client: &Client, args: UnfollowArgs) -> Result<()> {
+    let account = resolve_account(client, &args.account).await?;
+    let _: Relationship = client
+        .post(&format!("v1/accounts/{}/unfollow", account.id), &())
+        .await
+        .with_context(|| format!("Couldn't unfollow {}", account.acct))?;
+    info!(acct = account.acct, "unfollowed");
+    println!("unfollowed @{}", account.acct);
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Handling requests to follow a locked account.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::output::print_account;
+use crate::paging;
+use crate::resolve::resolve_account;
+
+#[derive(Debug, Args)]
+pub struct FollowRequestsArgs {
+    #[command(subcommand)]
+    command: FollowRequestsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum FollowRequestsCommand {
+    /// List the accounts waiting for you to accept their follow request
+    List {
+        /// Page through every request instead of showing the first page
+        #[arg(long)]
+        all: bool,
+    },
+    /// Let an account follow you
+    Accept {
+        /// The account id, or e.g. `@someone@example.com` or a profile URL
+        account: String,
+    },
+    /// Turn down a follow request
+    Reject {
+        /// The account id, or e.g. `@someone@example.com` or a profile URL
+        account: String,
+    },
+}
+
+/// The parts of the relationship that answering a request changes.
+#[derive(Debug, Deserialize)]
+struct Relationship {
+    followed_by: bool,
+}
+
+pub async fn run(client: &Client, args: FollowRequestsArgs) -> Result<()> {
+    match args.command {
+        FollowRequestsCommand::List { all } => list(client, all).await,
+        FollowRequestsCommand::Accept { account } => answer(client, &account, true).await,
+        FollowRequestsCommand::Reject { account } => answer(client, &account, false).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn list(client: &Client, all: bool) -> Result<()> {
+    paging::for_each_item(client, "v1/follow_requests", all, |account| {
+        print_account(&account);
+    })
+    .await
+    .context("Couldn't get follow requests")
+}
+
+/// Accepts or rejects a follow request. Requires the `write:follows` scope.
+#[instrument(skip(client), err)]
+async fn answer(client: &Client, id_or_acct: &str, accept: bool) -> Result<()> {
+    // the ids listed are local account ids, which are all digits
+    let id = if id_or_acct.chars().all(|c| c.is_ascii_digit()) {
+        id_or_acct.to_owned()
+    } else {
+        resolve_account(client, id_or_acct).await?.id.to_string()
+    };
+    let action = if accept { "authorize" } else { "reject" };
+    let relationship: Relationship = client
+        .post(&format!("v1/follow_requests/{id}/{action}"), &())
+        .await
+        .with_context(|| format!("Couldn't {action} the follow request from {id_or_acct}"))?;
+    info!(
+        id,
+        action,
+        followed_by = relationship.followed_by,
+        "answered follow request"
+    );
+    let answer = if accept { "accepted" } else { "rejected" };
+    println!("{answer} the follow request from {id_or_acct}");
+    Ok(())
+}
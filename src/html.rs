@@ -0,0 +1,493 @@
+//! Turning the HTML that Mastodon sends, such as status content and instance
+//! descriptions, into text or Markdown for the terminal.
+//!
+//! Mastodon only sends a small set of tags: paragraphs, line breaks and links,
+//! where mentions and hashtags are links too. Links show a shortened URL with
+//! the rest hidden in `invisible` spans, so the full URL is printed instead.
+//! Custom emoji are left as their `:shortcode:`, which is how they appear in
+//! the HTML.
+//!
+//! Control characters are dropped from the text, whether they were sent as is
+//! or as numeric entities, so that a post can't send escape sequences to the
+//! terminal. [`strip_controls`] does the same for plain text fields, such as
+//! content warnings and display names.
+
+use std::fmt::Write;
+
+/// Converts HTML to plain text. Paragraphs and headings are separated by blank
+/// lines, line breaks are kept, list items start with `- `, links are replaced
+/// by their URL, and everything else is reduced to its text, with whitespace
+/// collapsed as HTML would.
+pub fn to_text(html: &str) -> String {
+    to_styled_text(html, &|_, text| text)
+}
+
+/// Converts HTML to plain text like [`to_text`], passing the text of each link
+/// through `style`, e.g. to color it.
+pub fn to_styled_text(html: &str, style: &dyn Fn(LinkKind, String) -> String) -> String {
+    Renderer::new(false, style).render(html)
+}
+
+/// Converts HTML to Markdown, like [`to_text`] but with links, mentions and
+/// hashtags kept as links, emphasis and code kept, and the rest of the text
+/// escaped.
+pub fn to_markdown(html: &str) -> String {
+    Renderer::new(true, &|_, text| text).render(html)
+}
+
+/// What a link in the content is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Mention,
+    Hashtag,
+    Url,
+}
+
+struct Renderer<'a> {
+    markdown: bool,
+    style: &'a dyn Fn(LinkKind, String) -> String,
+    text: String,
+    /// The link being rendered, as its text is only known at the closing tag.
+    link: Option<Link>,
+}
+
+#[derive(Debug)]
+struct Link {
+    href: String,
+    kind: LinkKind,
+    /// Where the text of the link starts.
+    start: usize,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(markdown: bool, style: &'a dyn Fn(LinkKind, String) -> String) -> Self {
+        Self {
+            markdown,
+            style,
+            text: String::new(),
+            link: None,
+        }
+    }
+
+    fn render(mut self, html: &str) -> String {
+        self.text.reserve(html.len());
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            self.push_text(&rest[..start]);
+            let Some(end) = rest[start..].find('>') else {
+                // not a tag after all, so keep it as text
+                self.push_text(&rest[start..]);
+                rest = "";
+                break;
+            };
+            self.push_tag(&rest[start + 1..start + end]);
+            rest = &rest[start + end + 1..];
+        }
+        self.push_text(rest);
+        tidy(&self.text)
+    }
+
+    /// Adds the whitespace or Markdown that a tag stands for.
+    fn push_tag(&mut self, tag: &str) {
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match (name.as_str(), closing) {
+            // a backslash is a hard line break in Markdown, which keeps it
+            // from being trimmed away like trailing spaces would be
+            ("br", _) if self.markdown => self.text.push_str("\\\n"),
+            ("br", _) => self.text.push('\n'),
+            (
+                "p" | "div" | "blockquote" | "ul" | "ol" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6",
+                _,
+            ) => {
+                // a line break at the end of a paragraph isn't needed, and
+                // would be shown as a backslash
+                while self.text.ends_with("\\\n") {
+                    self.text.truncate(self.text.len() - 2);
+                }
+                self.text.push_str("\n\n");
+            }
+            ("li", false) => self.text.push_str("\n- "),
+            ("a", false) => {
+                let class = attribute(tag, "class").unwrap_or_default();
+                let rel = attribute(tag, "rel").unwrap_or_default();
+                let has_class = |name| class.split(' ').any(|class| class == name);
+                let kind = if has_class("hashtag") || rel.split(' ').any(|rel| rel == "tag") {
+                    LinkKind::Hashtag
+                } else if has_class("mention") {
+                    LinkKind::Mention
+                } else {
+                    LinkKind::Url
+                };
+                self.link = attribute(tag, "href").map(|href| Link {
+                    href,
+                    kind,
+                    start: self.text.len(),
+                });
+            }
+            ("a", true) => self.push_link(),
+            ("strong" | "b", _) if self.markdown => self.text.push_str("**"),
+            ("em" | "i", _) if self.markdown => self.text.push('*'),
+            ("code", _) if self.markdown => self.text.push('`'),
+            _ => {}
+        }
+    }
+
+    /// Replaces the text of the link that just closed with the link. Mentions
+    /// and hashtags show their text, e.g. `@someone`, rather than their URL.
+    fn push_link(&mut self) {
+        let Some(link) = self.link.take() else {
+            return;
+        };
+        let label = self.text.split_off(link.start);
+        let label = label.trim();
+        let mention = link.kind != LinkKind::Url;
+        let shows_url = label.is_empty() || without_scheme(label) == without_scheme(&link.href);
+        let rendered = match (self.markdown, mention, shows_url) {
+            (false, true, _) => (self.style)(link.kind, label.to_owned()),
+            (false, false, true) => (self.style)(link.kind, link.href),
+            (false, false, false) => format!("{label} ({})", (self.style)(link.kind, link.href)),
+            (true, false, true) => format!("<{}>", link.href),
+            (true, _, _) => format!("[{}]({})", escape_markdown(label), link.href),
+        };
+        self.text.push_str(&rendered);
+    }
+
+    /// Adds text from between tags, decoding entities and collapsing
+    /// whitespace. Markdown is escaped, except in links, which are escaped once
+    /// their text is complete.
+    fn push_text(&mut self, raw: &str) {
+        let decoded = decode_entities(raw);
+        let mut collapsed = String::with_capacity(decoded.len());
+        for c in decoded.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                let previous = collapsed
+                    .chars()
+                    .last()
+                    .or_else(|| self.text.chars().last());
+                if !matches!(previous, None | Some(' ' | '\n')) {
+                    collapsed.push(' ');
+                }
+            } else {
+                collapsed.push(c);
+            }
+        }
+        if self.markdown && self.link.is_none() {
+            self.text.push_str(&escape_markdown(&collapsed));
+        } else {
+            self.text.push_str(&collapsed);
+        }
+    }
+}
+
+/// The value of an attribute of a tag, e.g. `href="..."`, with its entities
+/// decoded.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+    while let Some(found) = rest.find(name) {
+        let before = rest[..found].chars().last();
+        rest = &rest[found + name.len()..];
+        if !before.map_or(false, char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = rest.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''));
+        let value = match quote {
+            Some(quote) => value[1..].split(quote).next().unwrap_or_default(),
+            None => value.split(char::is_whitespace).next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+fn without_scheme(url: &str) -> &str {
+    let url = url.trim_end_matches('/');
+    url.strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url)
+}
+
+/// Escapes the characters that Markdown would otherwise read as formatting.
+/// Underscores inside words are left alone, as they can't start emphasis and
+/// are common in custom emoji shortcodes.
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut previous = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        let needs_escape = match c {
+            '\\' | '*' | '`' | '[' | ']' | '<' | '>' => true,
+            '_' => {
+                !previous.map_or(false, char::is_alphanumeric)
+                    || !next.map_or(false, char::is_alphanumeric)
+            }
+            // a hashtag at the start of a line would be a heading
+            '#' => matches!(previous, None | Some('\n')),
+            _ => false,
+        };
+        if needs_escape {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        previous = Some(c);
+    }
+    escaped
+}
+
+/// Escapes text to go in HTML or XML, in text or in a quoted attribute.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The tags that [`sanitize`] keeps, which are the ones that Mastodon sends.
+const ALLOWED_TAGS: &str = "p br a span strong b em i u del s code pre blockquote ul ol li";
+
+/// Rebuilds HTML from another server with only the [`ALLOWED_TAGS`] and no
+/// attributes, apart from the `href` of links to `http` and `https` URLs. The
+/// text is escaped again, so nothing in the HTML can become a script, a style
+/// or a request for something else. Scripts and styles are dropped along with
+/// their content.
+pub fn sanitize(html: &str) -> String {
+    let mut sanitized = String::with_capacity(html.len());
+    // the element whose content is being dropped
+    let mut dropping: Option<String> = None;
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        if dropping.is_none() {
+            sanitized.push_str(&escape(&decode_entities(&rest[..start])));
+        }
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if let Some(dropped) = &dropping {
+            if closing && name == *dropped {
+                dropping = None;
+            }
+            continue;
+        }
+        match (name.as_str(), closing) {
+            ("script" | "style", false) => dropping = Some(name),
+            ("br", _) => sanitized.push_str("<br>"),
+            ("a", false) => {
+                let href = attribute(tag, "href").filter(|href| {
+                    let href = href.to_ascii_lowercase();
+                    href.starts_with("https://") || href.starts_with("http://")
+                });
+                match href {
+                    Some(href) => {
+                        let _ =
+                            write!(sanitized, "<a href=\"{}\" rel=\"nofollow\">", escape(&href));
+                    }
+                    None => sanitized.push_str("<a>"),
+                }
+            }
+            (name, closing) if ALLOWED_TAGS.split(' ').any(|allowed| allowed == name) => {
+                let slash = if closing { "/" } else { "" };
+                let _ = write!(sanitized, "<{slash}{name}>");
+            }
+            _ => {}
+        }
+    }
+    if dropping.is_none() {
+        sanitized.push_str(&escape(&decode_entities(rest)));
+    }
+    sanitized
+}
+
+/// Removes control characters other than newlines and tabs, such as the escape
+/// that starts a terminal escape sequence.
+pub fn strip_controls(text: &str) -> String {
+    text.chars()
+        .filter(|&c| !c.is_control() || matches!(c, '\n' | '\t'))
+        .collect()
+}
+
+/// Decodes the named entities that Mastodon uses, and numeric ones, dropping
+/// control characters.
+fn decode_entities(raw: &str) -> String {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let character = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let number = name.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (character, entity) {
+            (Some(character), Some((_, end))) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    strip_controls(&decoded)
+}
+
+/// Trims every line and leaves at most one blank line between paragraphs.
+fn tidy(text: &str) -> String {
+    let mut tidied = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !tidied.is_empty() {
+            tidied.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        blank_lines = 0;
+        tidied.push_str(line);
+    }
+    tidied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_keeps_the_tags_that_mastodon_sends() {
+        assert_eq!(
+            sanitize(
+                r#"<p class="x">Hi <a href="https://a.example" class="mention">@a</a><br/>!</p>"#
+            ),
+            r#"<p>Hi <a href="https://a.example" rel="nofollow">@a</a><br>!</p>"#
+        );
+    }
+
+    #[test]
+    fn sanitize_drops_scripts_styles_and_other_tags() {
+        assert_eq!(
+            sanitize(r#"<p onclick="x()">a<script>alert("<b>")</script><img src="i.png">b</p>"#),
+            "<p>ab</p>"
+        );
+        assert_eq!(sanitize("<style>p { color: red }</style>text"), "text");
+    }
+
+    #[test]
+    fn sanitize_only_keeps_web_links() {
+        assert_eq!(
+            sanitize(r#"<a href="javascript:alert(1)">x</a>"#),
+            "<a>x</a>"
+        );
+    }
+
+    #[test]
+    fn sanitize_escapes_the_text_again() {
+        assert_eq!(
+            sanitize("1 &lt; 2 &amp; <b>\"3\"</b>"),
+            "1 &lt; 2 &amp; <b>&quot;3&quot;</b>"
+        );
+        assert_eq!(sanitize("a < b"), "a &lt; b");
+    }
+
+    #[test]
+    fn escape_escapes_markup_and_quotes() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+        assert_eq!(escape("plain text"), "plain text");
+    }
+
+    #[test]
+    fn escape_escapes_entities_again() {
+        assert_eq!(escape("&amp;"), "&amp;amp;");
+    }
+
+    #[test]
+    fn to_text_separates_paragraphs_and_keeps_line_breaks() {
+        assert_eq!(
+            to_text("<p>one<br>two</p><p>three</p>"),
+            "one\ntwo\n\nthree"
+        );
+    }
+
+    #[test]
+    fn to_text_prints_full_urls_and_collapses_whitespace() {
+        let html = r#"<p>see   <a href="https://example.com/a/long/path"><span class="invisible">https://</span><span class="ellipsis">example.com/a/</span><span class="invisible">long/path</span></a></p>"#;
+        assert_eq!(to_text(html), "see https://example.com/a/long/path");
+    }
+
+    #[test]
+    fn to_text_decodes_entities() {
+        assert_eq!(
+            to_text("<p>1 &lt; 2 &amp;&amp; &#x41;&#66;</p>"),
+            "1 < 2 && AB"
+        );
+        assert_eq!(to_text("a &unknown; b & c"), "a &unknown; b & c");
+    }
+
+    #[test]
+    fn to_text_drops_control_characters() {
+        assert_eq!(
+            to_text("<p>a&#27;[2Jb\u{1b}]52;c;x\u{7}</p>"),
+            "a[2Jb]52;c;x"
+        );
+        assert_eq!(to_text("&#x9b;31m&#0;"), "31m");
+    }
+
+    #[test]
+    fn strip_controls_keeps_newlines_and_tabs() {
+        assert_eq!(strip_controls("a\tb\nc\u{1b}[0m\u{85}"), "a\tb\nc[0m");
+    }
+
+    #[test]
+    fn to_markdown_keeps_links_and_escapes_text() {
+        let html = r#"<p>*hi* <a href="https://example.com/tags/rust" class="mention hashtag">#<span>rust</span></a></p>"#;
+        assert_eq!(
+            to_markdown(html),
+            "\\*hi\\* [\\#rust](https://example.com/tags/rust)"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Importing the follows, blocks and mutes CSV files that Mastodon exports, or
+//! that [`crate::export`] writes.
+//!
+//! Each account is resolved and then followed, blocked or muted one at a time,
+//! with a pause in between to stay under the instance's rate limit. Accounts
+//! that are already followed, blocked or muted are skipped, so an import that
+//! failed partway can simply be run again.
+
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use reqwest::StatusCode;
+use tracing::{info, instrument, warn};
+
+use crate::account::{relationships, Relationship};
+use crate::client::{error_status, Client};
+use crate::duration::parse_duration;
+use crate::resolve::resolve_account;
+
+/// How long to wait after hitting the rate limit. Mastodon's limits reset every
+/// five minutes.
+const RATE_LIMITED_WAIT: Duration = Duration::from_secs(60);
+
+/// How many times to wait for the rate limit before giving up on an account.
+const RATE_LIMITED_RETRIES: u32 = 5;
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    #[command(subcommand)]
+    command: ImportCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ImportCommand {
+    /// Follow the accounts in a `following_accounts.csv` file
+    Follows(FileArgs),
+    /// Block the accounts in a `blocked_accounts.csv` file
+    Blocks(FileArgs),
+    /// Mute the accounts in a `muted_accounts.csv` file
+    Mutes(FileArgs),
+}
+
+#[derive(Debug, Args)]
+struct FileArgs {
+    /// The CSV file to import
+    file: PathBuf,
+
+    /// How long to pause between accounts
+    #[arg(long, value_parser = parse_duration, default_value = "2s")]
+    interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Follows,
+    Blocks,
+    Mutes,
+}
+
+/// A line of an import file. The columns after the address are only in the
+/// follows and mutes files.
+#[derive(Debug)]
+struct Row {
+    acct: String,
+    show_boosts: bool,
+    notify: bool,
+    languages: Vec<String>,
+    hide_notifications: bool,
+}
+
+enum Outcome {
+    Done,
+    Skipped,
+}
+
+pub async fn run(client: &Client, args: ImportArgs) -> Result<()> {
+    let (kind, args) = match args.command {
+        ImportCommand::Follows(args) => (Kind::Follows, args),
+        ImportCommand::Blocks(args) => (Kind::Blocks, args),
+        ImportCommand::Mutes(args) => (Kind::Mutes, args),
+    };
+    import(client, kind, &args.file, args.interval).await
+}
+
+#[instrument(skip(client), err)]
+async fn import(client: &Client, kind: Kind, path: &Path, interval: Duration) -> Result<()> {
+    let text = fs::read_to_string(path).with_context(|| format!("cannot read file {path:?}"))?;
+    let rows = parse(&text);
+    let (mut done, mut skipped, mut failed) = (0, 0, Vec::new());
+    for (index, row) in rows.iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(interval).await;
+        }
+        match import_with_retries(client, kind, row).await {
+            Ok(Outcome::Done) => done += 1,
+            Ok(Outcome::Skipped) => skipped += 1,
+            Err(err) => {
+                warn!(?err, acct = row.acct, "couldn't import account");
+                failed.push((&row.acct, err));
+            }
+        }
+    }
+    let action = match kind {
+        Kind::Follows => "followed",
+        Kind::Blocks => "blocked",
+        Kind::Mutes => "muted",
+    };
+    println!(
+        "{action} {done}, skipped {skipped} already {action}, failed {}",
+        failed.len()
+    );
+    for (acct, err) in &failed {
+        println!("  @{acct}: {err:#}");
+    }
+    Ok(())
+}
+
+async fn import_with_retries(client: &Client, kind: Kind, row: &Row) -> Result<Outcome> {
+    with_rate_limit_retries(|| import_row(client, kind, row)).await
+}
+
+/// Runs `attempt` until it isn't rate limited, waiting for the limit to reset
+/// in between, up to a few times.
+pub async fn with_rate_limit_retries<T, F>(mut attempt: impl FnMut() -> F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        match attempt().await {
+            Err(err)
+                if error_status(&err) == Some(StatusCode::TOO_MANY_REQUESTS)
+                    && retries < RATE_LIMITED_RETRIES =>
+            {
+                retries += 1;
+                warn!(wait = ?RATE_LIMITED_WAIT, "rate limited, waiting");
+                tokio::time::sleep(RATE_LIMITED_WAIT).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn import_row(client: &Client, kind: Kind, row: &Row) -> Result<Outcome> {
+    let account = resolve_account(client, &row.acct).await?;
+    let id = account.id.to_string();
+    let relationship = relationships(client, &[id.clone()])
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    if already(kind, &relationship) {
+        return Ok(Outcome::Skipped);
+    }
+    let (action, form) = match kind {
+        Kind::Follows => {
+            let mut form = vec![
+                ("reblogs", row.show_boosts.to_string()),
+                ("notify", row.notify.to_string()),
+            ];
+            form.extend(
+                row.languages
+                    .iter()
+                    .map(|lang| ("languages[]", lang.clone())),
+            );
+            ("follow", form)
+        }
+        Kind::Blocks => ("block", Vec::new()),
+        Kind::Mutes => (
+            "mute",
+            vec![("notifications", row.hide_notifications.to_string())],
+        ),
+    };
+    let _: Relationship = client
+        .post(&format!("v1/accounts/{id}/{action}"), &form)
+        .await
+        .with_context(|| format!("Couldn't {action} {}", account.acct))?;
+    info!(acct = account.acct, action, "imported");
+    Ok(Outcome::Done)
+}
+
+/// Whether the relationship that the import sets up is already there.
+/// Requesting to follow a locked account counts as following it.
+const fn already(kind: Kind, relationship: &Relationship) -> bool {
+    match kind {
+        Kind::Follows => relationship.following || relationship.requested,
+        Kind::Blocks => relationship.blocking,
+        Kind::Mutes => relationship.muting,
+    }
+}
+
+/// Parses an import file, skipping the header line if there is one.
+fn parse(text: &str) -> Vec<Row> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with("Account address"))
+        .map(|line| {
+            let fields = split_fields(line);
+            let flag = |index: usize, default: bool| {
+                fields
+                    .get(index)
+                    .and_then(|field| field.trim().parse().ok())
+                    .unwrap_or(default)
+            };
+            let languages = fields.get(3).map_or_else(Vec::new, |languages| {
+                languages
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|lang| !lang.is_empty())
+                    .map(str::to_owned)
+                    .collect()
+            });
+            Row {
+                acct: fields[0].trim().trim_start_matches('@').to_owned(),
+                show_boosts: flag(1, true),
+                notify: flag(2, false),
+                languages,
+                // the mutes file only has the address and this column
+                hide_notifications: flag(1, true),
+            }
+        })
+        .collect()
+}
+
+/// Splits a CSV line into fields, unquoting quoted fields.
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_plain_fields() {
+        assert_eq!(
+            split_fields("alice@example.com,true,,en"),
+            ["alice@example.com", "true", "", "en"]
+        );
+    }
+
+    #[test]
+    fn keeps_commas_inside_quotes() {
+        assert_eq!(split_fields(r#""a, b",c"#), ["a, b", "c"]);
+    }
+
+    #[test]
+    fn unescapes_doubled_quotes() {
+        assert_eq!(split_fields(r#""say ""hi""",x"#), [r#"say "hi""#, "x"]);
+    }
+
+    #[test]
+    fn empty_line_is_one_empty_field() {
+        assert_eq!(split_fields(""), [""]);
+    }
+}
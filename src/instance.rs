@@ -0,0 +1,598 @@
+//! Information about the instance that the client is connected to.
+
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Args, Subcommand};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{debug, instrument};
+
+use crate::client::{error_status, Client};
+use crate::duration::format_duration;
+use crate::output::render_html;
+use crate::selftest::Report;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct InstanceArgs {
+    #[command(subcommand)]
+    command: InstanceCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum InstanceCommand {
+    /// Show the version, limits, contact and registration status
+    Show(ServerArgs),
+    /// Show the rules that everyone on the instance agrees to
+    Rules(ServerArgs),
+    /// Show the extended description of the instance
+    About(ServerArgs),
+    /// List the domains of the other instances that this one knows of
+    Peers {
+        #[command(flatten)]
+        server: ServerArgs,
+        /// Only print how many there are
+        #[arg(long)]
+        count: bool,
+        /// Print as JSON
+        #[arg(long, conflicts_with = "count")]
+        json: bool,
+    },
+    /// Show the statuses, logins and registrations of each of the last weeks
+    Activity {
+        #[command(flatten)]
+        server: ServerArgs,
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that the API, nodeinfo and the streaming server answer, and how
+    /// quickly
+    Health(ServerArgs),
+}
+
+#[derive(Debug, Args)]
+struct ServerArgs {
+    /// Show another instance instead, e.g. `mastodon.social`. Only public
+    /// information is requested, without logging in.
+    #[arg(long, value_name = "NAME")]
+    server: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+    text: String,
+    /// More detail about the rule, on Mastodon 4.3 and later.
+    #[serde(default)]
+    hint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtendedDescription {
+    content: String,
+}
+
+/// The parts of nodeinfo that are reported by `instance health`. Nodeinfo is
+/// shared by the fediverse, so it is served outside of the Mastodon API.
+#[derive(Debug, Deserialize)]
+struct NodeInfo {
+    software: Software,
+    #[serde(default)]
+    usage: Usage,
+}
+
+#[derive(Debug, Deserialize)]
+struct Software {
+    name: String,
+    version: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Usage {
+    users: Users,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Users {
+    total: Option<u64>,
+    active_month: Option<u64>,
+}
+
+/// The activity of one week. The numbers are strings in the API.
+#[derive(Debug, Deserialize, Serialize)]
+struct Activity {
+    /// The start of the week, as a unix timestamp.
+    week: String,
+    statuses: String,
+    logins: String,
+    registrations: String,
+}
+
+/// The original instance endpoint, which older instances only have.
+#[derive(Debug, Deserialize)]
+struct Instance {
+    #[serde(default)]
+    uri: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    version: String,
+    email: Option<String>,
+    contact_account: Option<ContactAccount>,
+    #[serde(default)]
+    registrations: bool,
+    #[serde(default)]
+    approval_required: bool,
+    #[serde(default)]
+    configuration: Configuration,
+    #[serde(default)]
+    urls: Urls,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContactAccount {
+    acct: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Urls {
+    /// The websocket URL of the streaming server, e.g.
+    /// `wss://streaming.example.com`.
+    streaming_api: Option<String>,
+}
+
+/// The newer instance endpoint, which has some settings that the original one
+/// doesn't.
+#[derive(Debug, Deserialize)]
+struct InstanceV2 {
+    #[serde(default)]
+    domain: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    configuration: ConfigurationV2,
+    #[serde(default)]
+    registrations: Registrations,
+    #[serde(default)]
+    contact: Contact,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ConfigurationV2 {
+    #[serde(flatten)]
+    limits: Configuration,
+    translation: TranslationConfiguration,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Registrations {
+    enabled: bool,
+    approval_required: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Contact {
+    email: Option<String>,
+    account: Option<ContactAccount>,
+}
+
+/// What both instance endpoints have in common.
+#[derive(Debug)]
+pub struct InstanceInfo {
+    pub domain: String,
+    pub title: String,
+    pub version: String,
+    pub email: Option<String>,
+    /// The address of the account to contact, e.g. `admin`.
+    pub contact: Option<String>,
+    pub registrations: bool,
+    pub approval_required: bool,
+    pub configuration: Configuration,
+}
+
+impl From<InstanceV2> for InstanceInfo {
+    fn from(instance: InstanceV2) -> Self {
+        Self {
+            domain: instance.domain,
+            title: instance.title,
+            version: instance.version,
+            // an instance without an email reports an empty one
+            email: instance.contact.email.filter(|email| !email.is_empty()),
+            contact: instance.contact.account.map(|account| account.acct),
+            registrations: instance.registrations.enabled,
+            approval_required: instance.registrations.approval_required,
+            configuration: instance.configuration.limits,
+        }
+    }
+}
+
+impl From<Instance> for InstanceInfo {
+    fn from(instance: Instance) -> Self {
+        Self {
+            domain: instance.uri,
+            title: instance.title,
+            version: instance.version,
+            email: instance.email.filter(|email| !email.is_empty()),
+            contact: instance.contact_account.map(|account| account.acct),
+            registrations: instance.registrations,
+            approval_required: instance.approval_required,
+            configuration: instance.configuration,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TranslationConfiguration {
+    /// Whether the instance has a translation service set up.
+    pub enabled: bool,
+}
+
+/// The limits that the instance enforces. Older instances don't report these,
+/// in which case Mastodon's defaults are used.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Configuration {
+    pub statuses: StatusesConfiguration,
+    pub media_attachments: MediaConfiguration,
+    pub polls: PollsConfiguration,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct StatusesConfiguration {
+    pub max_characters: usize,
+    pub max_media_attachments: usize,
+    /// How many characters each URL counts as, whatever its length.
+    pub characters_reserved_per_url: usize,
+}
+
+impl Default for StatusesConfiguration {
+    fn default() -> Self {
+        Self {
+            max_characters: 500,
+            max_media_attachments: 4,
+            characters_reserved_per_url: 23,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MediaConfiguration {
+    /// The largest image that can be uploaded, in bytes.
+    pub image_size_limit: u64,
+    /// The largest video or audio file that can be uploaded, in bytes.
+    pub video_size_limit: u64,
+}
+
+impl Default for MediaConfiguration {
+    fn default() -> Self {
+        Self {
+            image_size_limit: 16 * 1024 * 1024,
+            video_size_limit: 99 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PollsConfiguration {
+    pub max_options: usize,
+    pub max_characters_per_option: usize,
+    /// The shortest time a poll can be open for, in seconds.
+    pub min_expiration: u64,
+    /// The longest time a poll can be open for, in seconds.
+    pub max_expiration: u64,
+}
+
+impl Default for PollsConfiguration {
+    fn default() -> Self {
+        Self {
+            max_options: 4,
+            max_characters_per_option: 50,
+            min_expiration: 300,
+            max_expiration: 2_629_746,
+        }
+    }
+}
+
+impl InstanceArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            InstanceCommand::Show(_)
+            | InstanceCommand::Peers { .. }
+            | InstanceCommand::Activity { .. }
+            | InstanceCommand::Health(_) => None,
+            InstanceCommand::Rules(_) => Some(Requirement::new("instance rules", Version(3, 4, 0))),
+            InstanceCommand::About(_) => {
+                Some(Requirement::new("extended descriptions", Version(4, 0, 0)))
+            }
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: InstanceArgs) -> Result<()> {
+    match args.command {
+        InstanceCommand::Show(args) => show(client, args.server.as_deref()).await,
+        InstanceCommand::Rules(args) => rules(client, args.server.as_deref()).await,
+        InstanceCommand::About(args) => about(client, args.server.as_deref()).await,
+        InstanceCommand::Peers {
+            server,
+            count,
+            json,
+        } => peers(client, server.server.as_deref(), count, json).await,
+        InstanceCommand::Activity { server, json } => {
+            activity(client, server.server.as_deref(), json).await
+        }
+        InstanceCommand::Health(args) => health(client, args.server.as_deref()).await,
+    }
+}
+
+/// Probes the instance API, nodeinfo and the streaming server, printing how
+/// long each took and what software the instance runs.
+#[instrument(skip(client), err)]
+async fn health(client: &Client, server: Option<&str>) -> Result<()> {
+    let base = server_base(client, server);
+    let mut report = Report::default();
+    let instance: Option<Instance> = report
+        .step("get the instance information", async {
+            get(client, server, "v1/instance")
+                .await
+                .context("Couldn't get instance information")
+        })
+        .await;
+    if let Some(instance) = &instance {
+        println!(
+            "     {} ({}), version {}",
+            instance.uri, instance.title, instance.version
+        );
+    }
+    let nodeinfo: Option<NodeInfo> = report
+        .step("get nodeinfo", async {
+            client
+                .get_public(&format!("{base}/nodeinfo/2.0"))
+                .await
+                .context("Couldn't get nodeinfo")
+        })
+        .await;
+    if let Some(nodeinfo) = &nodeinfo {
+        println!(
+            "     software: {} {}",
+            nodeinfo.software.name, nodeinfo.software.version
+        );
+        let users = &nodeinfo.usage.users;
+        if let (Some(total), Some(active_month)) = (users.total, users.active_month) {
+            println!("     users: {total}, {active_month} active this month");
+        }
+    }
+    match instance.and_then(|instance| instance.urls.streaming_api) {
+        Some(streaming_api) => {
+            report
+                .step(
+                    "check the streaming server",
+                    streaming_health(client, &streaming_api),
+                )
+                .await;
+        }
+        None => Report::skip("check the streaming server"),
+    }
+    if report.failed > 0 {
+        if report.passed == 0 {
+            // a server that is up answers something, even when it is broken
+            println!(
+                "nothing answered, so check the network connection and the name of the server"
+            );
+        }
+        bail!("{} checks failed", report.failed);
+    }
+    Ok(())
+}
+
+/// Checks that the streaming server answers its health check, which is what
+/// clients connect to before opening a websocket, given its URL as reported by
+/// the instance.
+pub async fn streaming_health(client: &Client, streaming_api: &str) -> Result<()> {
+    let base = streaming_api
+        .replacen("wss://", "https://", 1)
+        .replacen("ws://", "http://", 1);
+    let url = format!("{}/api/v1/streaming/health", base.trim_end_matches('/'));
+    let (body, _) = client.download(&url).await?;
+    ensure!(
+        body.starts_with(b"OK"),
+        "unexpected health check response from {url}"
+    );
+    Ok(())
+}
+
+/// Prints the peers one per line, sorted. Instances can turn this endpoint off.
+#[instrument(skip(client), err)]
+async fn peers(client: &Client, server: Option<&str>, count: bool, json: bool) -> Result<()> {
+    let mut peers: Vec<String> = get(client, server, "v1/instance/peers")
+        .await
+        .context("Couldn't get instance peers")?;
+    peers.sort_unstable();
+    if count {
+        println!("{}", peers.len());
+    } else if json {
+        println!("{}", serde_json::to_string_pretty(&peers)?);
+    } else {
+        for peer in &peers {
+            println!("{peer}");
+        }
+    }
+    Ok(())
+}
+
+/// Prints the weekly activity, newest week first. Instances can turn this
+/// endpoint off.
+#[instrument(skip(client), err)]
+async fn activity(client: &Client, server: Option<&str>, json: bool) -> Result<()> {
+    let weeks: Vec<Activity> = get(client, server, "v1/instance/activity")
+        .await
+        .context("Couldn't get instance activity")?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&weeks)?);
+        return Ok(());
+    }
+    println!(
+        "{:10}  {:>8}  {:>6}  {:>13}",
+        "week", "statuses", "logins", "registrations"
+    );
+    for week in &weeks {
+        let start = week
+            .week
+            .parse()
+            .ok()
+            .and_then(|week| OffsetDateTime::from_unix_timestamp(week).ok())
+            .map_or_else(|| week.week.clone(), |week| week.date().to_string());
+        println!(
+            "{start:10}  {:>8}  {:>6}  {:>13}",
+            week.statuses, week.logins, week.registrations
+        );
+    }
+    Ok(())
+}
+
+/// Prints the rules as a numbered list, each followed by its hint.
+#[instrument(skip(client), err)]
+async fn rules(client: &Client, server: Option<&str>) -> Result<()> {
+    let rules: Vec<Rule> = get(client, server, "v1/instance/rules")
+        .await
+        .context("Couldn't get instance rules")?;
+    for (number, rule) in rules.iter().enumerate() {
+        println!("{}. {}", number + 1, render_html(&rule.text));
+        if !rule.hint.is_empty() {
+            println!("   {}", render_html(&rule.hint));
+        }
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn about(client: &Client, server: Option<&str>) -> Result<()> {
+    let description: ExtendedDescription = get(client, server, "v1/instance/extended_description")
+        .await
+        .context("Couldn't get the extended description")?;
+    println!("{}", render_html(&description.content));
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn show(client: &Client, server: Option<&str>) -> Result<()> {
+    let instance = info(client, server).await?;
+    let limits = &instance.configuration;
+    println!("{} ({})", instance.domain, instance.title);
+    println!("version: {}", instance.version);
+    let registrations = match (instance.registrations, instance.approval_required) {
+        (false, _) => "closed",
+        (true, false) => "open",
+        (true, true) => "open, with approval",
+    };
+    println!("registrations: {registrations}");
+    let contact: Vec<String> = instance
+        .email
+        .iter()
+        .cloned()
+        .chain(instance.contact.iter().map(|acct| format!("@{acct}")))
+        .collect();
+    if !contact.is_empty() {
+        println!("contact: {}", contact.join(", "));
+    }
+    println!(
+        "characters per status: {}, with each URL counting as {}",
+        limits.statuses.max_characters, limits.statuses.characters_reserved_per_url
+    );
+    println!(
+        "media per status: {}, images up to {} MB, video up to {} MB",
+        limits.statuses.max_media_attachments,
+        limits.media_attachments.image_size_limit / (1024 * 1024),
+        limits.media_attachments.video_size_limit / (1024 * 1024)
+    );
+    println!(
+        "poll options: {} of up to {} characters, open for {} to {}",
+        limits.polls.max_options,
+        limits.polls.max_characters_per_option,
+        format_duration(Duration::from_secs(limits.polls.min_expiration)),
+        format_duration(Duration::from_secs(limits.polls.max_expiration))
+    );
+    Ok(())
+}
+
+/// Gets the information about the instance, or about another instance given
+/// its name, from the newer endpoint, falling back to the original one on
+/// instances older than Mastodon 4.0.
+#[instrument(skip(client), err)]
+pub async fn info(client: &Client, server: Option<&str>) -> Result<InstanceInfo> {
+    match get::<InstanceV2>(client, server, "v2/instance").await {
+        Ok(instance) => return Ok(instance.into()),
+        Err(err) if error_status(&err) == Some(StatusCode::NOT_FOUND) => {
+            debug!("no v2 instance endpoint, falling back to v1");
+        }
+        Err(err) => return Err(err).context("Couldn't get instance information"),
+    }
+    let instance: Instance = get(client, server, "v1/instance")
+        .await
+        .context("Couldn't get instance information")?;
+    Ok(instance.into())
+}
+
+/// Gets an endpoint of the instance, or without logging in from another
+/// instance, e.g. `mastodon.social` or `https://mastodon.social`.
+async fn get<T: DeserializeOwned>(client: &Client, server: Option<&str>, path: &str) -> Result<T> {
+    if server.is_none() {
+        return client.get_cached(path).await;
+    }
+    let base = server_base(client, server);
+    client.get_public(&format!("{base}/api/{path}")).await
+}
+
+/// The URL of the instance, or of another instance given its name.
+fn server_base(client: &Client, server: Option<&str>) -> String {
+    let base = server.unwrap_or(&client.data.base);
+    if base.starts_with("https://") || base.starts_with("http://") {
+        base.trim_end_matches('/').to_owned()
+    } else {
+        format!("https://{}", base.trim_end_matches('/'))
+    }
+}
+
+/// The limits of the instance, for checking statuses and media before they
+/// are sent.
+pub async fn configuration(client: &Client) -> Result<Configuration> {
+    Ok(info(client, None).await?.configuration)
+}
+
+/// The URL of the instance's streaming server, if it reports one.
+#[instrument(skip_all, err)]
+pub async fn streaming_api(client: &Client) -> Result<Option<String>> {
+    let instance: Instance = client
+        .get_cached("v1/instance")
+        .await
+        .context("Couldn't get instance information")?;
+    Ok(instance.urls.streaming_api)
+}
+
+/// The translation settings of the instance. Instances older than Mastodon 4.0
+/// don't have the newer endpoint and can't translate either.
+#[instrument(skip_all, err)]
+pub async fn translation(client: &Client) -> Result<TranslationConfiguration> {
+    match client.get_cached::<InstanceV2>("v2/instance").await {
+        Ok(instance) => Ok(instance.configuration.translation),
+        Err(err) if error_status(&err) == Some(StatusCode::NOT_FOUND) => {
+            Ok(TranslationConfiguration::default())
+        }
+        Err(err) => Err(err).context("Couldn't get instance configuration"),
+    }
+}
@@ -0,0 +1,61 @@
+//! An append only record of the changes made to the account on someone's
+//! behalf, `journal.jsonl` in the data folder.
+//!
+//! People are identified by their local user name (`$USER`), as a shared
+//! account looks the same on the server whoever uses it. The approval queue
+//! keeps its own journal in the approval folder, and identifies people by
+//! their logins instead, see [`crate::queue`].
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::config::data_folder;
+use crate::timestamp::format_timestamp;
+
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    at: String,
+    action: &'a str,
+    id: &'a str,
+    by: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+}
+
+/// The name of the person running the command.
+pub fn local_user() -> String {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_owned())
+}
+
+/// Appends an entry to the journal.
+pub fn record(action: &str, id: &str, by: &str, url: Option<&str>) -> Result<()> {
+    record_in(&data_folder()?, action, id, by, url)
+}
+
+/// Appends an entry to the journal in another folder.
+pub fn record_in(folder: &Path, action: &str, id: &str, by: &str, url: Option<&str>) -> Result<()> {
+    fs::create_dir_all(folder).with_context(|| format!("cannot create {folder:?}"))?;
+    let path = folder.join("journal.jsonl");
+    let entry = Entry {
+        at: format_timestamp(OffsetDateTime::now_utc()),
+        action,
+        id,
+        by,
+        url,
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("cannot open file {path:?}"))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)
+        .with_context(|| format!("cannot write file {path:?}"))
+}
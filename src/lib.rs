@@ -7,6 +7,377 @@
 //! $ cargo run -- --help
 //! ```
 //!
+//! The toolkit can also be embedded in other programs by creating a [`Client`]
+//! with the [`SpikeMastodonBuilder`].
+//!
 //! # License
 //!
 //! This project is licensed under the MIT license. See the [LICENSE](LICENSE) file for more details.
+#![warn(
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::cargo
+)]
+
+mod account;
+mod admin;
+mod announcements;
+mod archive;
+mod auth;
+mod backup;
+mod batch;
+mod block;
+mod bookmark;
+mod boost;
+mod bundle;
+mod cli;
+mod client;
+mod config;
+mod cw;
+mod dedup;
+mod domain;
+mod draft;
+mod duration;
+mod emoji;
+mod export;
+mod favourite;
+mod follow;
+mod follow_requests;
+mod html;
+mod import;
+mod instance;
+mod journal;
+mod lists;
+mod mail;
+mod mark;
+mod markers;
+mod media_store;
+mod metrics;
+mod mute;
+mod notifications;
+mod offline;
+mod output;
+mod paging;
+mod plugin;
+mod post;
+mod preview;
+mod profile;
+mod push;
+mod queue;
+mod relationships;
+mod reply;
+mod report;
+mod resolve;
+mod retention;
+mod schedule;
+mod scrub;
+mod search;
+mod selftest;
+mod serve;
+mod snapshot;
+mod split;
+mod status;
+mod storage;
+mod stream;
+mod suggestions;
+mod sync;
+mod tags;
+mod thread;
+mod timeline;
+mod timestamp;
+mod trends;
+mod version;
+mod watch;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use mastodon_async::{Data, Mastodon};
+use tracing::{instrument, warn, Level};
+
+pub use cli::Cli;
+pub use client::Client;
+pub use config::Config;
+pub use metrics::MastodonAsyncRequests;
+pub use output::detect_local_offset;
+
+use cli::Command;
+
+/// Runs the command line interface.
+///
+/// # Errors
+///
+/// Returns an error if logging in or the command fails.
+#[instrument(skip_all, err)]
+pub async fn run(cli: Cli, config: &Config) -> Result<()> {
+    if cli.scrub {
+        scrub::enable();
+    }
+    let low_bandwidth = cli.low_bandwidth || config.low_bandwidth;
+    output::set_format(cli.output);
+    output::set_columns(cli.columns);
+    output::set_template(cli.template)?;
+    output::set_time_format(cli.time.unwrap_or(config.time));
+    let emoji = if cli.output == output::OutputFormat::Markdown {
+        config.emoji.markdown
+    } else {
+        config.emoji.text
+    };
+    output::set_emoji_mode(cli.emoji.unwrap_or(emoji));
+    output::set_palette(
+        output::Palette::new(cli.color, &config.theme)
+            .context("invalid theme in the config file")?,
+    );
+    let command = match cli.command {
+        // importing a bundle can be the first thing done on a new machine, so
+        // don't ask to log in
+        Some(Command::Config(args)) => return bundle::run(args),
+        Some(Command::Auth(args)) => return auth::run(args).await,
+        // an export can be read after the account is gone
+        Some(Command::Archive(args)) if args.is_local() => {
+            let result = archive::run_local(args);
+            let flushed = output::flush().context("Couldn't print the JSON output");
+            return result.and(flushed);
+        }
+        command => command,
+    };
+    if cli.offline {
+        // the saved login is only needed to know which instance to read
+        let client = SpikeMastodonBuilder::new()
+            .low_bandwidth(low_bandwidth)
+            .build()
+            .context("--offline needs a saved login")?;
+        let result = offline::run(&client, command);
+        let flushed = output::flush().context("Couldn't print the JSON output");
+        return result.and(flushed);
+    }
+    let mastodon = auth::login(None).await?;
+    // the CLI only uses the default profile
+    auth::check_scopes(None, &["read:accounts"])?;
+    let client = SpikeMastodonBuilder::new()
+        .credentials(mastodon.data.clone())
+        .low_bandwidth(low_bandwidth)
+        .build()?;
+    let account = auth::verify_credentials(&client).await?;
+    scrub::set_own_acct(&account.acct);
+    // the history is a nice to have, so it shouldn't stop the command
+    if let Err(err) = profile::record_snapshot(&account) {
+        warn!(?err, "couldn't record profile snapshot");
+    }
+
+    if let Some(command @ Command::Serve(_)) = &command {
+        // serve answers its clients without going through dispatch
+        auth::check_scopes(None, command.scopes())?;
+    }
+    let result = match command {
+        Some(Command::Batch(args)) => batch::run(&client, config, args).await,
+        Some(Command::Serve(args)) => serve::run(&client, config, args).await,
+        command => dispatch(&client, config, command).await,
+    };
+    // print the JSON collected so far even if the command failed part way
+    let flushed = output::flush().context("Couldn't print the JSON output");
+    result.and(flushed)
+}
+
+/// Runs a single command with an authenticated client.
+async fn dispatch(client: &Client, config: &Config, command: Option<Command>) -> Result<()> {
+    if let Some(command) = &command {
+        auth::check_scopes(None, command.scopes())?;
+    }
+    if let Some(requirement) = command.as_ref().and_then(Command::requirement) {
+        version::check(client, requirement).await;
+    }
+    match command {
+        Some(Command::Batch(_)) => bail!("batch files can't be nested"),
+        Some(Command::Serve(_)) => bail!("serve can't be run from a batch file"),
+        Some(Command::Auth(_)) => bail!("auth can't be run from a batch file"),
+        Some(Command::Account(args)) => account::run(client, args).await,
+        Some(Command::Admin(args)) => admin::run(client, args).await,
+        Some(Command::Announcements(args)) => announcements::run(client, args).await,
+        Some(Command::Archive(args)) => archive::run(client, args).await,
+        Some(Command::Backup(args)) => backup::backup(client, args).await,
+        Some(Command::Block(args)) => block::run(client, args).await,
+        Some(Command::Blocks(args)) => block::list(client, args).await,
+        Some(Command::Bookmark(args)) => bookmark::run(client, args).await,
+        Some(Command::Bookmarks(args)) => bookmark::list(client, args).await,
+        Some(Command::Boost(args)) => boost::run(client, args).await,
+        Some(Command::Config(args)) => bundle::run(args),
+        Some(Command::Domain(args)) => domain::run(client, args).await,
+        Some(Command::Draft(args)) => draft::run(client, config, args).await,
+        Some(Command::Emoji(args)) => emoji::run(client, args).await,
+        Some(Command::Export(args)) => export::run(client, args).await,
+        Some(Command::Fav(args)) => favourite::run(client, args).await,
+        Some(Command::Favs(args)) => favourite::list(client, args).await,
+        Some(Command::Follow(args)) => follow::run(client, args).await,
+        Some(Command::FollowRequests(args)) => follow_requests::run(client, args).await,
+        Some(Command::Import(args)) => import::run(client, args).await,
+        Some(Command::Instance(args)) => instance::run(client, args).await,
+        Some(Command::Lists(args)) => lists::run(client, args).await,
+        Some(Command::Mail(args)) => mail::run(client, config, args).await,
+        Some(Command::Mute(args)) => mute::run(client, args).await,
+        Some(Command::Mutes(args)) => mute::list(client, args).await,
+        Some(Command::Notifications(args)) => notifications::run(client, args).await,
+        Some(Command::Plugin(args)) => plugin::run(client, config, args).await,
+        Some(Command::Post(args)) => post::run(client, config, args).await,
+        Some(Command::Profile(args)) => profile::run(client, args).await,
+        Some(Command::Push(args)) => push::run(client, args).await,
+        Some(Command::Queue(args)) => queue::run(client, config, args).await,
+        Some(Command::Relationships(args)) => relationships::run(client, args).await,
+        Some(Command::Reply(args)) => reply::run(client, config, args).await,
+        Some(Command::Report(args)) => report::run(client, args).await,
+        Some(Command::Resolve(args)) => resolve::run(client, args).await,
+        Some(Command::Restore(args)) => backup::restore(client, args).await,
+        Some(Command::Retention(args)) => retention::run(client, args).await,
+        Some(Command::Schedule(args)) => schedule::run(client, config, args).await,
+        Some(Command::Search(args)) => search::run(client, args).await,
+        Some(Command::Selftest(args)) => selftest::run(client, config, args).await,
+        Some(Command::Status(args)) => status::run(client, args).await,
+        Some(Command::Stream(args)) => stream::run(client, config, args).await,
+        Some(Command::Suggestions(args)) => suggestions::run(client, args).await,
+        Some(Command::Sync(args)) => sync::run(client, args).await,
+        Some(Command::Tags(args)) => tags::run(client, args).await,
+        Some(Command::Timeline(args)) => timeline::run(client, config, args).await,
+        Some(Command::Trends(args)) => trends::run(client, args).await,
+        Some(Command::Unblock(args)) => block::undo(client, args).await,
+        Some(Command::Unbookmark(args)) => bookmark::undo(client, args).await,
+        Some(Command::Unboost(args)) => boost::undo(client, args).await,
+        Some(Command::Unfav(args)) => favourite::undo(client, args).await,
+        Some(Command::Unfollow(args)) => follow::undo(client, args).await,
+        Some(Command::Unmute(args)) => mute::undo(client, args).await,
+        None => timeline::show_timeline(client).await,
+    }
+}
+
+/// Configures and creates a [`Client`] for using the toolkit from other
+/// programs.
+///
+/// Unlike the command line, the builder never prompts to log in, so the
+/// credentials must either be given or already saved for the profile (by
+/// running the command line once).
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use std::time::Duration;
+///
+/// let client = spike_mastodon::SpikeMastodonBuilder::new()
+///     .profile("work")
+///     .timeout(Duration::from_secs(10))
+///     .build()?;
+/// let account = client.verify_credentials().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+#[must_use]
+pub struct SpikeMastodonBuilder {
+    profile: Option<String>,
+    credentials: Option<Data>,
+    cache_dir: Option<PathBuf>,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+    log_level: Option<Level>,
+    low_bandwidth: bool,
+}
+
+impl SpikeMastodonBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the credentials saved for a named profile instead of the default
+    /// ones.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Use these credentials instead of loading saved ones.
+    pub fn credentials(mut self, credentials: Data) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Where to store cached data [default: the cache folder for the platform]
+    pub fn cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// The timeout for each HTTP request.
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The user agent sent with each HTTP request.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Log to stderr at this level. This does nothing if the program has
+    /// already set up logging.
+    pub const fn log_level(mut self, level: Level) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    /// Use less data, for metered or slow connections: smaller pages, and
+    /// nothing downloaded that isn't needed.
+    pub const fn low_bandwidth(mut self, low_bandwidth: bool) -> Self {
+        self.low_bandwidth = low_bandwidth;
+        self
+    }
+
+    /// Creates the client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no credentials or the HTTP client can't
+    /// be created.
+    pub fn build(self) -> Result<Client> {
+        if let Some(level) = self.log_level {
+            // fails if there is already a subscriber, which is fine
+            let _ = tracing_subscriber::fmt()
+                .with_max_level(level)
+                .with_writer(std::io::stderr)
+                .try_init();
+        }
+        let credentials = match self.credentials {
+            Some(credentials) => credentials,
+            None => auth::load_credentials(self.profile.as_deref())?,
+        };
+        let cache_dir = match self.cache_dir {
+            Some(cache_dir) => cache_dir,
+            None => config::cache_folder()?,
+        };
+        let builder = || {
+            let http = reqwest::Client::builder();
+            match &self.user_agent {
+                Some(user_agent) => http.user_agent(user_agent.as_str()),
+                None => http,
+            }
+        };
+        let mut http = builder();
+        // streams stay open for as long as they're followed, so only time out
+        // while connecting to them
+        let mut stream_http = builder();
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+            stream_http = stream_http.connect_timeout(timeout);
+        }
+        let http = http.build().context("Couldn't create HTTP client")?;
+        let stream_http = stream_http.build().context("Couldn't create HTTP client")?;
+        let mastodon = Mastodon::new(http.clone(), credentials);
+        Ok(Client::new(
+            mastodon,
+            http,
+            stream_http,
+            cache_dir,
+            self.low_bandwidth,
+        ))
+    }
+}
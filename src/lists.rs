@@ -0,0 +1,221 @@
+//! Managing lists, which are timelines of a chosen group of followed accounts.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Account;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::output::print_account;
+use crate::resolve::resolve_account;
+
+#[derive(Debug, Args)]
+pub struct ListsArgs {
+    #[command(subcommand)]
+    command: ListsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ListsCommand {
+    /// Show your lists
+    Show,
+    /// Create a list
+    Create {
+        /// The title of the list
+        title: String,
+    },
+    /// Change the title of a list
+    Rename {
+        /// The id or title of the list
+        list: String,
+        /// The new title
+        title: String,
+    },
+    /// Delete a list
+    Delete {
+        /// The id or title of the list
+        list: String,
+    },
+    /// Add accounts to a list. Only accounts that you follow can be added.
+    Add(MembersArgs),
+    /// Remove accounts from a list
+    Remove(MembersArgs),
+    /// List the accounts in a list
+    Members {
+        /// The id or title of the list
+        list: String,
+    },
+}
+
+#[derive(Debug, Args)]
+struct MembersArgs {
+    /// The id or title of the list
+    list: String,
+
+    /// The accounts, e.g. `@someone@example.com` or profile URLs
+    #[arg(required = true)]
+    accounts: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct List {
+    id: String,
+    title: String,
+}
+
+impl ListsArgs {
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            ListsCommand::Show | ListsCommand::Members { .. } => &["read:lists"],
+            // the members are given by address, and resolved with a search
+            ListsCommand::Add(_) | ListsCommand::Remove(_) => {
+                &["read:search", "read:lists", "write:lists"]
+            }
+            _ => &["read:lists", "write:lists"],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: ListsArgs) -> Result<()> {
+    match args.command {
+        ListsCommand::Show => show(client).await,
+        ListsCommand::Create { title } => create(client, &title).await,
+        ListsCommand::Rename { list, title } => rename(client, &list, &title).await,
+        ListsCommand::Delete { list } => delete(client, &list).await,
+        ListsCommand::Add(args) => add(client, args).await,
+        ListsCommand::Remove(args) => remove(client, args).await,
+        ListsCommand::Members { list } => members(client, &list).await,
+    }
+}
+
+async fn lists(client: &Client) -> Result<Vec<List>> {
+    client
+        .get("v1/lists", &())
+        .await
+        .context("Couldn't get lists")
+}
+
+/// Finds a list by its id, or otherwise by its title ignoring case.
+#[instrument(skip(client), err)]
+async fn find(client: &Client, id_or_title: &str) -> Result<List> {
+    let mut lists = lists(client).await?;
+    let by_id = lists.iter().position(|list| list.id == id_or_title);
+    let index = by_id
+        .or_else(|| {
+            lists
+                .iter()
+                .position(|list| list.title.eq_ignore_ascii_case(id_or_title))
+        })
+        .with_context(|| format!("no list {id_or_title}"))?;
+    Ok(lists.swap_remove(index))
+}
+
+#[instrument(skip(client), err)]
+async fn show(client: &Client) -> Result<()> {
+    for list in lists(client).await? {
+        println!("{} {}", list.id, list.title);
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn create(client: &Client, title: &str) -> Result<()> {
+    let list: List = client
+        .post("v1/lists", &[("title", title)])
+        .await
+        .with_context(|| format!("Couldn't create list {title}"))?;
+    info!(id = list.id, "created list");
+    println!("{} {}", list.id, list.title);
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn rename(client: &Client, id_or_title: &str, title: &str) -> Result<()> {
+    let list = find(client, id_or_title).await?;
+    let list: List = client
+        .put_json(
+            &format!("v1/lists/{}", list.id),
+            &serde_json::json!({ "title": title }),
+        )
+        .await
+        .with_context(|| format!("Couldn't rename list {}", list.title))?;
+    println!("{} {}", list.id, list.title);
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn delete(client: &Client, id_or_title: &str) -> Result<()> {
+    let list = find(client, id_or_title).await?;
+    let _: Value = client
+        .delete(&format!("v1/lists/{}", list.id))
+        .await
+        .with_context(|| format!("Couldn't delete list {}", list.title))?;
+    info!(id = list.id, title = list.title, "deleted list");
+    Ok(())
+}
+
+async fn account_ids(client: &Client, accts: &[String]) -> Result<Vec<String>> {
+    let mut ids = Vec::with_capacity(accts.len());
+    for acct in accts {
+        ids.push(resolve_account(client, acct).await?.id.to_string());
+    }
+    Ok(ids)
+}
+
+#[instrument(skip(client), err)]
+async fn add(client: &Client, args: MembersArgs) -> Result<()> {
+    let list = find(client, &args.list).await?;
+    let ids = account_ids(client, &args.accounts).await?;
+    let form: Vec<_> = ids
+        .iter()
+        .map(|id| ("account_ids[]", id.as_str()))
+        .collect();
+    let _: Value = client
+        .post(&format!("v1/lists/{}/accounts", list.id), &form)
+        .await
+        .with_context(|| format!("Couldn't add accounts to list {}", list.title))?;
+    info!(
+        count = ids.len(),
+        title = list.title,
+        "added accounts to list"
+    );
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn remove(client: &Client, args: MembersArgs) -> Result<()> {
+    let list = find(client, &args.list).await?;
+    let ids = account_ids(client, &args.accounts).await?;
+    // the ids are all digits, so they don't need escaping
+    let query: Vec<String> = ids.iter().map(|id| format!("account_ids[]={id}")).collect();
+    let _: Value = client
+        .delete(&format!(
+            "v1/lists/{}/accounts?{}",
+            list.id,
+            query.join("&")
+        ))
+        .await
+        .with_context(|| format!("Couldn't remove accounts from list {}", list.title))?;
+    info!(
+        count = ids.len(),
+        title = list.title,
+        "removed accounts from list"
+    );
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn members(client: &Client, id_or_title: &str) -> Result<()> {
+    let list = find(client, id_or_title).await?;
+    // a limit of 0 returns every member without paging
+    let accounts: Vec<Account> = client
+        .get(&format!("v1/lists/{}/accounts", list.id), &[("limit", "0")])
+        .await
+        .with_context(|| format!("Couldn't get the members of list {}", list.title))?;
+    for account in &accounts {
+        print_account(account);
+    }
+    Ok(())
+}
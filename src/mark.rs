@@ -0,0 +1,87 @@
+//! Bookmarks and favourites, which work the same way: each marks a status for
+//! the account, and each is listed most recently marked first.
+
+use anyhow::{Context, Result};
+use mastodon_async::prelude::Status;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::client::Client;
+use crate::output::print_status;
+use crate::paging;
+use crate::resolve::resolve_status;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Bookmarks,
+    Favourites,
+}
+
+impl Kind {
+    pub const fn action(self) -> &'static str {
+        match self {
+            Self::Bookmarks => "bookmark",
+            Self::Favourites => "favourite",
+        }
+    }
+
+    pub const fn done(self) -> &'static str {
+        match self {
+            Self::Bookmarks => "bookmarked",
+            Self::Favourites => "favourited",
+        }
+    }
+
+    const fn path(self) -> &'static str {
+        match self {
+            Self::Bookmarks => "v1/bookmarks",
+            Self::Favourites => "v1/favourites",
+        }
+    }
+
+    const fn undo(self) -> &'static str {
+        match self {
+            Self::Bookmarks => "unbookmark",
+            Self::Favourites => "unfavourite",
+        }
+    }
+}
+
+/// Bookmarks or favourites a status given its id or URL, or with `undo`
+/// removes the bookmark or favourite, and prints the URL of the status.
+pub async fn set(client: &Client, kind: Kind, id_or_url: &str, undo: bool) -> Result<()> {
+    let status = resolve_status(client, id_or_url).await?;
+    let action = if undo { kind.undo() } else { kind.action() };
+    post(client, &status.id.to_string(), action).await?;
+    println!("{}", status.url.as_deref().unwrap_or(&status.uri));
+    Ok(())
+}
+
+/// Bookmarks or favourites a status on the account's instance.
+pub async fn mark(client: &Client, kind: Kind, id: &str) -> Result<()> {
+    post(client, id, kind.action()).await
+}
+
+async fn post(client: &Client, id: &str, action: &str) -> Result<()> {
+    let _: Status = client
+        .post(&format!("v1/statuses/{id}/{action}"), &())
+        .await
+        .with_context(|| format!("Couldn't {action} status {id}"))?;
+    info!(id, action, "done");
+    Ok(())
+}
+
+/// Collects the bookmarks or favourites, most recently marked first.
+pub async fn collect(client: &Client, kind: Kind) -> Result<Vec<Status>> {
+    paging::collect(client, kind.path(), true)
+        .await
+        .with_context(|| format!("Couldn't get {}s", kind.action()))
+}
+
+/// Prints the latest page of bookmarks or favourites, or with `all` every one.
+pub async fn list(client: &Client, kind: Kind, all: bool) -> Result<()> {
+    paging::for_each_status(client, kind.path(), all, |status| print_status(&status))
+        .await
+        .with_context(|| format!("Couldn't get {}s", kind.action()))
+}
@@ -0,0 +1,39 @@
+//! The markers API, which stores the last read position of the home and
+//! notifications timelines on the server so that it is shared between clients.
+//!
+//! See <https://docs.joinmastodon.org/methods/markers/>
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+
+pub const HOME: &str = "home";
+pub const NOTIFICATIONS: &str = "notifications";
+
+#[derive(Debug, Deserialize)]
+pub struct Marker {
+    pub last_read_id: String,
+}
+
+/// Returns the marker for a timeline, or `None` if none has been saved yet.
+#[instrument(skip(client), err)]
+pub async fn get(client: &Client, timeline: &str) -> Result<Option<Marker>> {
+    let mut markers: HashMap<String, Marker> = client
+        .get("v1/markers", &[("timeline[]", timeline)])
+        .await?;
+    Ok(markers.remove(timeline))
+}
+
+#[instrument(skip(client), err)]
+pub async fn set(client: &Client, timeline: &str, last_read_id: &str) -> Result<()> {
+    let key = format!("{timeline}[last_read_id]");
+    let _: HashMap<String, Marker> = client
+        .post("v1/markers", &[(key.as_str(), last_read_id)])
+        .await?;
+    info!(timeline, last_read_id, "marker updated");
+    Ok(())
+}
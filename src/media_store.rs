@@ -0,0 +1,243 @@
+//! A folder of downloaded media attachments, stored by the SHA-256 of their
+//! content so that the same file is kept once however many statuses attach it,
+//! e.g. an image that is boosted or reposted again and again.
+//!
+//! ```text
+//! media/
+//!   manifest.json
+//!   <sha256>.<extension>
+//! ```
+//!
+//! `manifest.json` maps the id of each status to its attachments and the files
+//! that hold them, and is how [`MediaStore::verify`] knows what the files
+//! should contain. It is only saved when the caller is done, so an interrupted
+//! download fetches the last few attachments again, which are then found to be
+//! already on disk by their hash.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use mastodon_async::prelude::Status;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, instrument, warn};
+
+use crate::client::Client;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Manifest {
+    statuses: BTreeMap<String, Vec<Stored>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Stored {
+    attachment: String,
+    sha256: String,
+    file: String,
+}
+
+/// What happened to the attachments passed to [`MediaStore::store_status`].
+#[derive(Debug, Default)]
+pub struct Counts {
+    /// Attachments written to a new file.
+    pub downloaded: usize,
+    /// Attachments whose content was already stored for another status.
+    pub deduplicated: usize,
+    /// Attachments in the manifest already, which weren't downloaded again.
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+pub struct MediaStore {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl MediaStore {
+    /// Opens the store in a folder, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the folder can't be created or the manifest can't
+    /// be read.
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("cannot create {dir:?}"))?;
+        let path = dir.join("manifest.json");
+        let manifest = match fs::read_to_string(&path) {
+            Ok(text) => {
+                serde_json::from_str(&text).with_context(|| format!("cannot parse {path:?}"))?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Manifest::default(),
+            Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+        };
+        Ok(Self {
+            dir: dir.to_owned(),
+            manifest,
+        })
+    }
+
+    /// Downloads the attachments of a status that aren't in the manifest yet.
+    /// A failed download is logged and counted rather than stopping the rest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file can't be written.
+    pub async fn store_status(
+        &mut self,
+        client: &Client,
+        status: &Status,
+        counts: &mut Counts,
+    ) -> Result<()> {
+        let status_id = status.id.to_string();
+        let attachments = serde_json::to_value(&status.media_attachments)?;
+        let mut added = Vec::new();
+        for attachment in attachments.as_array().into_iter().flatten() {
+            // the URL is missing while the attachment is still processing
+            let (Some(id), Some(url)) = (
+                attachment.get("id").and_then(Value::as_str),
+                attachment.get("url").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            if self
+                .manifest
+                .statuses
+                .get(&status_id)
+                .map_or(false, |stored| {
+                    stored.iter().any(|stored| stored.attachment == id)
+                })
+            {
+                counts.skipped += 1;
+                continue;
+            }
+            let bytes = match client.download(url).await {
+                Ok((bytes, _)) => bytes,
+                Err(err) => {
+                    warn!(?err, id, "couldn't download media");
+                    counts.failed += 1;
+                    continue;
+                }
+            };
+            let sha256 = format!("{:x}", Sha256::digest(&bytes));
+            let file = format!("{sha256}.{}", extension(url));
+            let path = self.dir.join(&file);
+            if path.exists() {
+                debug!(id, sha256, "already stored");
+                counts.deduplicated += 1;
+            } else {
+                fs::write(&path, bytes).with_context(|| format!("cannot write file {path:?}"))?;
+                counts.downloaded += 1;
+            }
+            added.push(Stored {
+                attachment: id.to_owned(),
+                sha256,
+                file,
+            });
+        }
+        if !added.is_empty() {
+            self.manifest
+                .statuses
+                .entry(status_id)
+                .or_default()
+                .extend(added);
+        }
+        Ok(())
+    }
+
+    /// The stored file of an attachment of a status, if it was downloaded.
+    pub fn file(&self, status_id: &str, attachment_id: &str) -> Option<PathBuf> {
+        self.manifest
+            .statuses
+            .get(status_id)?
+            .iter()
+            .find(|stored| stored.attachment == attachment_id)
+            .map(|stored| self.dir.join(&stored.file))
+    }
+
+    /// Writes the manifest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest can't be written.
+    pub fn save(&self) -> Result<()> {
+        let path = self.dir.join("manifest.json");
+        let json = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(&path, json).with_context(|| format!("cannot write file {path:?}"))
+    }
+
+    /// Checks that every file in the manifest exists and still has the hash
+    /// it was stored with, returning a description of each one that doesn't.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file exists but can't be read.
+    #[instrument(skip(self), fields(dir = ?self.dir), err)]
+    pub fn verify(&self) -> Result<Vec<String>> {
+        let files: BTreeSet<(&str, &str)> = self
+            .manifest
+            .statuses
+            .values()
+            .flatten()
+            .map(|stored| (stored.file.as_str(), stored.sha256.as_str()))
+            .collect();
+        let mut problems = Vec::new();
+        for (file, sha256) in &files {
+            let path = self.dir.join(file);
+            let bytes = match fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    problems.push(format!("{file}: missing"));
+                    continue;
+                }
+                Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+            };
+            let actual = format!("{:x}", Sha256::digest(&bytes));
+            if actual != *sha256 {
+                problems.push(format!("{file}: expected sha256 {sha256}, found {actual}"));
+            }
+        }
+        info!(
+            files = files.len(),
+            problems = problems.len(),
+            "verified media"
+        );
+        Ok(problems)
+    }
+}
+
+/// The extension of the file in a URL, ignoring any query.
+pub fn extension(url: &str) -> &str {
+    url.split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit_once('/'))
+        .and_then(|(_, name)| name.rsplit_once('.'))
+        .map_or("bin", |(_, extension)| extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_is_the_end_of_the_file_name() {
+        assert_eq!(
+            extension("https://files.example/media/original/a.b1.png"),
+            "png"
+        );
+    }
+
+    #[test]
+    fn extension_ignores_queries_and_fragments() {
+        assert_eq!(extension("https://files.example/a.jpeg?v=1.2"), "jpeg");
+        assert_eq!(extension("https://files.example/a.mp4#t=1.5"), "mp4");
+    }
+
+    #[test]
+    fn extension_defaults_to_bin() {
+        assert_eq!(extension("https://files.example/media/original"), "bin");
+        assert_eq!(extension("https://files.example.social/"), "bin");
+    }
+}
@@ -0,0 +1,138 @@
+//! Process wide metrics, exposed in the Prometheus text format by serve mode
+//! (`serve --http ADDRESS --metrics`).
+//!
+//! mastodon-async doesn't let its HTTP requests be intercepted, but it logs
+//! each one, so [`MastodonAsyncRequests`] counts them from the logs.
+
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+pub struct Metric {
+    name: &'static str,
+    help: &'static str,
+    kind: &'static str,
+    value: AtomicU64,
+}
+
+impl Metric {
+    const fn counter(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            kind: "counter",
+            value: AtomicU64::new(0),
+        }
+    }
+
+    const fn gauge(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            kind: "gauge",
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.value.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, value: u64) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+}
+
+pub static API_REQUESTS: Metric = Metric::counter(
+    "spike_mastodon_api_requests_total",
+    "Requests made to the instance API",
+);
+pub static API_RATE_LIMITED: Metric = Metric::counter(
+    "spike_mastodon_api_rate_limited_total",
+    "Requests that were rejected by the instance's rate limit",
+);
+pub static POSTS_PUBLISHED: Metric =
+    Metric::counter("spike_mastodon_posts_published_total", "Statuses posted");
+pub static RPC_REQUESTS: Metric = Metric::counter(
+    "spike_mastodon_rpc_requests_total",
+    "Requests handled by serve mode",
+);
+pub static RPC_IN_FLIGHT: Metric = Metric::gauge(
+    "spike_mastodon_rpc_in_flight",
+    "Requests to serve mode that are waiting on a response",
+);
+pub static STREAM_RECONNECTS: Metric = Metric::counter(
+    "spike_mastodon_stream_reconnects_total",
+    "Times a stream was reconnected after it ended or failed",
+);
+/// Counted from the pending folder each time the metrics are rendered, as
+/// other processes add and remove queued posts.
+pub static QUEUE_DEPTH: Metric =
+    Metric::gauge("spike_mastodon_queue_depth", "Posts waiting for approval");
+
+static ALL: [&Metric; 7] = [
+    &API_REQUESTS,
+    &API_RATE_LIMITED,
+    &POSTS_PUBLISHED,
+    &RPC_REQUESTS,
+    &RPC_IN_FLIGHT,
+    &STREAM_RECONNECTS,
+    &QUEUE_DEPTH,
+];
+
+/// A tracing layer that counts the API requests that mastodon-async logs in
+/// [`API_REQUESTS`]. Its logs must be forwarded to tracing, e.g. with
+/// `tracing_log::LogTracer`, and not filtered out before this layer.
+#[derive(Debug, Default)]
+pub struct MastodonAsyncRequests;
+
+/// The message that mastodon-async logs before sending each request.
+const REQUEST_MESSAGE: &str = "making API request";
+
+impl<S: Subscriber> Layer<S> for MastodonAsyncRequests {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if !event.metadata().target().starts_with("mastodon_async") {
+            return;
+        }
+        let mut message = Message(false);
+        event.record(&mut message);
+        if message.0 {
+            API_REQUESTS.inc();
+        }
+    }
+}
+
+/// Records whether the message of an event is [`REQUEST_MESSAGE`].
+struct Message(bool);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{value:?}") == REQUEST_MESSAGE;
+        }
+    }
+}
+
+/// Renders all the metrics in the Prometheus text exposition format.
+pub fn render() -> String {
+    let mut text = String::new();
+    for metric in ALL {
+        let Metric {
+            name, help, kind, ..
+        } = metric;
+        let value = metric.value.load(Ordering::Relaxed);
+        // writing to a string can't fail
+        let _ = writeln!(
+            text,
+            "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+        );
+    }
+    text
+}
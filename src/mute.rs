@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::duration::parse_duration;
+use crate::output::print_account;
+use crate::paging;
+use crate::resolve::resolve_account;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct MuteArgs {
+    /// The account, e.g. `@someone@example.com` or a profile URL
+    account: String,
+
+    /// Unmute automatically after this long, e.g. `12h` or `7d` [default:
+    /// never]
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    duration: Option<Duration>,
+
+    /// Also hide notifications from the account
+    #[arg(long)]
+    notifications: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UnmuteArgs {
+    /// The account, e.g. `@someone@example.com` or a profile URL
+    account: String,
+}
+
+#[derive(Debug, Args)]
+pub struct MutesArgs {
+    #[command(subcommand)]
+    command: MutesCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum MutesCommand {
+    /// List muted accounts
+    List(ListArgs),
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    /// Page through every muted account instead of showing the first page
+    #[arg(long)]
+    all: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct MuteForm {
+    notifications: bool,
+    /// In seconds, where 0 is forever.
+    duration: u64,
+}
+
+/// The parts of the relationship that are checked after muting.
+#[derive(Debug, Deserialize)]
+struct Relationship {
+    muting: bool,
+    muting_notifications: bool,
+}
+
+impl MuteArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        if self.duration.is_some() {
+            Some(Requirement::new("temporary mutes", Version(3, 5, 0)))
+        } else {
+            None
+        }
+    }
+}
+
+/// Mutes an account. Requires the `write:mutes` scope.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: MuteArgs) -> Result<()> {
+    let account = resolve_account(client, &args.account).await?;
+    let form = MuteForm {
+        notifications: args.notifications,
+        duration: args.duration.map_or(0, |duration| duration.as_secs()),
+    };
+    let relationship: Relationship = client
+        .post(&format!("v1/accounts/{}/mute", account.id), &form)
+        .await
+        .with_context(|| format!("Couldn't mute {}", account.acct))?;
+    info!(
+        acct = account.acct,
+        muting = relationship.muting,
+        notifications = relationship.muting_notifications,
+        "muted"
+    );
+    println!("muted @{}", account.acct);
+    Ok(())
+}
+
+/// Unmutes an account. Requires the `write:mutes` scope.
+#[instrument(skip(client), err)]
+pub async fn undo(client: &Client, args: UnmuteArgs) -> Result<()> {
+    let account = resolve_account(client, &args.account).await?;
+    let _: Relationship = client
+        .post(&format!("v1/accounts/{}/unmute", account.id), &())
+        .await
+        .with_context(|| format!("Couldn't unmute {}", account.acct))?;
+    info!(acct = account.acct, "unmuted");
+    println!("unmuted @{}", account.acct);
+    Ok(())
+}
+
+pub async fn list(client: &Client, args: MutesArgs) -> Result<()> {
+    match args.command {
+        MutesCommand::List(args) => {
+            paging::for_each_item(client, "v1/mutes", args.all, |account| {
+                print_account(&account);
+            })
+            .await
+            .context("Couldn't get mutes")
+        }
+    }
+}
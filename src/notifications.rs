@@ -0,0 +1,258 @@
+//! Reading notifications.
+
+mod filtering;
+mod rules;
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::{Account, Status};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info, instrument, warn};
+
+use crate::client::Client;
+use crate::markers;
+use crate::output::print_notifications;
+use crate::version::{Requirement, Version};
+use filtering::{PolicyArgs, RequestsArgs};
+pub use rules::NotificationRules;
+
+#[derive(Debug, Args)]
+pub struct NotificationsArgs {
+    #[command(subcommand)]
+    command: NotificationsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum NotificationsCommand {
+    /// List notifications, newest first
+    List(ListArgs),
+    /// List the notifications newer than the last read position saved on the
+    /// server, oldest first
+    Unread {
+        /// Then move the read position to the newest of them
+        #[arg(long)]
+        mark_read: bool,
+
+        /// Show every favourite and boost on its own line instead of grouping
+        /// them by status
+        #[arg(long)]
+        no_group: bool,
+    },
+    /// Dismiss a notification, e.g. once it has been dealt with
+    Dismiss {
+        /// The id of the notification
+        id: String,
+    },
+    /// Dismiss every notification
+    Clear,
+    /// Manage which notifications are filtered
+    Policy(PolicyArgs),
+    /// Review the notifications held back by the policy
+    Requests(RequestsArgs),
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    /// Only list these types, e.g. `mention,follow,favourite`
+    #[arg(long = "type", value_delimiter = ',', value_name = "TYPES")]
+    types: Vec<String>,
+
+    /// List at most this many notifications [default: one page]
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    limit: Option<usize>,
+
+    /// Page through every notification instead of showing the first page
+    #[arg(long)]
+    all: bool,
+
+    /// Show every favourite and boost on its own line instead of grouping them
+    /// by status
+    #[arg(long)]
+    no_group: bool,
+}
+
+/// A notification. The type is kept as text, as instances add new types.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub created_at: String,
+    pub account: Account,
+    pub status: Option<Status>,
+}
+
+impl NotificationsArgs {
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match &self.command {
+            NotificationsCommand::List(_)
+            | NotificationsCommand::Unread {
+                mark_read: false, ..
+            } => &["read:notifications"],
+            // saving the read position
+            NotificationsCommand::Unread {
+                mark_read: true, ..
+            } => &["read:notifications", "write:statuses"],
+            NotificationsCommand::Dismiss { .. } | NotificationsCommand::Clear => {
+                &["write:notifications"]
+            }
+            NotificationsCommand::Policy(args) if args.writes() => &["write:notifications"],
+            NotificationsCommand::Requests(args) if args.writes() => &["write:notifications"],
+            NotificationsCommand::Policy(_) | NotificationsCommand::Requests(_) => {
+                &["read:notifications"]
+            }
+        }
+    }
+
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            NotificationsCommand::Unread { .. } => {
+                Some(Requirement::new("markers", Version(3, 0, 0)))
+            }
+            NotificationsCommand::Policy(_) | NotificationsCommand::Requests(_) => {
+                Some(Requirement::new("filtered notifications", Version(4, 3, 0)))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: NotificationsArgs) -> Result<()> {
+    match args.command {
+        NotificationsCommand::List(args) => list(client, args).await,
+        NotificationsCommand::Unread {
+            mark_read,
+            no_group,
+        } => unread(client, mark_read, !no_group).await,
+        NotificationsCommand::Dismiss { id } => dismiss(client, &id).await,
+        NotificationsCommand::Clear => clear(client).await,
+        NotificationsCommand::Policy(args) => filtering::policy(client, args).await,
+        NotificationsCommand::Requests(args) => filtering::requests(client, args).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn list(client: &Client, args: ListArgs) -> Result<()> {
+    let types: Vec<&str> = args.types.iter().map(String::as_str).collect();
+    let notifications = fetch(client, &types, args.limit, args.all).await?;
+    let notifications = NotificationRules::load()?.retain(notifications);
+    print_notifications(&notifications, !args.no_group);
+    Ok(())
+}
+
+/// Prints every notification newer than the notifications marker, oldest
+/// first, and with `mark_read` moves the marker to the newest one.
+#[instrument(skip(client), err)]
+async fn unread(client: &Client, mark_read: bool, group: bool) -> Result<()> {
+    let Some(marker) = markers::get(client, markers::NOTIFICATIONS).await? else {
+        warn!("no notifications marker saved yet, showing the latest page instead");
+        let mut notifications = fetch(client, &[], None, false).await?;
+        notifications.reverse();
+        return print_and_mark_read(client, notifications, mark_read, group).await;
+    };
+    // like the home timeline, `min_id` pages forward from the marker
+    let mut notifications = Vec::new();
+    let mut min_id = marker.last_read_id;
+    loop {
+        let page: Vec<Notification> = client
+            .get(
+                "v1/notifications",
+                &[("min_id", min_id.as_str()), ("limit", client.page_limit())],
+            )
+            .await
+            .context("Couldn't get notifications")?;
+        let Some(newest) = page.first() else {
+            break;
+        };
+        min_id = newest.id.clone();
+        debug!(count = page.len(), %min_id, "loaded newer page");
+        notifications.extend(page.into_iter().rev());
+    }
+    print_and_mark_read(client, notifications, mark_read, group).await
+}
+
+async fn print_and_mark_read(
+    client: &Client,
+    notifications: Vec<Notification>,
+    mark_read: bool,
+    group: bool,
+) -> Result<()> {
+    info!(count = notifications.len(), "unread notifications");
+    // notifications hidden by the rules still count as read
+    let newest = notifications
+        .last()
+        .map(|notification| notification.id.clone());
+    print_notifications(&NotificationRules::load()?.retain(notifications), group);
+    if let Some(newest) = newest.filter(|_| mark_read) {
+        markers::set(client, markers::NOTIFICATIONS, &newest).await?;
+    }
+    Ok(())
+}
+
+/// Dismisses a notification. Requires the `write:notifications` scope.
+#[instrument(skip(client), err)]
+async fn dismiss(client: &Client, id: &str) -> Result<()> {
+    let _: Value = client
+        .post(&format!("v1/notifications/{id}/dismiss"), &())
+        .await
+        .with_context(|| format!("Couldn't dismiss notification {id}"))?;
+    info!(id, "dismissed notification");
+    Ok(())
+}
+
+/// Dismisses every notification. Requires the `write:notifications` scope.
+#[instrument(skip(client), err)]
+async fn clear(client: &Client) -> Result<()> {
+    let _: Value = client
+        .post("v1/notifications/clear", &())
+        .await
+        .context("Couldn't clear notifications")?;
+    info!("cleared notifications");
+    Ok(())
+}
+
+/// Gets the newest notifications of the given types, or of every type if none
+/// are given. This pages with `max_id` until there are `limit` notifications,
+/// or, with `all`, until there are no more.
+pub async fn fetch(
+    client: &Client,
+    types: &[&str],
+    limit: Option<usize>,
+    all: bool,
+) -> Result<Vec<Notification>> {
+    let page_size: usize = client.page_limit().parse()?;
+    let mut notifications = Vec::new();
+    let mut max_id: Option<String> = None;
+    loop {
+        let page_limit = limit
+            .map_or(page_size, |limit| {
+                page_size.min(limit.saturating_sub(notifications.len()))
+            })
+            .to_string();
+        let mut query = vec![("limit", page_limit.as_str())];
+        query.extend(types.iter().map(|kind| ("types[]", *kind)));
+        if let Some(max_id) = &max_id {
+            query.push(("max_id", max_id.as_str()));
+        }
+        let page: Vec<Notification> = client
+            .get("v1/notifications", &query)
+            .await
+            .context("Couldn't get notifications")?;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        max_id = Some(oldest.id.clone());
+        debug!(count = page.len(), ?max_id, "loaded older page");
+        notifications.extend(page);
+        let full = limit.map_or(false, |limit| notifications.len() >= limit);
+        if full || (!all && limit.is_none()) {
+            break;
+        }
+    }
+    // the instance can send more than was asked for
+    if let Some(limit) = limit {
+        notifications.truncate(limit);
+    }
+    Ok(notifications)
+}
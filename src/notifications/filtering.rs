@@ -0,0 +1,234 @@
+//! Filtered notifications, which Mastodon 4.3 holds back as notification
+//! requests according to the notification policy, until the requests are
+//! accepted or dismissed.
+
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use mastodon_async::prelude::{Account, Status};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::output::print_account;
+
+#[derive(Debug, Args)]
+pub struct PolicyArgs {
+    #[command(subcommand)]
+    command: PolicyCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum PolicyCommand {
+    /// Show what happens to notifications from each kind of account
+    Show,
+    /// Change what happens to notifications from some kinds of accounts
+    Update(UpdateArgs),
+}
+
+#[derive(Debug, Args)]
+struct UpdateArgs {
+    /// Accounts you don't follow
+    #[arg(long, value_enum)]
+    not_following: Option<PolicyAction>,
+
+    /// Accounts that don't follow you
+    #[arg(long, value_enum)]
+    not_followers: Option<PolicyAction>,
+
+    /// Accounts created in the last 30 days
+    #[arg(long, value_enum)]
+    new_accounts: Option<PolicyAction>,
+
+    /// Private mentions that aren't replies to your own mentions
+    #[arg(long, value_enum)]
+    private_mentions: Option<PolicyAction>,
+
+    /// Accounts limited by the moderators
+    #[arg(long, value_enum)]
+    limited_accounts: Option<PolicyAction>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PolicyAction {
+    /// Show the notifications as usual
+    Accept,
+    /// Hold the notifications back as requests
+    Filter,
+    /// Drop the notifications
+    Drop,
+}
+
+impl PolicyAction {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Accept => "accept",
+            Self::Filter => "filter",
+            Self::Drop => "drop",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Policy {
+    for_not_following: PolicyAction,
+    for_not_followers: PolicyAction,
+    for_new_accounts: PolicyAction,
+    for_private_mentions: PolicyAction,
+    for_limited_accounts: PolicyAction,
+    summary: PolicySummary,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolicySummary {
+    pending_requests_count: u64,
+    pending_notifications_count: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct RequestsArgs {
+    #[command(subcommand)]
+    command: RequestsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum RequestsCommand {
+    /// List the accounts whose notifications are being held back
+    List,
+    /// Let the notifications from a request through, now and from then on
+    Accept {
+        /// The id of the request
+        id: String,
+    },
+    /// Drop the notifications held back by a request
+    Dismiss {
+        /// The id of the request
+        id: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationRequest {
+    id: String,
+    notifications_count: Value,
+    account: Account,
+    last_status: Option<Status>,
+}
+
+impl RequestsArgs {
+    pub const fn writes(&self) -> bool {
+        !matches!(self.command, RequestsCommand::List)
+    }
+}
+
+impl PolicyArgs {
+    pub const fn writes(&self) -> bool {
+        matches!(self.command, PolicyCommand::Update(_))
+    }
+}
+
+pub async fn policy(client: &Client, args: PolicyArgs) -> Result<()> {
+    match args.command {
+        PolicyCommand::Show => show_policy(client).await,
+        PolicyCommand::Update(args) => update_policy(client, args).await,
+    }
+}
+
+pub async fn requests(client: &Client, args: RequestsArgs) -> Result<()> {
+    match args.command {
+        RequestsCommand::List => list_requests(client).await,
+        RequestsCommand::Accept { id } => answer_request(client, &id, "accept").await,
+        RequestsCommand::Dismiss { id } => answer_request(client, &id, "dismiss").await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn show_policy(client: &Client) -> Result<()> {
+    let policy: Policy = client
+        .get("v2/notifications/policy", &())
+        .await
+        .context("Couldn't get the notification policy")?;
+    print_policy(&policy);
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn update_policy(client: &Client, args: UpdateArgs) -> Result<()> {
+    let changes = [
+        ("for_not_following", args.not_following),
+        ("for_not_followers", args.not_followers),
+        ("for_new_accounts", args.new_accounts),
+        ("for_private_mentions", args.private_mentions),
+        ("for_limited_accounts", args.limited_accounts),
+    ];
+    let mut body = Map::new();
+    for (name, action) in changes {
+        if let Some(action) = action {
+            body.insert(name.to_owned(), serde_json::to_value(action)?);
+        }
+    }
+    ensure!(!body.is_empty(), "nothing to update");
+    let policy: Policy = client
+        .patch_json("v2/notifications/policy", &body)
+        .await
+        .context("Couldn't update the notification policy")?;
+    info!("updated notification policy");
+    print_policy(&policy);
+    Ok(())
+}
+
+fn print_policy(policy: &Policy) {
+    let rows = [
+        ("not following", policy.for_not_following),
+        ("not followers", policy.for_not_followers),
+        ("new accounts", policy.for_new_accounts),
+        ("private mentions", policy.for_private_mentions),
+        ("limited accounts", policy.for_limited_accounts),
+    ];
+    for (name, action) in rows {
+        println!("{name}: {}", action.name());
+    }
+    println!(
+        "{} requests holding {} notifications",
+        policy.summary.pending_requests_count, policy.summary.pending_notifications_count
+    );
+}
+
+#[instrument(skip(client), err)]
+async fn list_requests(client: &Client) -> Result<()> {
+    let requests: Vec<NotificationRequest> = client
+        .get(
+            "v1/notifications/requests",
+            &[("limit", client.page_limit())],
+        )
+        .await
+        .context("Couldn't get notification requests")?;
+    for request in &requests {
+        print!("{} ", request.id);
+        print_account(&request.account);
+        // the count is a string, as it can be large
+        let count = match &request.notifications_count {
+            Value::String(count) => count.clone(),
+            count => count.to_string(),
+        };
+        match &request.last_status {
+            Some(status) => println!(
+                "  {count} notifications, latest {}",
+                status.url.as_deref().unwrap_or(&status.uri)
+            ),
+            None => println!("  {count} notifications"),
+        }
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn answer_request(client: &Client, id: &str, action: &str) -> Result<()> {
+    let _: Value = client
+        .post(&format!("v1/notifications/requests/{id}/{action}"), &())
+        .await
+        .with_context(|| format!("Couldn't {action} notification request {id}"))?;
+    info!(id, action, "answered notification request");
+    Ok(())
+}
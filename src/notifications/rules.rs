@@ -0,0 +1,87 @@
+//! Client side rules for hiding noisy notifications.
+//!
+//! The rules are kept in `notification-rules.toml` in the config folder, and
+//! hide every notification that matches any of them, whatever the instance
+//! supports:
+//!
+//! ```toml
+//! # statuses containing these words, ignoring case
+//! keywords = ["giveaway", "crypto"]
+//! # notifications from these accounts
+//! accounts = ["spammer@example.com"]
+//! # notifications of these types
+//! types = ["favourite", "reblog"]
+//! ```
+//!
+//! They apply to `notifications list`, `notifications unread`, `stream user`
+//! and the `notifications` method of `serve`.
+
+use std::fs;
+use std::io::ErrorKind;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tracing::debug;
+
+use super::Notification;
+use crate::config::config_folder;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NotificationRules {
+    keywords: Vec<String>,
+    accounts: Vec<String>,
+    types: Vec<String>,
+}
+
+impl NotificationRules {
+    /// The name of the rules file in the config folder.
+    pub const FILE_NAME: &'static str = "notification-rules.toml";
+
+    pub fn load() -> Result<Self> {
+        let path = config_folder()?.join(Self::FILE_NAME);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+        };
+        let mut rules: Self =
+            toml::from_str(&text).with_context(|| format!("cannot parse file {path:?}"))?;
+        // match the way the values are compared
+        for keyword in &mut rules.keywords {
+            *keyword = keyword.to_lowercase();
+        }
+        for account in &mut rules.accounts {
+            *account = account.trim_start_matches('@').to_lowercase();
+        }
+        Ok(rules)
+    }
+
+    /// Whether the notification matches any of the rules.
+    pub fn hides(&self, notification: &Notification) -> bool {
+        if self.types.contains(&notification.kind) {
+            return true;
+        }
+        let acct = notification.account.acct.to_lowercase();
+        if self.accounts.contains(&acct) {
+            return true;
+        }
+        notification.status.as_ref().map_or(false, |status| {
+            let text = format!("{} {}", status.spoiler_text, status.content).to_lowercase();
+            self.keywords
+                .iter()
+                .any(|keyword| text.contains(keyword.as_str()))
+        })
+    }
+
+    /// Removes the notifications that match any of the rules.
+    pub fn retain(&self, mut notifications: Vec<Notification>) -> Vec<Notification> {
+        let count = notifications.len();
+        notifications.retain(|notification| !self.hides(notification));
+        debug!(
+            hidden = count - notifications.len(),
+            "applied notification rules"
+        );
+        notifications
+    }
+}
@@ -0,0 +1,66 @@
+//! `--offline`, for reading on a plane or while the instance is down: a few
+//! read-only commands show what [`crate::storage`] saved the last time they
+//! ran, or what `sync` saved, without sending anything to the instance.
+//!
+//! Saved statuses keep the counts and content they had when they were fetched,
+//! so each command first says when that was.
+
+use anyhow::{bail, Result};
+use tracing::info;
+
+use crate::account;
+use crate::cli::Command;
+use crate::client::Client;
+use crate::output::{format_time_text, is_structured};
+use crate::status;
+use crate::storage::{Saved, Storage};
+use crate::timeline;
+
+/// How many statuses of a timeline to show, as many as a page from the
+/// instance.
+pub const PAGE: usize = 40;
+
+/// Runs a command from the storage.
+///
+/// # Errors
+///
+/// Returns an error if the command doesn't work offline or the storage can't
+/// be read.
+pub fn run(client: &Client, command: Option<Command>) -> Result<()> {
+    let storage = Storage::open(client)?;
+    match command {
+        Some(Command::Timeline(args)) => timeline::run_offline(&storage, args),
+        Some(Command::Status(args)) => status::run_offline(&storage, args),
+        Some(Command::Account(args)) => account::run_offline(&storage, args),
+        _ => bail!("only timeline, status thread and account show and statuses work offline"),
+    }
+}
+
+/// Says when the entities about to be printed were saved, from the oldest to
+/// the newest, unless the output is data.
+pub fn print_freshness<T>(what: &str, saved: &[Saved<T>]) {
+    // the timestamps are all RFC 3339 in UTC, so they sort as text
+    let oldest = saved.iter().map(|saved| saved.fetched_at.as_str()).min();
+    let newest = saved.iter().map(|saved| saved.fetched_at.as_str()).max();
+    info!(
+        what,
+        count = saved.len(),
+        oldest,
+        newest,
+        "read from storage"
+    );
+    if is_structured() {
+        return;
+    }
+    match (oldest, newest) {
+        (Some(oldest), Some(newest)) if oldest == newest => {
+            println!("offline: {what} saved {}\n", format_time_text(oldest));
+        }
+        (Some(oldest), Some(newest)) => println!(
+            "offline: {what} saved between {} and {}\n",
+            format_time_text(oldest),
+            format_time_text(newest)
+        ),
+        _ => println!("offline: no {what} saved yet, run the command online first"),
+    }
+}
@@ -0,0 +1,477 @@
+//! Printing of entities to stdout.
+
+mod atom;
+mod color;
+mod csv;
+mod emoji;
+mod fields;
+mod template;
+mod timestamp;
+
+use std::collections::hash_map::{Entry, HashMap};
+use std::sync::{Mutex, PoisonError};
+
+use clap::ValueEnum;
+use mastodon_async::prelude::{Account, Status};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::html::{self, LinkKind};
+use crate::notifications::Notification;
+use crate::scrub;
+use crate::timestamp::parse_timestamp;
+pub use atom::set_feed;
+use color::paint;
+pub use color::{ColorChoice, Palette, Theme};
+pub use csv::{escape as csv_escape, set_columns};
+pub use emoji::{
+    emoji_mode, has_emoji_image, set_emoji_image, set_emoji_mode, CustomEmoji, EmojiConfig,
+    EmojiMode,
+};
+pub use template::set_template;
+pub use timestamp::{
+    detect_local_offset, format_time, format_time_text, set_time_format, TimeFormat,
+};
+
+/// The width of a poll result bar, in characters.
+const BAR_WIDTH: usize = 20;
+
+/// How entities are printed, set once from the command line.
+static FORMAT: Mutex<OutputFormat> = Mutex::new(OutputFormat::Text);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Plain text, with links replaced by their URL
+    #[default]
+    Text,
+    /// Markdown, with links, mentions and hashtags kept as links
+    Markdown,
+    /// An indented JSON array of the entities, with every field the instance
+    /// sent
+    Json,
+    /// A JSON array of the entities on one line
+    JsonCompact,
+    /// One JSON object per line, printed as soon as each entity arrives, for
+    /// `--all` and streams
+    Ndjson,
+    /// A row per entity with the `--columns`, after a header row
+    Csv,
+    /// An Atom feed of the statuses, for feed readers
+    Atom,
+}
+
+/// Sets how entities are printed for the rest of the process.
+pub fn set_format(format: OutputFormat) {
+    *FORMAT.lock().unwrap_or_else(PoisonError::into_inner) = format;
+}
+
+fn format() -> OutputFormat {
+    *FORMAT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether entities are printed as data, such as JSON or CSV, or with a
+/// template, so other text should be left out.
+pub fn is_structured() -> bool {
+    template::is_set() || !matches!(format(), OutputFormat::Text | OutputFormat::Markdown)
+}
+
+/// The entities to print as JSON or Atom, collected until [`flush`] so that the
+/// output is a single array or feed whatever the command prints.
+static JSON: Mutex<Vec<Value>> = Mutex::new(Vec::new());
+
+/// Prints an entity with the template, as NDJSON or as CSV right away, or
+/// collects it for the JSON output, returning whether the output is data
+/// rather than text.
+pub fn print_data(entity: &impl Serialize) -> bool {
+    if !is_structured() {
+        return false;
+    }
+    match serde_json::to_value(entity) {
+        Ok(value) if template::is_set() => template::print(value),
+        Ok(value) => match format() {
+            OutputFormat::Ndjson => println!("{value}"),
+            OutputFormat::Csv => csv::print_row(&value),
+            _ => JSON
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(value),
+        },
+        Err(err) => warn!(?err, "couldn't convert entity to JSON"),
+    }
+    true
+}
+
+/// Prints the entities collected for the JSON output as an array or as an
+/// Atom feed, or nothing for the other formats.
+///
+/// # Errors
+///
+/// Returns an error if the entities can't be converted to JSON.
+pub fn flush() -> serde_json::Result<()> {
+    let format = format();
+    if matches!(
+        format,
+        // NDJSON and CSV are printed as they arrive
+        OutputFormat::Text | OutputFormat::Markdown | OutputFormat::Ndjson | OutputFormat::Csv
+    ) {
+        return Ok(());
+    }
+    let values = std::mem::take(&mut *JSON.lock().unwrap_or_else(PoisonError::into_inner));
+    let output = match format {
+        OutputFormat::Atom => atom::feed(&values),
+        OutputFormat::Json => serde_json::to_string_pretty(&values)?,
+        _ => serde_json::to_string(&values)?,
+    };
+    println!("{output}");
+    Ok(())
+}
+
+/// The colors to print with, if any, set once from the command line.
+static PALETTE: Mutex<Option<Palette>> = Mutex::new(None);
+
+/// Sets the colors to print with for the rest of the process, or turns them
+/// off.
+pub fn set_palette(palette: Option<Palette>) {
+    *PALETTE.lock().unwrap_or_else(PoisonError::into_inner) = palette;
+}
+
+/// Styles the text with a part of the palette, if colors are used. Markdown is
+/// never colored, as it is meant to be read by other programs.
+fn style(part: fn(&Palette) -> &str, text: &str) -> String {
+    let palette = PALETTE.lock().unwrap_or_else(PoisonError::into_inner);
+    match palette.as_ref().filter(|_| format() == OutputFormat::Text) {
+        Some(palette) => paint(part(palette), text),
+        None => text.to_owned(),
+    }
+}
+
+/// Renders HTML from the instance, such as the content of a status, in the
+/// output format.
+pub fn render_html(content: &str) -> String {
+    match format() {
+        OutputFormat::Text => html::to_styled_text(content, &|kind, text| match kind {
+            LinkKind::Mention => style(|palette| &palette.mention, &text),
+            LinkKind::Hashtag => style(|palette| &palette.hashtag, &text),
+            LinkKind::Url => text,
+        }),
+        OutputFormat::Markdown => html::to_markdown(content),
+        OutputFormat::Json
+        | OutputFormat::JsonCompact
+        | OutputFormat::Ndjson
+        | OutputFormat::Csv
+        | OutputFormat::Atom => html::to_text(content),
+    }
+}
+
+fn author(acct: &str) -> String {
+    style(
+        |palette| &palette.author,
+        &format!("@{}", html::strip_controls(acct)),
+    )
+}
+
+/// The URL of a status, which is only ever plain text.
+fn status_url(status: &Status) -> String {
+    html::strip_controls(status.url.as_deref().unwrap_or(&status.uri))
+}
+
+/// Prints the author, URL and time of a status, its content warning, content,
+/// poll and link preview card, followed by a blank line. Boosts are printed as
+/// the boosted status.
+pub fn print_status(status: &Status) {
+    let status = &*scrub::status(status);
+    if print_data(status) {
+        return;
+    }
+    let original = status.reblog.as_deref().unwrap_or(status);
+    if status.reblog.is_some() {
+        println!("{} boosted", author(&status.account.acct));
+    }
+    println!(
+        "{} {} {}",
+        author(&original.account.acct),
+        status_url(original),
+        format_time(original.created_at)
+    );
+    let emojis: Vec<CustomEmoji> = from_entity(&original.emojis).unwrap_or_else(|err| {
+        warn!(?err, "couldn't read custom emoji");
+        Vec::new()
+    });
+    let markdown = format() == OutputFormat::Markdown;
+    if !original.spoiler_text.is_empty() {
+        let warning = format!(
+            "CW: {}",
+            emoji::substitute(
+                &html::strip_controls(&original.spoiler_text),
+                &emojis,
+                markdown
+            )
+        );
+        println!("{}", style(|palette| &palette.content_warning, &warning));
+    }
+    let content = emoji::substitute(&render_html(&original.content), &emojis, markdown);
+    if !content.is_empty() {
+        println!("{content}");
+    }
+    if let Some(poll) = &original.poll {
+        match from_entity(poll) {
+            Ok(poll) => print_poll(&poll, OffsetDateTime::now_utc()),
+            Err(err) => warn!(?err, "couldn't read poll"),
+        }
+    }
+    if let Some(card) = &original.card {
+        match from_entity(card) {
+            Ok(card) => print_card(&card),
+            Err(err) => warn!(?err, "couldn't read preview card"),
+        }
+    }
+    println!();
+}
+
+pub fn print_account(account: &Account) {
+    let account = &*scrub::account(account);
+    if print_data(account) {
+        return;
+    }
+    println!(
+        "{} {}",
+        author(&account.acct),
+        html::strip_controls(&account.url)
+    );
+}
+
+/// Prints who did what, and to which status.
+pub fn print_notification(notification: &Notification) {
+    let notification = &*scrub::notification(notification);
+    if print_data(notification) {
+        return;
+    }
+    let acct = author(&notification.account.acct);
+    let action = notification_action(&notification.kind);
+    match &notification.status {
+        Some(status) => println!("{acct} {action} {}", status_url(status)),
+        None => println!("{acct} {action}"),
+    }
+}
+
+/// Prints notifications in order, like the web UI, with the favourites and
+/// boosts of the same status collapsed into one line where the first of them
+/// was, e.g. `12 people boosted https://...`.
+pub fn print_notifications(notifications: &[Notification], group: bool) {
+    let notifications = &*scrub::notifications(notifications);
+    if !group || is_structured() {
+        notifications.iter().for_each(print_notification);
+        return;
+    }
+    let mut groups: Vec<Vec<&Notification>> = Vec::new();
+    let mut index = HashMap::new();
+    for notification in notifications {
+        let key = notification
+            .status
+            .as_ref()
+            .filter(|_| matches!(notification.kind.as_str(), "favourite" | "reblog"))
+            .map(|status| (notification.kind.as_str(), status.id.to_string()));
+        match key {
+            Some(key) => match index.entry(key) {
+                Entry::Occupied(entry) => groups[*entry.get()].push(notification),
+                Entry::Vacant(entry) => {
+                    entry.insert(groups.len());
+                    groups.push(vec![notification]);
+                }
+            },
+            None => groups.push(vec![notification]),
+        }
+    }
+    for group in groups {
+        // every group has at least one notification
+        match (group.len(), &group[0].status) {
+            (count @ 2.., Some(status)) => println!(
+                "{count} people {} {}",
+                notification_action(&group[0].kind),
+                status_url(status)
+            ),
+            _ => group.into_iter().for_each(print_notification),
+        }
+    }
+}
+
+fn notification_action(kind: &str) -> &str {
+    match kind {
+        "mention" => "mentioned you",
+        "status" => "posted",
+        "reblog" => "boosted",
+        "favourite" => "favourited",
+        "follow" => "followed you",
+        "follow_request" => "requested to follow you",
+        "poll" => "has a poll that ended",
+        "update" => "edited",
+        "admin.sign_up" => "signed up",
+        "admin.report" => "filed a report",
+        kind => kind,
+    }
+}
+
+/// The parts of a poll needed to show its results.
+#[derive(Debug, Deserialize)]
+struct Poll {
+    expires_at: Option<String>,
+    expired: bool,
+    multiple: bool,
+    votes_count: u64,
+    voters_count: Option<u64>,
+    options: Vec<PollOption>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PollOption {
+    title: String,
+    /// Hidden until the poll closes on some instances.
+    votes_count: Option<u64>,
+}
+
+/// Reads the parts of an entity that are needed, from its JSON, as the types
+/// of mastodon-async are missing some of them.
+fn from_entity<T: DeserializeOwned>(entity: &impl Serialize) -> serde_json::Result<T> {
+    serde_json::from_value(serde_json::to_value(entity)?)
+}
+
+impl Poll {
+    /// The number that percentages are relative to. Each voter can pick several
+    /// options in a multiple choice poll, so that uses the number of voters.
+    fn total(&self) -> u64 {
+        if self.multiple {
+            self.voters_count.unwrap_or(self.votes_count)
+        } else {
+            self.votes_count
+        }
+    }
+}
+
+/// Prints a bar chart of the poll results followed by the number of votes and
+/// when the poll closes.
+fn print_poll(poll: &Poll, now: OffsetDateTime) {
+    let total = poll.total();
+    for option in &poll.options {
+        let title = html::strip_controls(&option.title);
+        let Some(votes) = option.votes_count else {
+            println!("  {:BAR_WIDTH$}    ? {title}", "");
+            continue;
+        };
+        #[allow(clippy::cast_precision_loss)] // vote counts are nowhere near 2^52
+        let fraction = if total == 0 {
+            0.0
+        } else {
+            votes as f64 / total as f64
+        };
+        println!(
+            "  {} {:>3.0}% {title} ({votes})",
+            bar(fraction),
+            fraction * 100.0,
+        );
+    }
+    let votes = if poll.multiple {
+        format!("{total} voters")
+    } else {
+        format!("{total} votes")
+    };
+    let expires_at = poll
+        .expires_at
+        .as_deref()
+        .and_then(|expires_at| parse_timestamp(expires_at).ok());
+    match expires_at {
+        _ if poll.expired => println!("  {votes}, closed"),
+        Some(expires_at) if expires_at <= now => println!("  {votes}, closed"),
+        Some(expires_at) => println!("  {votes}, closes in {}", countdown(expires_at - now)),
+        None => println!("  {votes}"),
+    }
+}
+
+/// The parts of a link preview card needed to show it.
+#[derive(Debug, Deserialize)]
+struct Card {
+    url: String,
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    provider_name: String,
+}
+
+/// Prints the title, site, description and URL of a link preview card,
+/// indented like a poll, or as a quote with a link in Markdown.
+fn print_card(card: &Card) {
+    let title = if card.provider_name.is_empty() {
+        html::strip_controls(&card.title)
+    } else {
+        html::strip_controls(&format!("{} ({})", card.title, card.provider_name))
+    };
+    let description = summary(&html::strip_controls(&card.description));
+    let url = html::strip_controls(&card.url);
+    if format() == OutputFormat::Markdown {
+        println!("> [{}]({url})", html::escape_markdown(&title));
+        if !description.is_empty() {
+            println!(">");
+            println!("> {}", html::escape_markdown(&description));
+        }
+        return;
+    }
+    println!("  {title}");
+    if !description.is_empty() {
+        println!("  {description}");
+    }
+    println!("  {url}");
+}
+
+/// The description of a card on one line, shortened to some 200 characters.
+fn summary(description: &str) -> String {
+    const MAX_CHARS: usize = 200;
+    let words = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    if words.chars().count() <= MAX_CHARS {
+        return words;
+    }
+    let mut shortened: String = words.chars().take(MAX_CHARS).collect();
+    shortened.push('…');
+    shortened
+}
+
+fn bar(fraction: f64) -> String {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )] // the fraction is between 0 and 1
+    let filled = (fraction.clamp(0.0, 1.0) * BAR_WIDTH as f64).round() as usize;
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+/// A line of block characters, one per value, scaled so that the largest value
+/// is a full block.
+pub fn sparkline(values: &[u64]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|&value| {
+            // the index is at most 7, so it can't truncate
+            #[allow(clippy::cast_possible_truncation)]
+            let index = (value * 7 / max) as usize;
+            BLOCKS[index]
+        })
+        .collect()
+}
+
+/// Formats the time until a poll closes with its two largest units, e.g.
+/// `2d 4h` or `35m`.
+fn countdown(remaining: time::Duration) -> String {
+    let minutes = remaining.whole_minutes();
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) if minutes == 0 => "less than a minute".to_owned(),
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
@@ -0,0 +1,190 @@
+//! Printing statuses as an Atom feed with `--output atom`, so that feed readers
+//! can follow timelines that need a login.
+//!
+//! The commands that print a timeline name the feed with [`set_feed`]; other
+//! commands get a generic name. Entities other than statuses are left out.
+
+use std::fmt::Write;
+use std::sync::{Mutex, PoisonError};
+
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use super::fields;
+use crate::html::escape;
+use crate::timestamp::{format_timestamp, parse_timestamp};
+
+/// The longest title of an entry, in characters, taken from the start of the
+/// status when it has no content warning.
+const TITLE_LENGTH: usize = 80;
+
+/// The title and web page of the feed, set by the command.
+static FEED: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// Names the feed after the timeline being printed, with the URL of the same
+/// timeline in the web interface, which is also used as the id of the feed.
+pub fn set_feed(title: String, link: String) {
+    *FEED.lock().unwrap_or_else(PoisonError::into_inner) = Some((title, link));
+}
+
+/// The feed of the statuses among the entities, newest first as they were
+/// printed.
+pub fn feed(entities: &[Value]) -> String {
+    let (title, link) = FEED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_else(|| ("Statuses".to_owned(), String::new()));
+    let statuses: Vec<&Value> = entities
+        .iter()
+        .filter(|entity| entity.get("content").is_some())
+        .collect();
+    // the feed changed when its newest entry did
+    let updated = statuses
+        .iter()
+        .filter_map(|status| parse_timestamp(&fields::field(status, "created_at")).ok())
+        .max()
+        .unwrap_or_else(OffsetDateTime::now_utc);
+    let updated = format_timestamp(updated);
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    let id = if link.is_empty() {
+        "urn:spike-mastodon:statuses"
+    } else {
+        &link
+    };
+    element(&mut xml, 1, "id", id);
+    element(&mut xml, 1, "title", &title);
+    if !link.is_empty() {
+        let _ = writeln!(
+            xml,
+            "  <link rel=\"alternate\" href=\"{}\"/>",
+            escape(&link)
+        );
+    }
+    element(&mut xml, 1, "updated", &updated);
+    element(&mut xml, 1, "generator", env!("CARGO_PKG_NAME"));
+    for status in statuses {
+        push_entry(&mut xml, status);
+    }
+    xml.push_str("</feed>");
+    xml
+}
+
+fn push_entry(xml: &mut String, status: &Value) {
+    // a boost is shown as the boosted status, but keeps its own id so that
+    // boosting the same status again is a new entry
+    let original = status.get("reblog").filter(|reblog| !reblog.is_null());
+    let original = original.unwrap_or(status);
+    let author = fields::field(original, "account.display_name");
+    let author = if author.is_empty() {
+        fields::acct(original)
+    } else {
+        author
+    };
+    let warning = fields::field(original, "spoiler_text");
+    let title = if warning.is_empty() {
+        summary_title(&fields::content_text(original))
+    } else {
+        warning.clone()
+    };
+
+    xml.push_str("  <entry>\n");
+    element(xml, 2, "id", &fields::field(status, "uri"));
+    element(xml, 2, "title", &title);
+    let _ = writeln!(
+        xml,
+        "    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>",
+        escape(&fields::url(original))
+    );
+    xml.push_str("    <author>\n");
+    element(xml, 3, "name", &author);
+    element(xml, 3, "uri", &fields::field(original, "account.url"));
+    xml.push_str("    </author>\n");
+    let published = timestamp(status, "created_at");
+    let updated = match original.get("edited_at") {
+        Some(edited_at) if !edited_at.is_null() => timestamp(original, "edited_at"),
+        _ => published.clone(),
+    };
+    element(xml, 2, "published", &published);
+    element(xml, 2, "updated", &updated);
+    if !warning.is_empty() {
+        element(xml, 2, "summary", &warning);
+    }
+    let media = original
+        .get("media_attachments")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten();
+    for attachment in media {
+        let _ = writeln!(
+            xml,
+            "    <link rel=\"enclosure\" href=\"{}\"/>",
+            escape(&fields::field(attachment, "url"))
+        );
+    }
+    let _ = writeln!(
+        xml,
+        "    <content type=\"html\">{}</content>",
+        escape(&fields::field(original, "content"))
+    );
+    xml.push_str("  </entry>\n");
+}
+
+/// The start of the text, cut at a word if it's too long for a title.
+fn summary_title(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() <= TITLE_LENGTH {
+        return line.to_owned();
+    }
+    let cut: String = line.chars().take(TITLE_LENGTH).collect();
+    let cut = cut
+        .rsplit_once(' ')
+        .map_or(cut.as_str(), |(start, _)| start);
+    format!("{cut}…")
+}
+
+/// A timestamp of the entity in RFC 3339 form, as Atom requires.
+fn timestamp(entity: &Value, name: &str) -> String {
+    let text = fields::field(entity, name);
+    parse_timestamp(&text).map_or(text, format_timestamp)
+}
+
+/// Adds an element with text, indented by two spaces per level.
+fn element(xml: &mut String, depth: usize, name: &str, text: &str) {
+    let _ = writeln!(
+        xml,
+        "{:indent$}<{name}>{}</{name}>",
+        "",
+        escape(text),
+        indent = depth * 2
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn element_escapes_its_text() {
+        let mut xml = String::new();
+        element(&mut xml, 2, "title", "<b>AT&T</b>");
+        assert_eq!(xml, "    <title>&lt;b&gt;AT&amp;T&lt;/b&gt;</title>\n");
+    }
+
+    #[test]
+    fn summary_title_keeps_short_first_lines() {
+        assert_eq!(summary_title("first line\nsecond line"), "first line");
+        assert_eq!(summary_title(""), "");
+    }
+
+    #[test]
+    fn summary_title_cuts_long_lines_at_a_word() {
+        let text = "word ".repeat(20);
+        let title = summary_title(&text);
+        assert!(title.ends_with("word…"), "{title}");
+        assert!(title.chars().count() <= TITLE_LENGTH + 1, "{title}");
+    }
+}
@@ -0,0 +1,135 @@
+//! Colors for the terminal output.
+//!
+//! Colors are used when stdout is a terminal, unless the `NO_COLOR`
+//! environment variable is set (see <https://no-color.org>) or `--color` says
+//! otherwise. The `theme` section of the config file picks the style of each
+//! part of a status, as words separated by spaces:
+//!
+//! ```toml
+//! [theme]
+//! author = "bold"
+//! content_warning = "dim"
+//! hashtag = "cyan"
+//! mention = "bright-blue"
+//! ```
+//!
+//! The words are `bold`, `dim`, `italic` and `underline`, and the colors
+//! `black`, `red`, `green`, `yellow`, `blue`, `magenta`, `cyan` and `white`,
+//! optionally prefixed with `bright-`. An empty style turns the color off.
+
+use std::env;
+use std::io;
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use is_terminal::IsTerminal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Use colors when printing to a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    /// Always use colors, e.g. when piping to `less -R`
+    Always,
+    /// Never use colors
+    Never,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Theme {
+    pub author: String,
+    pub content_warning: String,
+    pub hashtag: String,
+    pub mention: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            author: "bold".to_owned(),
+            content_warning: "dim".to_owned(),
+            hashtag: "cyan".to_owned(),
+            mention: "blue".to_owned(),
+        }
+    }
+}
+
+/// The escape sequences of a theme, empty for the parts that aren't styled.
+#[derive(Debug, Clone, Default)]
+pub struct Palette {
+    pub author: String,
+    pub content_warning: String,
+    pub hashtag: String,
+    pub mention: String,
+}
+
+impl Palette {
+    /// The palette to print with, or `None` if colors shouldn't be used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the theme has a style that isn't known.
+    pub fn new(choice: ColorChoice, theme: &Theme) -> Result<Option<Self>> {
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                io::stdout().is_terminal()
+                    && env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+                    && env::var_os("TERM").map_or(true, |term| term != "dumb")
+            }
+        };
+        if !enabled {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            author: escape_sequence(&theme.author)?,
+            content_warning: escape_sequence(&theme.content_warning)?,
+            hashtag: escape_sequence(&theme.hashtag)?,
+            mention: escape_sequence(&theme.mention)?,
+        }))
+    }
+}
+
+/// Wraps the text in an escape sequence and a reset, unless the sequence is
+/// empty.
+pub fn paint(sequence: &str, text: &str) -> String {
+    if sequence.is_empty() {
+        text.to_owned()
+    } else {
+        format!("{sequence}{text}\x1b[0m")
+    }
+}
+
+/// The escape sequence for a style like `bold bright-red`.
+fn escape_sequence(style: &str) -> Result<String> {
+    const COLORS: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    let mut codes = Vec::new();
+    for word in style.split_whitespace() {
+        let code = match word {
+            "bold" => 1,
+            "dim" => 2,
+            "italic" => 3,
+            "underline" => 4,
+            _ => {
+                let (base, color) = match word.strip_prefix("bright-") {
+                    Some(color) => (90, color),
+                    None => (30, word),
+                };
+                let Some(index) = COLORS.iter().position(|&known| known == color) else {
+                    bail!("unknown style {word:?} in the theme");
+                };
+                base + index
+            }
+        };
+        codes.push(code.to_string());
+    }
+    if codes.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format!("\x1b[{}m", codes.join(";")))
+}
@@ -0,0 +1,88 @@
+//! Printing entities as CSV, one row per entity with the columns given by
+//! `--columns`.
+//!
+//! A column is a field of the entity as the instance sent it, e.g. `id` or
+//! `favourites_count`, with `.` between nested fields, e.g.
+//! `account.display_name`. A few columns work for every type of entity:
+//!
+//! - `acct`: the address of the author, or of the account itself
+//! - `content`: the text of the status, or of the account's bio
+//! - `url`: the URL of the status, or of the account's profile
+//!
+//! Fields that an entity doesn't have are left empty.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use serde_json::Value;
+
+use super::fields;
+
+/// The columns to print, set once from the command line.
+static COLUMNS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Whether the header row has been printed, which is done before the first
+/// entity so that nothing is printed if there are none.
+static HEADER_PRINTED: AtomicBool = AtomicBool::new(false);
+
+/// Sets the columns to print for the rest of the process.
+pub fn set_columns(columns: Vec<String>) {
+    *COLUMNS.lock().unwrap_or_else(PoisonError::into_inner) = columns;
+}
+
+/// Prints the entity as a row, after the header row if this is the first one.
+pub fn print_row(entity: &Value) {
+    let columns = COLUMNS.lock().unwrap_or_else(PoisonError::into_inner);
+    if !HEADER_PRINTED.swap(true, Ordering::Relaxed) {
+        println!("{}", row(columns.iter().cloned()));
+    }
+    println!("{}", row(columns.iter().map(|column| cell(entity, column))));
+}
+
+fn row(cells: impl Iterator<Item = String>) -> String {
+    cells
+        .map(|cell| escape(&cell))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn cell(entity: &Value, column: &str) -> String {
+    match column {
+        "acct" => fields::acct(entity),
+        "content" => fields::content_text(entity),
+        "url" => fields::url(entity),
+        column => fields::field(entity, column),
+    }
+}
+
+/// Quotes a cell if it has a separator, quote or line break in it.
+pub fn escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_plain_cells_alone() {
+        assert_eq!(escape("alice@example.com"), "alice@example.com");
+        assert_eq!(escape(""), "");
+    }
+
+    #[test]
+    fn quotes_separators_and_line_breaks() {
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("a\nb"), "\"a\nb\"");
+        assert_eq!(escape("a\rb"), "\"a\rb\"");
+    }
+
+    #[test]
+    fn doubles_quotes() {
+        assert_eq!(escape(r#"say "hi""#), r#""say ""hi""""#);
+    }
+}
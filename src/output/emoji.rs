@@ -0,0 +1,174 @@
+//! Custom emoji in the content of statuses, which is HTML with the emoji left
+//! as their `:shortcode:`.
+//!
+//! `--emoji` or the `emoji` section of the config file picks what to print
+//! instead, for text and Markdown separately:
+//!
+//! ```toml
+//! [emoji]
+//! text = "unicode"
+//! markdown = "image"
+//! ```
+//!
+//! `unicode` swaps emoji named like a standard one, e.g. `:thumbsup:` or
+//! `:blobheart:`, for that emoji. `image` links the image in Markdown, and
+//! draws it in text when `timeline --preview-images` can draw images, see
+//! [`crate::preview`]. Anything else keeps its shortcode.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmojiMode {
+    /// Keep the `:shortcode:`
+    #[default]
+    Keep,
+    /// Use the standard emoji with the same name, if there is one
+    Unicode,
+    /// Show the image of the emoji
+    Image,
+}
+
+/// The emoji mode of each output format.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmojiConfig {
+    pub text: EmojiMode,
+    pub markdown: EmojiMode,
+}
+
+/// The parts of a custom emoji needed to show it.
+#[derive(Debug, Deserialize)]
+pub struct CustomEmoji {
+    pub shortcode: String,
+    pub url: String,
+    /// A still version of animated emoji.
+    pub static_url: String,
+}
+
+static MODE: Mutex<EmojiMode> = Mutex::new(EmojiMode::Keep);
+
+/// The escape sequences that draw emoji images in the terminal, by their
+/// static URL, added by [`crate::preview`] before the statuses are printed.
+static IMAGES: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+/// Sets what custom emoji are printed as for the rest of the process.
+pub fn set_emoji_mode(mode: EmojiMode) {
+    *MODE.lock().unwrap_or_else(PoisonError::into_inner) = mode;
+}
+
+pub fn emoji_mode() -> EmojiMode {
+    *MODE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether the escape sequence of an emoji image is known already.
+pub fn has_emoji_image(static_url: &str) -> bool {
+    IMAGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map_or(false, |images| images.contains_key(static_url))
+}
+
+/// Saves the escape sequence that draws an emoji image in the terminal.
+pub fn set_emoji_image(static_url: String, escape: String) {
+    IMAGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(HashMap::new)
+        .insert(static_url, escape);
+}
+
+/// Replaces the shortcodes of the custom emoji in rendered text.
+pub fn substitute(text: &str, emojis: &[CustomEmoji], markdown: bool) -> String {
+    let mode = emoji_mode();
+    if mode == EmojiMode::Keep || emojis.is_empty() {
+        return text.to_owned();
+    }
+    let images = IMAGES.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut text = text.to_owned();
+    for emoji in emojis {
+        let shortcode = format!(":{}:", emoji.shortcode);
+        // Markdown escapes underscores at the edges of words, such as at the
+        // start of a shortcode
+        let escaped = format!(":{}:", crate::html::escape_markdown(&emoji.shortcode));
+        let replacement = match mode {
+            EmojiMode::Keep => None,
+            EmojiMode::Unicode => unicode(&emoji.shortcode).map(str::to_owned),
+            EmojiMode::Image if markdown => Some(format!("![{shortcode}]({})", emoji.url)),
+            EmojiMode::Image => images
+                .as_ref()
+                .and_then(|images| images.get(&emoji.static_url))
+                .cloned(),
+        };
+        if let Some(replacement) = replacement {
+            if markdown && escaped != shortcode {
+                text = text.replace(&escaped, &replacement);
+            }
+            text = text.replace(&shortcode, &replacement);
+        }
+    }
+    text
+}
+
+/// The standard emoji that a custom emoji is named after, ignoring the common
+/// prefixes of emoji sets, e.g. `blob` in `blobheart`.
+fn unicode(shortcode: &str) -> Option<&'static str> {
+    const PREFIXES: [&str; 3] = ["blob", "meow", "neko"];
+    const EMOJI: [(&str, &str); 40] = [
+        ("100", "💯"),
+        ("angry", "😠"),
+        ("blush", "😊"),
+        ("check", "✅"),
+        ("clap", "👏"),
+        ("cool", "😎"),
+        ("cry", "😢"),
+        ("eyes", "👀"),
+        ("fire", "🔥"),
+        ("grin", "😁"),
+        ("hug", "🤗"),
+        ("heart", "❤️"),
+        ("joy", "😂"),
+        ("laugh", "😆"),
+        ("love", "😍"),
+        ("nervous", "😬"),
+        ("ok", "👌"),
+        ("party", "🥳"),
+        ("pray", "🙏"),
+        ("rainbow", "🌈"),
+        ("sad", "😞"),
+        ("scream", "😱"),
+        ("shrug", "🤷"),
+        ("sleep", "😴"),
+        ("smile", "😄"),
+        ("sob", "😭"),
+        ("sparkles", "✨"),
+        ("star", "⭐"),
+        ("sweat", "😅"),
+        ("think", "🤔"),
+        ("thinking", "🤔"),
+        ("thumbsdown", "👎"),
+        ("thumbsup", "👍"),
+        ("tada", "🎉"),
+        ("upsidedown", "🙃"),
+        ("wave", "👋"),
+        ("wink", "😉"),
+        ("x", "❌"),
+        ("yay", "🎉"),
+        ("zzz", "💤"),
+    ];
+    let name = shortcode.to_ascii_lowercase().replace(['-', '_'], "");
+    let name = PREFIXES
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(&name);
+    EMOJI
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, emoji)| *emoji)
+}
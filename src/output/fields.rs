@@ -0,0 +1,46 @@
+//! The fields that every type of entity has in some form, for CSV columns and
+//! templates.
+
+use serde_json::Value;
+
+use crate::html;
+
+/// The address of the author, or of the account itself.
+pub fn acct(entity: &Value) -> String {
+    first(entity, &["/account/acct", "/acct"])
+}
+
+/// The text of the status, or of the account's bio. Boosts have no content of
+/// their own, and notifications have the content of their status.
+pub fn content_text(entity: &Value) -> String {
+    let content = first(
+        entity,
+        &["/reblog/content", "/content", "/status/content", "/note"],
+    );
+    html::to_text(&content)
+}
+
+/// The URL of the status, or of the account's profile.
+pub fn url(entity: &Value) -> String {
+    first(entity, &["/url", "/uri", "/status/url", "/status/uri"])
+}
+
+/// A field as the instance sent it, with `.` between nested fields, e.g.
+/// `account.display_name`. Fields that the entity doesn't have are empty.
+pub fn field(entity: &Value, path: &str) -> String {
+    first(entity, &[&format!("/{}", path.replace('.', "/"))])
+}
+
+/// The first of the fields that the entity has, as text.
+fn first(entity: &Value, pointers: &[&str]) -> String {
+    pointers
+        .iter()
+        .filter_map(|pointer| entity.pointer(pointer))
+        .find(|value| !value.is_null())
+        .map(|value| match value {
+            Value::String(text) => text.clone(),
+            // numbers and booleans, and lists and objects as JSON
+            value => value.to_string(),
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,70 @@
+//! Printing entities with a template given by `--template`, e.g.
+//! `'{{ acct }}: {{ content_text }} ({{ url }})'`.
+//!
+//! Templates use the [minijinja] syntax and see every field of the entity as
+//! the instance sent it, e.g. `{{ account.display_name }}` or
+//! `{{ favourites_count }}`, along with the fields that every type of entity
+//! has in some form:
+//!
+//! - `acct`: the address of the author, or of the account itself
+//! - `content_text`: the text of the status, or of the account's bio
+//! - `url`: the URL of the status, or of the account's profile
+//!
+//! [minijinja]: https://docs.rs/minijinja
+
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{Context, Result};
+use minijinja::Environment;
+use serde_json::Value;
+use tracing::warn;
+
+use super::fields;
+
+/// The template to print with, set once from the command line.
+static TEMPLATE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Sets the template to print with for the rest of the process, checking its
+/// syntax first so that a mistake is reported before anything is fetched.
+///
+/// # Errors
+///
+/// Returns an error if the template can't be parsed.
+pub fn set_template(template: Option<String>) -> Result<()> {
+    if let Some(template) = &template {
+        Environment::new()
+            .template_from_str(template)
+            .context("Couldn't parse the template")?;
+    }
+    *TEMPLATE.lock().unwrap_or_else(PoisonError::into_inner) = template;
+    Ok(())
+}
+
+pub fn is_set() -> bool {
+    TEMPLATE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_some()
+}
+
+/// Prints the entity with the template, if one is set.
+pub fn print(mut entity: Value) {
+    let template = TEMPLATE.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(template) = template.as_deref() else {
+        return;
+    };
+    let common = [
+        ("acct", fields::acct(&entity)),
+        ("content_text", fields::content_text(&entity)),
+        ("url", fields::url(&entity)),
+    ];
+    if let Value::Object(map) = &mut entity {
+        for (name, value) in common {
+            map.insert(name.to_owned(), Value::String(value));
+        }
+    }
+    match Environment::new().render_str(template, entity) {
+        Ok(text) => println!("{text}"),
+        Err(err) => warn!(?err, "couldn't render the template"),
+    }
+}
@@ -0,0 +1,91 @@
+//! How times are printed in the text output, with `--time` or `time` in the
+//! config file.
+
+use std::sync::{Mutex, PoisonError};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use time::macros::format_description;
+use time::{Duration, OffsetDateTime, UtcOffset};
+use tracing::debug;
+
+use crate::timestamp::{format_timestamp, parse_timestamp};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeFormat {
+    /// How long ago, e.g. `3m ago`
+    Relative,
+    /// The date and time in the local time zone, e.g. `2024-06-01 11:00`
+    Local,
+    /// The date and time in UTC, e.g. `2024-06-01 09:00 UTC`
+    Utc,
+    /// An ISO 8601 timestamp, e.g. `2024-06-01T09:00:00Z`
+    #[default]
+    Iso,
+}
+
+static FORMAT: Mutex<TimeFormat> = Mutex::new(TimeFormat::Iso);
+
+/// The offset of the local time zone, which is read once at startup.
+static LOCAL_OFFSET: Mutex<UtcOffset> = Mutex::new(UtcOffset::UTC);
+
+/// Sets how times are printed for the rest of the process.
+pub fn set_time_format(format: TimeFormat) {
+    *FORMAT.lock().unwrap_or_else(PoisonError::into_inner) = format;
+}
+
+/// Reads the offset of the local time zone, falling back to UTC. This has to
+/// be called before any other thread is started, as the time zone can't be
+/// read safely once there are several.
+pub fn detect_local_offset() {
+    match UtcOffset::current_local_offset() {
+        Ok(offset) => *LOCAL_OFFSET.lock().unwrap_or_else(PoisonError::into_inner) = offset,
+        Err(err) => debug!(?err, "couldn't read the local time zone, using UTC"),
+    }
+}
+
+/// Formats a time from an entity, which is printed as is if it isn't a
+/// timestamp.
+pub fn format_time_text(text: &str) -> String {
+    parse_timestamp(text).map_or_else(|_| text.to_owned(), format_time)
+}
+
+/// Formats a time with the `--time` format.
+pub fn format_time(at: OffsetDateTime) -> String {
+    let format = *FORMAT.lock().unwrap_or_else(PoisonError::into_inner);
+    let minutes = format_description!("[year]-[month]-[day] [hour]:[minute]");
+    match format {
+        TimeFormat::Relative => relative(OffsetDateTime::now_utc() - at),
+        TimeFormat::Local => {
+            let offset = *LOCAL_OFFSET.lock().unwrap_or_else(PoisonError::into_inner);
+            at.to_offset(offset)
+                .format(minutes)
+                .unwrap_or_else(|_| format_timestamp(at))
+        }
+        TimeFormat::Utc => at
+            .to_offset(UtcOffset::UTC)
+            .format(minutes)
+            .map_or_else(|_| format_timestamp(at), |text| format!("{text} UTC")),
+        TimeFormat::Iso => format_timestamp(at.to_offset(UtcOffset::UTC)),
+    }
+}
+
+/// Formats how long ago something was with its largest unit, e.g. `3m ago`,
+/// or how long until it is for times in the future, e.g. `in 2h`.
+fn relative(elapsed: Duration) -> String {
+    let seconds = elapsed.whole_seconds().abs();
+    let amount = match seconds {
+        0..=59 => return "just now".to_owned(),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86_399 => format!("{}h", seconds / 3600),
+        86_400..=604_799 => format!("{}d", seconds / 86_400),
+        604_800..=31_535_999 => format!("{}w", seconds / 604_800),
+        _ => format!("{}y", seconds / 31_536_000),
+    };
+    if elapsed.is_negative() {
+        format!("in {amount}")
+    } else {
+        format!("{amount} ago")
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use mastodon_async::prelude::Status;
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+use crate::client::Client;
+use crate::dedup::SeenStatuses;
+use crate::storage;
+
+/// Collects the statuses of an endpoint that is paged with the `Link` header,
+/// e.g. `v1/bookmarks`, and with `all` of every page after the first.
+pub async fn collect(client: &Client, path: &str, all: bool) -> Result<Vec<Status>> {
+    let mut statuses = Vec::new();
+    for_each_status(client, path, all, |status| statuses.push(status)).await?;
+    Ok(statuses)
+}
+
+/// Calls `f` with each of the statuses of [`collect`], a page at a time so that
+/// they can be printed as they arrive.
+pub async fn for_each_status(
+    client: &Client,
+    path: &str,
+    all: bool,
+    mut f: impl FnMut(Status),
+) -> Result<()> {
+    // statuses can show up on both sides of a page boundary
+    let mut seen = SeenStatuses::new();
+    for_each_page(client, path, all, |statuses| {
+        seen.retain_unseen(statuses).into_iter().for_each(&mut f);
+    })
+    .await
+}
+
+/// Collects the items of an endpoint that is paged with the `Link` header,
+/// e.g. `v1/blocks`, and with `all` of every page after the first.
+pub async fn collect_items<T: DeserializeOwned>(
+    client: &Client,
+    path: &str,
+    all: bool,
+) -> Result<Vec<T>> {
+    let mut items = Vec::new();
+    for_each_item(client, path, all, |item| items.push(item)).await?;
+    Ok(items)
+}
+
+/// Calls `f` with each of the items of [`collect_items`], a page at a time so
+/// that they can be printed as they arrive.
+pub async fn for_each_item<T: DeserializeOwned>(
+    client: &Client,
+    path: &str,
+    all: bool,
+    mut f: impl FnMut(T),
+) -> Result<()> {
+    for_each_page(client, path, all, |items| {
+        items.into_iter().for_each(&mut f)
+    })
+    .await
+}
+
+/// Pages through an endpoint [`Client::page_limit`] items at a time, which the
+/// helpers of mastodon-async can't do as they leave out the `limit`.
+async fn for_each_page<T: DeserializeOwned>(
+    client: &Client,
+    path: &str,
+    all: bool,
+    mut f: impl FnMut(Vec<T>),
+) -> Result<()> {
+    let (items, mut next) = client
+        .get_page::<T>(path, &[("limit", client.page_limit())])
+        .await?;
+    f(items);
+    while let Some(url) = next.filter(|_| all) {
+        let (items, page_next) = client
+            .get_next_page::<T>(&url)
+            .await
+            .context("Couldn't get next page")?;
+        if items.is_empty() {
+            break;
+        }
+        debug!(count = items.len(), "loaded next page");
+        f(items);
+        next = page_next;
+    }
+    Ok(())
+}
+
+/// The statuses posted by an account, newest first, filtered by the extra query
+/// parameters of the endpoint, e.g. `("exclude_replies", "true")`. This pages
+/// with `max_id` directly, as mastodon-async doesn't support the filters.
+pub async fn account_statuses(
+    client: &Client,
+    account_id: &str,
+    filters: &[(&str, &str)],
+    all: bool,
+) -> Result<Vec<Status>> {
+    let mut statuses = Vec::new();
+    for_each_account_status(client, account_id, filters, all, |status| {
+        statuses.push(status);
+    })
+    .await?;
+    Ok(statuses)
+}
+
+/// Calls `f` with each of the statuses of [`account_statuses`], a page at a
+/// time so that they can be printed as they arrive.
+pub async fn for_each_account_status(
+    client: &Client,
+    account_id: &str,
+    filters: &[(&str, &str)],
+    all: bool,
+    mut f: impl FnMut(Status),
+) -> Result<()> {
+    let path = format!("v1/accounts/{account_id}/statuses");
+    let mut seen = SeenStatuses::new();
+    let mut max_id: Option<String> = None;
+    loop {
+        let mut query = vec![("limit", client.page_limit())];
+        query.extend_from_slice(filters);
+        if let Some(max_id) = &max_id {
+            query.push(("max_id", max_id.as_str()));
+        }
+        let page: Vec<Status> = client
+            .get(&path, &query)
+            .await
+            .context("Couldn't get statuses")?;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        max_id = Some(oldest.id.to_string());
+        debug!(count = page.len(), ?max_id, "loaded older page");
+        storage::remember(client, &page);
+        seen.retain_unseen(page).into_iter().for_each(&mut f);
+        if !all {
+            break;
+        }
+    }
+    Ok(())
+}
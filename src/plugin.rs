@@ -0,0 +1,285 @@
+//! Experimental plugins, compiled to WebAssembly.
+//!
+//! Plugins run without any imports, so they can't touch the filesystem or the
+//! network; they only see the data that is passed to them. Plugins are listed
+//! in the config file and are applied to the statuses shown by `timeline`:
+//!
+//! ```toml
+//! plugins = ["/path/to/plugin.wasm"]
+//! ```
+//!
+//! A plugin module exports `memory` and `alloc(len: u32) -> u32`, which
+//! returns a pointer to `len` bytes that the host can write a JSON status
+//! into, plus any of these functions:
+//!
+//! - `filter(ptr: u32, len: u32) -> u32` returns 0 to hide the status
+//! - `transform(ptr: u32, len: u32) -> u64` returns a replacement JSON status
+//! - `generate() -> u64` returns the text of a status to post
+//!
+//! Functions returning `u64` pack a pointer into the upper 32 bits and a length
+//! into the lower 32 bits. This is only available when built with the
+//! `plugins` feature.
+//!
+//! Each call to a plugin can run about a billion instructions, and a plugin can
+//! use at most 64 MiB of memory. A plugin that goes over either limit fails the
+//! call, and with it the command, rather than hanging it.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Status;
+use tracing::instrument;
+
+use crate::client::Client;
+use crate::config::Config;
+use crate::post::{self, StatusOptions};
+
+#[cfg(feature = "plugins")]
+use wasm::Plugin;
+
+#[derive(Debug, Args)]
+pub struct PluginArgs {
+    #[command(subcommand)]
+    command: PluginCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum PluginCommand {
+    /// Post the status generated by a plugin
+    Post {
+        /// The path of the plugin
+        path: PathBuf,
+    },
+}
+
+pub async fn run(client: &Client, config: &Config, args: PluginArgs) -> Result<()> {
+    match args.command {
+        PluginCommand::Post { path } => generate_post(client, config, &path).await,
+    }
+}
+
+#[instrument(skip(client, config), err)]
+async fn generate_post(client: &Client, config: &Config, path: &Path) -> Result<()> {
+    let text = generate(path)?;
+    post::send(client, config, text, StatusOptions::default()).await
+}
+
+/// The plugins listed in the config file, in order.
+#[derive(Default)]
+pub struct Plugins {
+    #[cfg(feature = "plugins")]
+    plugins: Vec<Plugin>,
+}
+
+#[cfg(feature = "plugins")]
+impl Plugins {
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        let plugins = paths
+            .iter()
+            .map(|path| Plugin::load(path))
+            .collect::<Result<_>>()?;
+        Ok(Self { plugins })
+    }
+
+    /// Runs the statuses through each plugin's filter and transform functions.
+    pub fn apply(&mut self, mut statuses: Vec<Status>) -> Result<Vec<Status>> {
+        for plugin in &mut self.plugins {
+            let mut kept = Vec::with_capacity(statuses.len());
+            for status in statuses {
+                if plugin.filter(&status)? {
+                    kept.push(plugin.transform(status)?);
+                }
+            }
+            statuses = kept;
+        }
+        Ok(statuses)
+    }
+}
+
+#[cfg(feature = "plugins")]
+fn generate(path: &Path) -> Result<String> {
+    use anyhow::Context;
+    Plugin::load(path)?
+        .generate()?
+        .with_context(|| format!("{path:?} doesn't export a generate function"))
+}
+
+#[cfg(not(feature = "plugins"))]
+impl Plugins {
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        anyhow::ensure!(
+            paths.is_empty(),
+            "plugins are configured, but this was built without the plugins feature"
+        );
+        Ok(Self {})
+    }
+
+    // keeps the same signature as the real implementation
+    #[allow(
+        clippy::unnecessary_wraps,
+        clippy::unused_self,
+        clippy::needless_pass_by_ref_mut
+    )]
+    pub fn apply(&mut self, statuses: Vec<Status>) -> Result<Vec<Status>> {
+        Ok(statuses)
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+fn generate(_path: &Path) -> Result<String> {
+    anyhow::bail!("this was built without the plugins feature")
+}
+
+#[cfg(feature = "plugins")]
+mod wasm {
+    use std::path::Path;
+
+    use anyhow::{Context, Result};
+    use mastodon_async::prelude::Status;
+    use tracing::debug;
+    use wasmtime::{
+        Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Trap,
+        TypedFunc, WasmParams, WasmResults,
+    };
+
+    /// The fuel of each call, which wasmtime uses up at about one unit per
+    /// instruction.
+    const FUEL: u64 = 1_000_000_000;
+
+    /// The most memory that a plugin can grow to.
+    const MEMORY: usize = 64 << 20;
+
+    pub struct Plugin {
+        store: Store<StoreLimits>,
+        instance: Instance,
+        memory: Memory,
+        alloc: TypedFunc<u32, u32>,
+    }
+
+    impl Plugin {
+        pub fn load(path: &Path) -> Result<Self> {
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            let engine = Engine::new(&config)?;
+            let module = Module::from_file(&engine, path)
+                .with_context(|| format!("cannot load plugin {path:?}"))?;
+            let limits = StoreLimitsBuilder::new()
+                .memory_size(MEMORY)
+                .instances(1)
+                .trap_on_grow_failure(true)
+                .build();
+            let mut store = Store::new(&engine, limits);
+            store.limiter(|limits| limits);
+            // the start function of the module runs when it is instantiated
+            refuel(&mut store)?;
+            // no imports, so the plugin has no access to anything outside
+            // of its own memory
+            let instance = Instance::new(&mut store, &module, &[])
+                .map_err(limit_error)
+                .with_context(|| format!("cannot instantiate plugin {path:?}"))?;
+            let memory = instance
+                .get_memory(&mut store, "memory")
+                .with_context(|| format!("plugin {path:?} doesn't export memory"))?;
+            let alloc = instance
+                .get_typed_func(&mut store, "alloc")
+                .with_context(|| format!("plugin {path:?} doesn't export alloc"))?;
+            debug!(?path, "loaded plugin");
+            Ok(Self {
+                store,
+                instance,
+                memory,
+                alloc,
+            })
+        }
+
+        pub fn filter(&mut self, status: &Status) -> Result<bool> {
+            let Ok(filter) = self
+                .instance
+                .get_typed_func::<(u32, u32), u32>(&mut self.store, "filter")
+            else {
+                return Ok(true);
+            };
+            let (ptr, len) = self.write(&serde_json::to_vec(status)?)?;
+            Ok(call(&mut self.store, &filter, (ptr, len))? != 0)
+        }
+
+        pub fn transform(&mut self, status: Status) -> Result<Status> {
+            let Ok(transform) = self
+                .instance
+                .get_typed_func::<(u32, u32), u64>(&mut self.store, "transform")
+            else {
+                return Ok(status);
+            };
+            let (ptr, len) = self.write(&serde_json::to_vec(&status)?)?;
+            let packed = call(&mut self.store, &transform, (ptr, len))?;
+            let json = self.read(packed)?;
+            serde_json::from_slice(&json).context("plugin returned an invalid status")
+        }
+
+        pub fn generate(&mut self) -> Result<Option<String>> {
+            let Ok(generate) = self
+                .instance
+                .get_typed_func::<(), u64>(&mut self.store, "generate")
+            else {
+                return Ok(None);
+            };
+            let packed = call(&mut self.store, &generate, ())?;
+            let text = String::from_utf8(self.read(packed)?)
+                .context("plugin returned text that isn't UTF-8")?;
+            Ok(Some(text))
+        }
+
+        /// Copies bytes into memory allocated by the plugin.
+        fn write(&mut self, bytes: &[u8]) -> Result<(u32, u32)> {
+            let len = u32::try_from(bytes.len()).context("status is too large")?;
+            let ptr = call(&mut self.store, &self.alloc, len)?;
+            self.memory
+                .write(&mut self.store, usize::try_from(ptr)?, bytes)
+                .context("plugin allocated memory out of bounds")?;
+            Ok((ptr, len))
+        }
+
+        /// Copies bytes out of the plugin's memory given a packed pointer and
+        /// length.
+        fn read(&mut self, packed: u64) -> Result<Vec<u8>> {
+            let ptr = usize::try_from(packed >> 32)?;
+            let len = usize::try_from(packed & u64::from(u32::MAX))?;
+            let mut bytes = vec![0; len];
+            self.memory
+                .read(&self.store, ptr, &mut bytes)
+                .context("plugin returned memory out of bounds")?;
+            Ok(bytes)
+        }
+    }
+
+    /// Calls a function of a plugin with a fresh supply of fuel.
+    fn call<Params, Results>(
+        store: &mut Store<StoreLimits>,
+        func: &TypedFunc<Params, Results>,
+        params: Params,
+    ) -> Result<Results>
+    where
+        Params: WasmParams,
+        Results: WasmResults,
+    {
+        refuel(store)?;
+        func.call(store, params).map_err(limit_error)
+    }
+
+    /// Tops the fuel of the store up to [`FUEL`], whatever the last call left.
+    fn refuel(store: &mut Store<StoreLimits>) -> Result<()> {
+        let remaining = store.consume_fuel(0)?;
+        store.add_fuel(FUEL.saturating_sub(remaining))
+    }
+
+    /// Explains the error of a plugin that ran out of fuel. Going over the
+    /// memory limit already fails with an error saying so.
+    fn limit_error(err: anyhow::Error) -> anyhow::Error {
+        if err.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) {
+            err.context("the plugin ran for too long")
+        } else {
+            err
+        }
+    }
+}
@@ -0,0 +1,461 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{ensure, Context, Result};
+use clap::{Args, ValueEnum};
+use isolang::Language;
+use mastodon_async::polling_time::PollingTime;
+use mastodon_async::prelude::{AttachmentId, Status, StatusId, Visibility};
+use mastodon_async::{NewStatus, StatusBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, instrument, warn};
+
+use crate::client::Client;
+use crate::config::Config;
+use crate::duration::parse_duration;
+use crate::instance::StatusesConfiguration;
+use crate::split::split_thread;
+use crate::{cw, instance, metrics, queue, schedule};
+
+#[derive(Debug, Args)]
+pub struct PostArgs {
+    /// The text of the status, which can be left out for a status with media
+    /// or a poll
+    #[arg(
+        required_unless_present_any = ["stdin", "file", "media", "media_ids", "poll_options"],
+        conflicts_with_all = ["stdin", "file"]
+    )]
+    text: Option<String>,
+
+    /// Read the text from stdin. Text that is too long for one status is
+    /// posted as a numbered thread.
+    #[arg(long, conflicts_with = "file")]
+    stdin: bool,
+
+    /// Read the text from a file. Text that is too long for one status is
+    /// posted as a numbered thread.
+    #[arg(long, value_name = "PATH")]
+    file: Option<PathBuf>,
+
+    #[command(flatten)]
+    options: StatusOptions,
+
+    /// Add the content warning suggested by the configured trigger topics,
+    /// instead of only suggesting it
+    #[arg(long, conflicts_with = "cw")]
+    auto_cw: bool,
+}
+
+/// The options that can be set on any new status.
+#[derive(Debug, Default, Args, Serialize, Deserialize)]
+pub struct StatusOptions {
+    /// Who can see the status [default: the account's default visibility]
+    #[arg(long, value_enum)]
+    visibility: Option<PostVisibility>,
+
+    /// A content warning, shown in place of the text until expanded
+    #[arg(long, value_name = "SPOILER TEXT")]
+    pub cw: Option<String>,
+
+    /// The language of the text as an ISO 639 code, e.g. `en` or `deu`
+    #[arg(long, value_name = "CODE", value_parser = parse_language)]
+    language: Option<Language>,
+
+    /// Mark the status as sensitive
+    #[arg(long)]
+    sensitive: bool,
+
+    /// Attach an image, video or audio file. Can be repeated up to the
+    /// instance's limit, usually 4 times.
+    #[arg(long, value_name = "PATH")]
+    media: Vec<PathBuf>,
+
+    /// Alt text describing the media, given in the same order as --media
+    #[arg(long, value_name = "DESCRIPTION")]
+    alt: Vec<String>,
+
+    /// Attach media that was already uploaded, such as the media of a status
+    /// deleted with `status delete --redraft`. Can be repeated.
+    #[arg(long = "media-id", value_name = "ID")]
+    #[serde(default)]
+    media_ids: Vec<String>,
+
+    /// Add a poll with this option. Repeat for each option.
+    #[arg(long = "poll-option", value_name = "OPTION")]
+    poll_options: Vec<String>,
+
+    /// How long the poll is open for, e.g. `30m`, `12h` or `3d`
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "1d")]
+    poll_expires: Duration,
+
+    /// Allow choosing more than one poll option
+    #[arg(long)]
+    poll_multiple: bool,
+
+    /// The status that this is a reply to
+    #[arg(skip)]
+    pub in_reply_to_id: Option<StatusId>,
+
+    /// When the instance should post the status, for `schedule post`
+    #[arg(skip)]
+    pub scheduled_at: Option<String>,
+}
+
+impl StatusOptions {
+    /// The options for a direct status, which only mentioned accounts see.
+    pub fn direct() -> Self {
+        Self {
+            visibility: Some(PostVisibility::Direct),
+            ..Self::default()
+        }
+    }
+
+    /// The options for the rest of a thread started with these options. Media
+    /// and polls only go on the first status.
+    fn for_thread(&self) -> Self {
+        Self {
+            visibility: self.visibility,
+            cw: self.cw.clone(),
+            language: self.language,
+            sensitive: self.sensitive,
+            ..Self::default()
+        }
+    }
+
+    /// Makes the media paths absolute, so that they still work from another
+    /// folder.
+    pub fn make_media_paths_absolute(&mut self) -> Result<()> {
+        for path in &mut self.media {
+            *path = path
+                .canonicalize()
+                .with_context(|| format!("{path:?} is not a file"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The poll parameters of the statuses endpoint, which mastodon-async's
+/// `NewStatus` doesn't support yet.
+#[derive(Debug, Serialize)]
+pub struct NewPoll {
+    options: Vec<String>,
+    expires_in: u64,
+    multiple: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+pub enum PostVisibility {
+    /// Visible to everyone, shown in public timelines
+    Public,
+    /// Visible to everyone, but not shown in public timelines
+    Unlisted,
+    /// Visible to followers only
+    Followers,
+    /// Visible to mentioned accounts only
+    Direct,
+}
+
+impl From<PostVisibility> for Visibility {
+    fn from(visibility: PostVisibility) -> Self {
+        match visibility {
+            PostVisibility::Public => Self::Public,
+            PostVisibility::Unlisted => Self::Unlisted,
+            PostVisibility::Followers => Self::Private,
+            PostVisibility::Direct => Self::Direct,
+        }
+    }
+}
+
+pub fn parse_language(code: &str) -> Result<Language, String> {
+    Language::from_639_1(code)
+        .or_else(|| Language::from_639_3(code))
+        .ok_or_else(|| format!("{code} is not an ISO 639-1 or ISO 639-3 language code"))
+}
+
+/// Posts a new status and prints its URL, or a thread of statuses if the text
+/// is too long for one. Requires the `write:statuses` scope.
+///
+/// When the config requires approval the status is queued instead, and the id
+/// of the queued post is printed.
+#[instrument(skip(client, config), err)]
+pub async fn run(client: &Client, config: &Config, args: PostArgs) -> Result<()> {
+    let PostArgs {
+        text,
+        stdin,
+        file,
+        mut options,
+        auto_cw,
+    } = args;
+    let text = match (text, file) {
+        (Some(text), _) => text,
+        (None, Some(path)) => {
+            fs::read_to_string(&path).with_context(|| format!("cannot read file {path:?}"))?
+        }
+        (None, None) if stdin => io::read_to_string(io::stdin()).context("cannot read stdin")?,
+        // clap only allows no text at all with media or a poll
+        (None, None) => String::new(),
+    };
+    if options.cw.is_none() {
+        if let Some(suggestion) = cw::suggest(&config.content_warnings, &text) {
+            if auto_cw {
+                info!(cw = %suggestion, "adding suggested content warning");
+                options.cw = Some(suggestion);
+            } else {
+                warn!(
+                    cw = %suggestion,
+                    "the text mentions a trigger topic, consider --cw or --auto-cw"
+                );
+            }
+        }
+    }
+    send(client, config, text, options).await
+}
+
+/// Posts the text as a status, or as a thread if it is too long for one, and
+/// prints the URLs. When the config requires approval the status is queued
+/// instead, and statuses with `scheduled_at` are scheduled.
+///
+/// Every command that posts goes through here, so none of them skip the
+/// approval.
+pub async fn send(
+    client: &Client,
+    config: &Config,
+    text: String,
+    options: StatusOptions,
+) -> Result<()> {
+    // every instance allows at least the default, so only ask about longer text
+    let limit = if text.chars().count() > StatusesConfiguration::default().max_characters {
+        instance::configuration(client)
+            .await?
+            .statuses
+            .max_characters
+    } else {
+        StatusesConfiguration::default().max_characters
+    };
+    let mut parts = split_thread(&text, limit);
+    if config.require_approval {
+        ensure!(
+            parts.len() == 1,
+            "threads can't be queued for approval, the text is longer than {limit} characters"
+        );
+        let pending = queue::submit(client, config, parts.remove(0), options).await?;
+        println!("queued {} for approval", pending.id);
+        return Ok(());
+    }
+    if options.scheduled_at.is_some() {
+        ensure!(
+            parts.len() == 1,
+            "threads can't be scheduled, the text is longer than {limit} characters"
+        );
+        return schedule::publish(client, parts.remove(0), options).await;
+    }
+
+    let mut parts = parts.into_iter();
+    let first = parts.next().unwrap_or_default();
+    let thread_options = options.for_thread();
+    let mut status = publish(client, first, options).await?;
+    println!("{}", status.url.as_deref().unwrap_or(&status.uri));
+    for part in parts {
+        let mut options = thread_options.for_thread();
+        options.in_reply_to_id = Some(status.id.clone());
+        status = publish(client, part, options).await?;
+        println!("{}", status.url.as_deref().unwrap_or(&status.uri));
+    }
+    Ok(())
+}
+
+pub async fn publish(client: &Client, text: String, options: StatusOptions) -> Result<Status> {
+    let (new_status, poll) = prepare(client, text, options).await?;
+    let status = if poll.is_some() {
+        let body = json_body(&new_status, poll)?;
+        client.post_json::<Status>("v1/statuses", &body).await
+    } else {
+        client.new_status(new_status).await.map_err(Into::into)
+    }
+    .context("Couldn't post status")?;
+    metrics::POSTS_PUBLISHED.inc();
+    info!(id = %status.id, "posted status");
+    Ok(status)
+}
+
+/// Validates the status and uploads any media, returning everything needed to
+/// post it.
+pub async fn prepare(
+    client: &Client,
+    text: String,
+    options: StatusOptions,
+) -> Result<(NewStatus, Option<NewPoll>)> {
+    ensure!(
+        !text.trim().is_empty()
+            || !options.media.is_empty()
+            || !options.media_ids.is_empty()
+            || !options.poll_options.is_empty(),
+        "the status text is empty, which it can only be with media or a poll"
+    );
+    ensure!(
+        options.alt.len() <= options.media.len(),
+        "there is more alt text than media"
+    );
+    for path in &options.media {
+        ensure!(path.is_file(), "{path:?} is not a file");
+    }
+    if !options.media.is_empty() {
+        check_media(client, &options.media).await?;
+    }
+    let poll = if options.poll_options.is_empty() {
+        None
+    } else {
+        ensure!(
+            options.media.is_empty() && options.media_ids.is_empty(),
+            "a status can't have both a poll and media"
+        );
+        Some(
+            new_poll(
+                client,
+                options.poll_options,
+                options.poll_expires,
+                options.poll_multiple,
+            )
+            .await?,
+        )
+    };
+    let mut builder = StatusBuilder::new();
+    builder.status(text);
+    if let Some(id) = options.in_reply_to_id {
+        builder.in_reply_to(id.to_string());
+    }
+    if let Some(visibility) = options.visibility {
+        builder.visibility(visibility.into());
+    }
+    if let Some(cw) = options.cw {
+        ensure!(!cw.trim().is_empty(), "the content warning is empty");
+        builder.spoiler_text(cw);
+    }
+    if let Some(language) = options.language {
+        builder.language(language);
+    }
+    if options.sensitive {
+        builder.sensitive(true);
+    }
+    if !options.media.is_empty() || !options.media_ids.is_empty() {
+        let mut alts = options.alt.into_iter();
+        let mut media_ids: Vec<AttachmentId> = options
+            .media_ids
+            .into_iter()
+            .map(AttachmentId::new)
+            .collect();
+        for path in &options.media {
+            media_ids.push(upload_media(client, path, alts.next()).await?);
+        }
+        builder.media_ids(media_ids);
+    }
+    let new_status = builder.build().context("Couldn't build status")?;
+    Ok((new_status, poll))
+}
+
+/// The body of a request to the statuses endpoint, for the parameters that
+/// mastodon-async doesn't support yet.
+pub fn json_body(new_status: &NewStatus, poll: Option<NewPoll>) -> Result<Value> {
+    let mut body = serde_json::to_value(new_status)?;
+    if let Some(poll) = poll {
+        body["poll"] = serde_json::to_value(poll)?;
+    }
+    Ok(body)
+}
+
+/// Checks the number and size of the media files against the limits of the
+/// instance. The type of each file is guessed from its extension.
+async fn check_media(client: &Client, paths: &[PathBuf]) -> Result<()> {
+    let configuration = instance::configuration(client).await?;
+    let max = configuration.statuses.max_media_attachments;
+    ensure!(
+        paths.len() <= max,
+        "this instance allows at most {max} media attachments"
+    );
+    let limits = &configuration.media_attachments;
+    for path in paths {
+        let size = fs::metadata(path)
+            .with_context(|| format!("cannot read file {path:?}"))?
+            .len();
+        let limit = if is_image(path) {
+            limits.image_size_limit
+        } else {
+            limits.video_size_limit
+        };
+        ensure!(
+            size <= limit,
+            "{path:?} is larger than the {} MB that this instance allows",
+            limit / (1024 * 1024)
+        );
+    }
+    Ok(())
+}
+
+fn is_image(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    matches!(
+        extension.as_deref(),
+        Some("jpg" | "jpeg" | "png" | "gif" | "webp" | "heic" | "heif" | "avif")
+    )
+}
+
+/// Checks the poll against the limits of the instance.
+async fn new_poll(
+    client: &Client,
+    options: Vec<String>,
+    expires: Duration,
+    multiple: bool,
+) -> Result<NewPoll> {
+    let limits = instance::configuration(client).await?.polls;
+    ensure!(options.len() >= 2, "a poll needs at least 2 options");
+    ensure!(
+        options.len() <= limits.max_options,
+        "this instance allows at most {} poll options",
+        limits.max_options
+    );
+    for option in &options {
+        ensure!(
+            option.chars().count() <= limits.max_characters_per_option,
+            "poll option {option:?} is longer than {} characters",
+            limits.max_characters_per_option
+        );
+    }
+    let expires_in = expires.as_secs();
+    ensure!(
+        (limits.min_expiration..=limits.max_expiration).contains(&expires_in),
+        "this instance allows polls to be open for between {} and {} seconds",
+        limits.min_expiration,
+        limits.max_expiration
+    );
+    Ok(NewPoll {
+        options,
+        expires_in,
+        multiple,
+    })
+}
+
+/// Uploads a media file and waits for the server to finish processing it, as
+/// statuses can't be posted with attachments that are still processing.
+#[instrument(skip(client), err)]
+async fn upload_media(
+    client: &Client,
+    path: &Path,
+    description: Option<String>,
+) -> Result<AttachmentId> {
+    let attachment = client
+        .media(path, description)
+        .await
+        .with_context(|| format!("Couldn't upload {path:?}"))?;
+    let attachment = client
+        .wait_for_processing(attachment, PollingTime::default())
+        .await
+        .with_context(|| format!("Couldn't process {path:?}"))?;
+    info!(id = %attachment.id, "uploaded media");
+    Ok(attachment.id)
+}
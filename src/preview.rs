@@ -0,0 +1,343 @@
+//! Inline previews of the images attached to statuses, for `timeline
+//! --preview-images`.
+//!
+//! The preview is drawn with the graphics protocol of the terminal, which is
+//! guessed from the environment: the kitty protocol (kitty, and WezTerm, which
+//! also speaks the iTerm2 one), the iTerm2 protocol (iTerm2) or sixel (foot,
+//! mlterm, and terminals whose `TERM` says so). Other terminals, and
+//! `--low-bandwidth`, get the blurhash of the image instead, drawn with
+//! colored half blocks, which needs no download at all.
+//!
+//! With `--emoji image`, the kitty and iTerm2 protocols also draw custom emoji
+//! in the text, two columns wide. Sixel images can't be drawn inside a line,
+//! so other terminals keep the shortcodes.
+
+use std::env;
+use std::fmt::Write;
+use std::io::{self, Cursor};
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use is_terminal::IsTerminal;
+use mastodon_async::prelude::Status;
+use serde::Deserialize;
+use tracing::{debug, instrument, warn};
+
+use crate::client::Client;
+use crate::output::{self, print_status, CustomEmoji, EmojiMode};
+
+/// How wide a preview is, in terminal columns.
+const COLUMNS: u32 = 40;
+
+/// How wide a custom emoji is, in terminal columns. It is one line tall.
+const EMOJI_COLUMNS: u32 = 2;
+
+/// The largest preview in pixels, for sixel, which can't be scaled by the
+/// terminal. This is about 40 columns of a typical font.
+const SIXEL_SIZE: (u32, u32) = (320, 240);
+
+/// The size of a blurhash placeholder in half blocks, so it is half as many
+/// lines tall.
+const BLURHASH_SIZE: (u32, u32) = (32, 16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Kitty,
+    Iterm,
+    Sixel,
+    /// No graphics, so only the blurhash is drawn.
+    Blurhash,
+}
+
+impl Protocol {
+    fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if term == "xterm-kitty" || env::var_os("KITTY_WINDOW_ID").is_some() {
+            Self::Kitty
+        } else if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+            Self::Iterm
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            Self::Sixel
+        } else {
+            Self::Blurhash
+        }
+    }
+}
+
+/// The parts of a media attachment needed to preview it.
+#[derive(Debug, Deserialize)]
+struct Attachment {
+    #[serde(rename = "type")]
+    kind: String,
+    /// A small version of an image, or a frame of a video.
+    preview_url: Option<String>,
+    blurhash: Option<String>,
+}
+
+/// Whether and how to preview images.
+#[derive(Debug)]
+pub struct Previews {
+    protocol: Option<Protocol>,
+}
+
+impl Previews {
+    /// Previews images if asked to, when printing text to a terminal.
+    pub fn new(enabled: bool, low_bandwidth: bool) -> Self {
+        let enabled = enabled && io::stdout().is_terminal() && !output::is_structured();
+        let protocol = match (enabled, low_bandwidth) {
+            (false, _) => None,
+            (true, true) => Some(Protocol::Blurhash),
+            (true, false) => Some(Protocol::detect()),
+        };
+        debug!(?protocol, "image previews");
+        Self { protocol }
+    }
+
+    /// Prints a status followed by a preview of each image and video attached
+    /// to it, or to the boosted status, drawing its custom emoji if asked to.
+    /// Failing to preview one only logs a warning.
+    pub async fn print_status(&self, client: &Client, status: &Status) {
+        let Some(protocol) = self.protocol else {
+            print_status(status);
+            return;
+        };
+        let original = status.reblog.as_deref().unwrap_or(status);
+        if output::emoji_mode() == EmojiMode::Image {
+            load_emojis(client, protocol, original).await;
+        }
+        print_status(status);
+        let attachments: Vec<Attachment> = match serde_json::to_value(&original.media_attachments)
+            .and_then(serde_json::from_value)
+        {
+            Ok(attachments) => attachments,
+            Err(err) => {
+                warn!(?err, "couldn't read media attachments");
+                return;
+            }
+        };
+        for attachment in &attachments {
+            if !matches!(attachment.kind.as_str(), "image" | "gifv" | "video") {
+                continue;
+            }
+            match preview(client, protocol, attachment).await {
+                Ok(Some(preview)) => println!("{preview}"),
+                Ok(None) => {}
+                Err(err) => warn!(?err, "couldn't preview attachment"),
+            }
+        }
+    }
+}
+
+/// Saves the escape sequences that draw the custom emoji in a status, for
+/// [`print_status`] to put in place of their shortcodes.
+async fn load_emojis(client: &Client, protocol: Protocol, status: &Status) {
+    if !matches!(protocol, Protocol::Kitty | Protocol::Iterm) {
+        return;
+    }
+    let emojis: Vec<CustomEmoji> =
+        match serde_json::to_value(&status.emojis).and_then(serde_json::from_value) {
+            Ok(emojis) => emojis,
+            Err(err) => {
+                warn!(?err, "couldn't read custom emoji");
+                return;
+            }
+        };
+    for emoji in emojis {
+        if output::has_emoji_image(&emoji.static_url) {
+            continue;
+        }
+        match emoji_image(client, protocol, &emoji.static_url).await {
+            Ok(escape) => output::set_emoji_image(emoji.static_url, escape),
+            Err(err) => warn!(
+                ?err,
+                shortcode = %emoji.shortcode,
+                "couldn't draw custom emoji"
+            ),
+        }
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn emoji_image(client: &Client, protocol: Protocol, url: &str) -> Result<String> {
+    let (bytes, _) = client.download(url).await?;
+    let image = image::load_from_memory(&bytes)
+        .context("Couldn't decode emoji image")?
+        // larger than a line of text is tall, but not by much
+        .thumbnail(64, 64);
+    match protocol {
+        Protocol::Kitty => kitty(&image, &format!("c={EMOJI_COLUMNS},r=1")),
+        _ => iterm(&image, &format!("width={EMOJI_COLUMNS};height=1")),
+    }
+}
+
+/// The escape sequences that draw the attachment, falling back to the
+/// blurhash if there is no preview image.
+#[instrument(skip(client), err)]
+async fn preview(
+    client: &Client,
+    protocol: Protocol,
+    attachment: &Attachment,
+) -> Result<Option<String>> {
+    let url = match (protocol, &attachment.preview_url) {
+        (Protocol::Blurhash, _) | (_, None) => {
+            return Ok(attachment.blurhash.as_deref().and_then(blurhash));
+        }
+        (_, Some(url)) => url,
+    };
+    let (bytes, _) = client.download(url).await?;
+    let image = image::load_from_memory(&bytes).context("Couldn't decode preview image")?;
+    let preview = match protocol {
+        Protocol::Kitty => kitty(&image, &format!("c={COLUMNS}"))?,
+        Protocol::Iterm => iterm(&image, &format!("width={COLUMNS}"))?,
+        Protocol::Sixel => sixel(
+            &image
+                .resize(SIXEL_SIZE.0, SIXEL_SIZE.1, FilterType::Triangle)
+                .to_rgb8(),
+        ),
+        Protocol::Blurhash => unreachable!("blurhash previews are drawn without downloading"),
+    };
+    Ok(Some(preview))
+}
+
+fn png(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+        .context("Couldn't encode preview image")?;
+    Ok(bytes)
+}
+
+/// Draws a PNG with the kitty graphics protocol, which takes it in chunks of
+/// 4096 bytes of base64, scaled to the columns and rows of the placement, e.g.
+/// `c=40`.
+fn kitty(image: &DynamicImage, placement: &str) -> Result<String> {
+    let encoded = BASE64.encode(png(image)?);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+    let mut escape = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        let chunk = String::from_utf8_lossy(chunk);
+        if index == 0 {
+            let _ = write!(escape, "\x1b_Gf=100,a=T,{placement},m={more};{chunk}\x1b\\");
+        } else {
+            let _ = write!(escape, "\x1b_Gm={more};{chunk}\x1b\\");
+        }
+    }
+    Ok(escape)
+}
+
+/// Draws an image with the iTerm2 inline images protocol, scaled to the size,
+/// e.g. `width=40`.
+fn iterm(image: &DynamicImage, size: &str) -> Result<String> {
+    let bytes = png(image)?;
+    Ok(format!(
+        "\x1b]1337;File=inline=1;size={};{size};preserveAspectRatio=1:{}\x07",
+        bytes.len(),
+        BASE64.encode(&bytes)
+    ))
+}
+
+/// Draws an image as sixels, with the colors reduced to a 6×6×6 cube. Each
+/// band of six rows is drawn once per color in it, with runs of the same sixel
+/// shortened.
+fn sixel(image: &RgbImage) -> String {
+    // the level of each channel, from 0 to 5
+    let level = |value: u8| u16::from(value) * 5 / 255;
+    let color = |x, y| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        level(r) * 36 + level(g) * 6 + level(b)
+    };
+    let (width, height) = image.dimensions();
+    let mut escape = format!("\x1bPq\"1;1;{width};{height}");
+    for index in 0..216 {
+        let percent = |level: u16| level * 100 / 5;
+        let _ = write!(
+            escape,
+            "#{index};2;{};{};{}",
+            percent(index / 36),
+            percent(index / 6 % 6),
+            percent(index % 6)
+        );
+    }
+    for top in (0..height).step_by(6) {
+        let rows = top..(top + 6).min(height);
+        let mut colors: Vec<u16> = rows
+            .clone()
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| color(x, y))
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for &band_color in &colors {
+            let _ = write!(escape, "#{band_color}");
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = rows
+                    .clone()
+                    .filter(|&y| color(x, y) == band_color)
+                    .fold(0, |bits, y| bits | 1 << (y - top));
+                // bits is at most 0b11_1111, so this is always a sixel
+                let sixel = char::from_u32(63 + bits).unwrap_or('?');
+                run = match run {
+                    Some((previous, count)) if previous == sixel => Some((sixel, count + 1)),
+                    Some((previous, count)) => {
+                        push_run(&mut escape, previous, count);
+                        Some((sixel, 1))
+                    }
+                    None => Some((sixel, 1)),
+                };
+            }
+            if let Some((sixel, count)) = run {
+                push_run(&mut escape, sixel, count);
+            }
+            // back to the start of the band for the next color
+            escape.push('$');
+        }
+        escape.push('-');
+    }
+    escape.push_str("\x1b\\");
+    escape
+}
+
+fn push_run(escape: &mut String, sixel: char, count: usize) {
+    if count > 3 {
+        let _ = write!(escape, "!{count}{sixel}");
+    } else {
+        escape.extend(std::iter::repeat(sixel).take(count));
+    }
+}
+
+/// Draws a blurhash with half blocks, each colored with two pixels, one above
+/// the other.
+fn blurhash(hash: &str) -> Option<String> {
+    let (width, height) = BLURHASH_SIZE;
+    let pixels = match blurhash::decode(hash, width, height, 1.0) {
+        Ok(pixels) => pixels,
+        Err(err) => {
+            warn!(?err, hash, "couldn't decode blurhash");
+            return None;
+        }
+    };
+    // the pixels are RGBA
+    let pixel = |x: u32, y: u32| {
+        let start = ((y * width + x) * 4) as usize;
+        (pixels[start], pixels[start + 1], pixels[start + 2])
+    };
+    let mut escape = String::new();
+    for y in (0..height).step_by(2) {
+        for x in 0..width {
+            let (r, g, b) = pixel(x, y);
+            let (br, bg, bb) = pixel(x, y + 1);
+            let _ = write!(escape, "\x1b[38;2;{r};{g};{b}m\x1b[48;2;{br};{bg};{bb}m▀");
+        }
+        escape.push_str("\x1b[0m\n");
+    }
+    // println adds the last line break
+    escape.pop();
+    Some(escape)
+}
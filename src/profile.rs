@@ -0,0 +1,396 @@
+//! The authenticated account's own profile.
+//!
+//! Every run records a snapshot of the public profile when it has changed since
+//! the last one, so that `profile history` can show what changed and when.
+//! This is mostly useful for accounts shared by several people.
+
+use std::fs::{self, OpenOptions};
+use std::io::{Cursor, ErrorKind, Write};
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand};
+use image::imageops::FilterType;
+use image::{ImageFormat, ImageOutputFormat};
+use mastodon_async::prelude::Account;
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::{debug, info, instrument};
+
+use crate::client::Client;
+use crate::config::data_folder;
+use crate::output::format_time_text;
+use crate::timestamp::format_timestamp;
+
+#[derive(Debug, Args)]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    command: ProfileCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ProfileCommand {
+    /// Show how the profile has changed over time
+    History,
+    /// Change the profile. Anything not given is left as it is.
+    Update(UpdateArgs),
+    /// Upload a new profile picture
+    Avatar(ImageArgs),
+    /// Upload a new banner image for the top of the profile
+    Header(ImageArgs),
+}
+
+#[derive(Debug, Args)]
+struct ImageArgs {
+    /// A PNG, JPEG, GIF or WebP image
+    path: PathBuf,
+
+    /// Scale the image down to the size that Mastodon shows it at, if it is
+    /// bigger, instead of leaving that to the server
+    #[arg(long)]
+    resize: bool,
+}
+
+/// The profile images, with the size they are shown at.
+#[derive(Debug, Clone, Copy)]
+enum ProfileImage {
+    Avatar,
+    Header,
+}
+
+impl ProfileImage {
+    const fn field(self) -> &'static str {
+        match self {
+            Self::Avatar => "avatar",
+            Self::Header => "header",
+        }
+    }
+
+    const fn size(self) -> (u32, u32) {
+        match self {
+            Self::Avatar => (400, 400),
+            Self::Header => (1500, 500),
+        }
+    }
+}
+
+/// The largest profile image that Mastodon accepts.
+const MAX_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Args)]
+struct UpdateArgs {
+    /// The name shown instead of the account's username
+    #[arg(long, value_name = "NAME")]
+    display_name: Option<String>,
+
+    /// The profile description
+    #[arg(long, value_name = "TEXT")]
+    bio: Option<String>,
+
+    /// A profile metadata field, e.g. `"Pronouns=they/them"`. Can be given
+    /// several times, and replaces all of the current fields.
+    #[arg(long = "field", value_name = "KEY=VALUE", value_parser = parse_field)]
+    fields: Vec<(String, String)>,
+
+    /// Whether follow requests have to be approved
+    #[arg(long, value_name = "BOOL")]
+    locked: Option<bool>,
+
+    /// Whether the account is marked as automated
+    #[arg(long, value_name = "BOOL")]
+    bot: Option<bool>,
+}
+
+fn parse_field(text: &str) -> Result<(String, String), String> {
+    let (name, value) = text
+        .split_once('=')
+        .ok_or_else(|| format!("{text} is not a field like Key=Value"))?;
+    Ok((name.trim().to_owned(), value.trim().to_owned()))
+}
+
+impl ProfileArgs {
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            ProfileCommand::History => &["read:accounts"],
+            ProfileCommand::Update(_) | ProfileCommand::Avatar(_) | ProfileCommand::Header(_) => {
+                &["write:accounts"]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    taken_at: String,
+    acct: String,
+    profile: Profile,
+}
+
+/// The parts of the profile that other instances can see.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Profile {
+    display_name: String,
+    note: String,
+    fields: Vec<(String, String)>,
+    /// A hash of the avatar URL, which changes whenever the image does.
+    avatar_hash: String,
+    header_hash: String,
+    locked: bool,
+    bot: bool,
+    followers_count: u64,
+    following_count: u64,
+    statuses_count: u64,
+}
+
+impl Profile {
+    fn from_account(account: &Account) -> Result<Self> {
+        let value = serde_json::to_value(account)?;
+        let text = |key: &str| value[key].as_str().unwrap_or_default().to_owned();
+        let count = |key: &str| value[key].as_u64().unwrap_or_default();
+        let flag = |key: &str| value[key].as_bool().unwrap_or_default();
+        let fields = value["fields"]
+            .as_array()
+            .map(|fields| {
+                fields
+                    .iter()
+                    .map(|field| {
+                        let name = field["name"].as_str().unwrap_or_default();
+                        let value = field["value"].as_str().unwrap_or_default();
+                        (name.to_owned(), value.to_owned())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            display_name: text("display_name"),
+            note: text("note"),
+            fields,
+            avatar_hash: hash(&text("avatar")),
+            header_hash: hash(&text("header")),
+            locked: flag("locked"),
+            bot: flag("bot"),
+            followers_count: count("followers_count"),
+            following_count: count("following_count"),
+            statuses_count: count("statuses_count"),
+        })
+    }
+
+    /// The fields that differ between two profiles, as `(name, old, new)`.
+    fn changes(&self, newer: &Self) -> Vec<(&'static str, Value, Value)> {
+        let old = serde_json::to_value(self).unwrap_or_default();
+        let new = serde_json::to_value(newer).unwrap_or_default();
+        [
+            "display_name",
+            "note",
+            "fields",
+            "avatar_hash",
+            "header_hash",
+            "locked",
+            "bot",
+            "followers_count",
+            "following_count",
+            "statuses_count",
+        ]
+        .into_iter()
+        .filter(|&name| old[name] != new[name])
+        .map(|name| (name, old[name].clone(), new[name].clone()))
+        .collect()
+    }
+}
+
+/// A 64 bit FNV-1a hash, which unlike the standard library's hasher is stable
+/// between builds, so it can be stored.
+fn hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+fn history_path() -> Result<PathBuf> {
+    Ok(data_folder()?.join("profile-history.jsonl"))
+}
+
+fn load_snapshots(acct: &str) -> Result<Vec<Snapshot>> {
+    let path = history_path()?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+    };
+    let mut snapshots = Vec::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let snapshot: Snapshot =
+            serde_json::from_str(line).with_context(|| format!("cannot parse file {path:?}"))?;
+        if snapshot.acct == acct {
+            snapshots.push(snapshot);
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Appends a snapshot of the profile to the history if it has changed since
+/// the last snapshot.
+#[instrument(skip_all, err)]
+pub fn record_snapshot(account: &Account) -> Result<()> {
+    let profile = Profile::from_account(account)?;
+    let snapshots = load_snapshots(&account.acct)?;
+    if snapshots.last().map(|last| &last.profile) == Some(&profile) {
+        return Ok(());
+    }
+    let snapshot = Snapshot {
+        taken_at: format_timestamp(OffsetDateTime::now_utc()),
+        acct: account.acct.clone(),
+        profile,
+    };
+    let path = history_path()?;
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder).with_context(|| format!("cannot create {folder:?}"))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("cannot open file {path:?}"))?;
+    writeln!(file, "{}", serde_json::to_string(&snapshot)?)
+        .with_context(|| format!("cannot write file {path:?}"))?;
+    debug!("recorded profile snapshot");
+    Ok(())
+}
+
+pub async fn run(client: &Client, args: ProfileArgs) -> Result<()> {
+    match args.command {
+        ProfileCommand::History => history(client).await,
+        ProfileCommand::Update(args) => update(client, args).await,
+        ProfileCommand::Avatar(args) => upload_image(client, ProfileImage::Avatar, args).await,
+        ProfileCommand::Header(args) => upload_image(client, ProfileImage::Header, args).await,
+    }
+}
+
+/// Updates the profile. Requires the `write:accounts` scope.
+#[instrument(skip(client), err)]
+async fn update(client: &Client, args: UpdateArgs) -> Result<()> {
+    let mut form: Vec<(String, String)> = Vec::new();
+    if let Some(display_name) = args.display_name {
+        form.push(("display_name".to_owned(), display_name));
+    }
+    if let Some(bio) = args.bio {
+        form.push(("note".to_owned(), bio));
+    }
+    for (index, (name, value)) in args.fields.into_iter().enumerate() {
+        form.push((format!("fields_attributes[{index}][name]"), name));
+        form.push((format!("fields_attributes[{index}][value]"), value));
+    }
+    if let Some(locked) = args.locked {
+        form.push(("locked".to_owned(), locked.to_string()));
+    }
+    if let Some(bot) = args.bot {
+        form.push(("bot".to_owned(), bot.to_string()));
+    }
+    ensure!(!form.is_empty(), "nothing to update");
+    let account: Account = client
+        .patch("v1/accounts/update_credentials", &form)
+        .await
+        .context("Couldn't update profile")?;
+    info!(acct = account.acct, "updated profile");
+    record_snapshot(&account)?;
+    println!("updated @{}", account.acct);
+    Ok(())
+}
+
+/// Uploads a profile image, checking that the server will accept it first.
+/// Requires the `write:accounts` scope.
+#[instrument(skip(client), err)]
+async fn upload_image(client: &Client, image: ProfileImage, args: ImageArgs) -> Result<()> {
+    let path = &args.path;
+    let mut bytes = fs::read(path).with_context(|| format!("cannot read file {path:?}"))?;
+    let mut format = image::guess_format(&bytes)
+        .ok()
+        .filter(|format| {
+            matches!(
+                format,
+                ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP
+            )
+        })
+        .with_context(|| format!("{path:?} is not a PNG, JPEG, GIF or WebP image"))?;
+    if args.resize {
+        (bytes, format) = downscale(bytes, format, image.size())
+            .with_context(|| format!("Couldn't resize {path:?}"))?;
+    }
+    ensure!(
+        bytes.len() <= MAX_IMAGE_BYTES,
+        "{path:?} is {} KB, more than the {} KB that Mastodon accepts{}",
+        bytes.len() / 1024,
+        MAX_IMAGE_BYTES / 1024,
+        if args.resize { "" } else { ", try --resize" }
+    );
+    // resizing can change the format, so name the file after the format
+    let extension = format.extensions_str().first().copied().unwrap_or("img");
+    let file_name = format!("{}.{extension}", image.field());
+    let part = Part::bytes(bytes)
+        .file_name(file_name)
+        .mime_str(format.to_mime_type())?;
+    let form = Form::new().part(image.field(), part);
+    let account: Account = client
+        .patch_multipart("v1/accounts/update_credentials", form)
+        .await
+        .with_context(|| format!("Couldn't upload the {}", image.field()))?;
+    info!(acct = account.acct, image = image.field(), "uploaded image");
+    record_snapshot(&account)?;
+    println!("updated the {} of @{}", image.field(), account.acct);
+    Ok(())
+}
+
+/// Scales an image down to fit within a size, keeping its aspect ratio, and
+/// encodes it in the same format where possible. Images that already fit are
+/// left alone. Animated GIFs keep only their first frame.
+fn downscale(
+    bytes: Vec<u8>,
+    format: ImageFormat,
+    (width, height): (u32, u32),
+) -> Result<(Vec<u8>, ImageFormat)> {
+    let decoded = image::load_from_memory_with_format(&bytes, format)?;
+    if decoded.width() <= width && decoded.height() <= height {
+        return Ok((bytes, format));
+    }
+    let resized = decoded.resize(width, height, FilterType::Lanczos3);
+    debug!(
+        width = resized.width(),
+        height = resized.height(),
+        "resized image"
+    );
+    let (output, format) = match format {
+        ImageFormat::Jpeg => (ImageOutputFormat::Jpeg(90), ImageFormat::Jpeg),
+        ImageFormat::Gif => (ImageOutputFormat::Gif, ImageFormat::Gif),
+        // there's no lossy WebP encoder, so WebP images become PNGs
+        _ => (ImageOutputFormat::Png, ImageFormat::Png),
+    };
+    let mut resized_bytes = Vec::new();
+    resized.write_to(&mut Cursor::new(&mut resized_bytes), output)?;
+    Ok((resized_bytes, format))
+}
+
+#[instrument(skip_all, err)]
+async fn history(client: &Client) -> Result<()> {
+    let account = client
+        .verify_credentials()
+        .await
+        .context("Couldn't get account")?;
+    let snapshots = load_snapshots(&account.acct)?;
+    let mut previous: Option<&Profile> = None;
+    for snapshot in &snapshots {
+        println!("{}", format_time_text(&snapshot.taken_at));
+        match previous {
+            None => println!("  first snapshot"),
+            Some(previous) => {
+                for (name, old, new) in previous.changes(&snapshot.profile) {
+                    println!("  {name}: {old} -> {new}");
+                }
+            }
+        }
+        previous = Some(&snapshot.profile);
+    }
+    Ok(())
+}
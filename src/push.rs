@@ -0,0 +1,202 @@
+//! Managing the Web Push subscription, which has the instance send
+//! notifications to a push endpoint, e.g. a self-hosted notification relay.
+//!
+//! Each access token has at most one subscription. The instance encrypts every
+//! push message for the subscription's P-256 public key. Without `--key`, a key
+//! pair and auth secret are generated and saved to `push-keys.json` in the data
+//! folder, so that they can be given to the relay that decrypts the messages.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use base64::Engine;
+use clap::{Args, Subcommand, ValueEnum};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::SecretKey;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::config::{data_folder, write_private};
+
+#[derive(Debug, Args)]
+pub struct PushArgs {
+    #[command(subcommand)]
+    command: PushCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum PushCommand {
+    /// Subscribe a push endpoint, replacing any existing subscription
+    Subscribe(SubscribeArgs),
+    /// Show the current subscription
+    Show,
+    /// Remove the current subscription
+    Delete,
+}
+
+#[derive(Debug, Args)]
+struct SubscribeArgs {
+    /// The URL that the instance sends push messages to
+    #[arg(long, value_name = "URL")]
+    endpoint: String,
+
+    /// The P-256 public key to encrypt for, base64url encoded [default:
+    /// generate a key pair]
+    #[arg(long, value_name = "P256DH", requires = "auth")]
+    key: Option<String>,
+
+    /// The auth secret that goes with --key, base64url encoded
+    #[arg(long, value_name = "SECRET", requires = "key")]
+    auth: Option<String>,
+
+    /// The notification types to push, e.g. `mention,follow` [default:
+    /// mention, follow, favourite, reblog and poll]
+    #[arg(long, value_delimiter = ',', value_name = "TYPES")]
+    alerts: Vec<String>,
+
+    /// Whose notifications to push
+    #[arg(long, value_enum, default_value_t = PushPolicy::All)]
+    policy: PushPolicy,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PushPolicy {
+    /// Everyone
+    All,
+    /// Accounts you follow
+    Followed,
+    /// Accounts that follow you
+    Follower,
+    /// Nobody
+    None,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subscription {
+    id: Value,
+    endpoint: String,
+    alerts: serde_json::Map<String, Value>,
+    /// The instance's public key, which push messages are signed with.
+    server_key: String,
+    #[serde(default)]
+    policy: Option<String>,
+}
+
+/// A generated key pair, saved for the relay that decrypts the messages.
+#[derive(Debug, Serialize)]
+struct PushKeys<'a> {
+    endpoint: &'a str,
+    /// The base64url encoded private key.
+    private_key: String,
+    /// The base64url encoded uncompressed public key.
+    public_key: String,
+    auth: String,
+}
+
+const DEFAULT_ALERTS: [&str; 5] = ["mention", "follow", "favourite", "reblog", "poll"];
+
+pub async fn run(client: &Client, args: PushArgs) -> Result<()> {
+    match args.command {
+        PushCommand::Subscribe(args) => subscribe(client, args).await,
+        PushCommand::Show => show(client).await,
+        PushCommand::Delete => delete(client).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn subscribe(client: &Client, args: SubscribeArgs) -> Result<()> {
+    let (key, auth) = match (args.key, args.auth) {
+        (Some(key), Some(auth)) => (key, auth),
+        _ => generate_keys(&args.endpoint)?,
+    };
+    let alerts: Vec<&str> = if args.alerts.is_empty() {
+        DEFAULT_ALERTS.to_vec()
+    } else {
+        args.alerts.iter().map(String::as_str).collect()
+    };
+    let alerts: serde_json::Map<String, Value> = alerts
+        .into_iter()
+        .map(|alert| (alert.to_owned(), Value::Bool(true)))
+        .collect();
+    let body = json!({
+        "subscription": {
+            "endpoint": args.endpoint,
+            "keys": { "p256dh": key, "auth": auth },
+        },
+        "data": { "alerts": alerts, "policy": args.policy },
+    });
+    let subscription: Subscription = client
+        .post_json("v1/push/subscription", &body)
+        .await
+        .context("Couldn't subscribe to push notifications")?;
+    info!(id = %subscription.id, "subscribed");
+    print_subscription(&subscription);
+    Ok(())
+}
+
+/// Generates a key pair and auth secret, and saves them for the relay.
+fn generate_keys(endpoint: &str) -> Result<(String, String)> {
+    let secret = SecretKey::random(&mut OsRng);
+    let public_key = BASE64_URL.encode(secret.public_key().to_encoded_point(false).as_bytes());
+    let mut auth = [0; 16];
+    OsRng.fill_bytes(&mut auth);
+    let auth = BASE64_URL.encode(auth);
+    let keys = PushKeys {
+        endpoint,
+        private_key: BASE64_URL.encode(secret.to_bytes()),
+        public_key: public_key.clone(),
+        auth: auth.clone(),
+    };
+    let path = keys_path()?;
+    let folder = data_folder()?;
+    fs::create_dir_all(&folder).with_context(|| format!("cannot create {folder:?}"))?;
+    // it holds a private key
+    write_private(&path, serde_json::to_string_pretty(&keys)?)?;
+    println!("saved the generated keys to {}", path.display());
+    Ok((public_key, auth))
+}
+
+fn keys_path() -> Result<PathBuf> {
+    Ok(data_folder()?.join("push-keys.json"))
+}
+
+#[instrument(skip(client), err)]
+async fn show(client: &Client) -> Result<()> {
+    let subscription: Subscription = client
+        .get("v1/push/subscription", &())
+        .await
+        .context("Couldn't get the push subscription")?;
+    print_subscription(&subscription);
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn delete(client: &Client) -> Result<()> {
+    let _: Value = client
+        .delete("v1/push/subscription")
+        .await
+        .context("Couldn't delete the push subscription")?;
+    info!("deleted push subscription");
+    Ok(())
+}
+
+fn print_subscription(subscription: &Subscription) {
+    println!("{} {}", subscription.id, subscription.endpoint);
+    let alerts: Vec<&str> = subscription
+        .alerts
+        .iter()
+        .filter(|(_, enabled)| enabled.as_bool() == Some(true))
+        .map(|(alert, _)| alert.as_str())
+        .collect();
+    println!("alerts: {}", alerts.join(", "));
+    if let Some(policy) = &subscription.policy {
+        println!("policy: {policy}");
+    }
+    println!("server key: {}", subscription.server_key);
+}
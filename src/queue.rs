@@ -0,0 +1,236 @@
+//! An approval queue for accounts that are shared by a team.
+//!
+//! With `require_approval = true` in the config, `post`, `reply`, `schedule
+//! post` and `plugin post` save the status to the `pending` folder instead of
+//! publishing it. Someone else then publishes it with `queue approve`, or
+//! drops it with `queue reject`. Everything that happens to a queued post is
+//! recorded in `journal.jsonl` next to that folder, see [`crate::journal`].
+//!
+//! Both are kept in the data folder, or in `approval_folder` from the config,
+//! which lets reviewers on other user accounts of the machine, or on other
+//! machines sharing the folder, see the queue.
+//!
+//! The author and the reviewer are the accounts of their logins, as the
+//! instance reports them. On an account shared by a team, which looks the same
+//! whoever uses it, reviewers approve with a login of their own account saved
+//! with `auth login --profile <name>`, which can't be the account that queued
+//! the post. The post is still published with the default login.
+//!
+//! This is a guardrail against mistakes, not a security boundary: anyone with
+//! the credentials can still post.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::Mastodon;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{info, instrument};
+
+use crate::auth;
+use crate::client::Client;
+use crate::config::{data_folder, Config};
+use crate::journal;
+use crate::post::{self, StatusOptions};
+use crate::schedule;
+use crate::timestamp::format_timestamp;
+
+#[derive(Debug, Args)]
+pub struct QueueArgs {
+    #[command(subcommand)]
+    command: QueueCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum QueueCommand {
+    /// List the posts waiting for approval
+    List,
+    /// Publish a queued post
+    Approve(ReviewArgs),
+    /// Drop a queued post without publishing it
+    Reject(ReviewArgs),
+}
+
+#[derive(Debug, Args)]
+struct ReviewArgs {
+    /// The id of the queued post
+    id: String,
+
+    /// The login to review with, saved with `auth login --profile`
+    /// [default: the default login]
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+}
+
+/// A post waiting for approval.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingPost {
+    pub id: String,
+    submitted_at: String,
+    author: String,
+    text: String,
+    options: StatusOptions,
+}
+
+pub async fn run(client: &Client, config: &Config, args: QueueArgs) -> Result<()> {
+    let folder = approval_folder(config)?;
+    match args.command {
+        QueueCommand::List => list(&folder),
+        QueueCommand::Approve(args) => approve(client, &folder, args).await,
+        QueueCommand::Reject(args) => reject(client, &folder, args).await,
+    }
+}
+
+/// The folder with the `pending` folder and the journal of the queue.
+fn approval_folder(config: &Config) -> Result<PathBuf> {
+    config.approval_folder.clone().map_or_else(data_folder, Ok)
+}
+
+fn pending_path(folder: &Path, id: &str) -> Result<PathBuf> {
+    ensure!(
+        !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()),
+        "{id} is not a queued post id"
+    );
+    Ok(folder.join("pending").join(format!("{id}.json")))
+}
+
+/// The account of the default login or of a profile, which is who queues,
+/// approves or rejects a post.
+async fn login_acct(client: &Client, profile: Option<&str>) -> Result<String> {
+    let account = match profile {
+        Some(profile) => {
+            let data = auth::load_credentials(Some(profile)).with_context(|| {
+                format!("no login saved for {profile}, run auth login --profile {profile}")
+            })?;
+            auth::verify_credentials(&Mastodon::from(data)).await?
+        }
+        None => auth::verify_credentials(client).await?,
+    };
+    Ok(account.acct)
+}
+
+/// Saves a post to the pending folder instead of publishing it, with the
+/// account of the login as its author.
+#[instrument(skip(client, config, options), err)]
+pub async fn submit(
+    client: &Client,
+    config: &Config,
+    text: String,
+    mut options: StatusOptions,
+) -> Result<PendingPost> {
+    // the reviewer may run from a different folder
+    options.make_media_paths_absolute()?;
+    let author = login_acct(client, None).await?;
+    let now = OffsetDateTime::now_utc();
+    let pending = PendingPost {
+        id: now.unix_timestamp_nanos().to_string(),
+        submitted_at: format_timestamp(now),
+        author,
+        text,
+        options,
+    };
+    let folder = approval_folder(config)?;
+    let path = pending_path(&folder, &pending.id)?;
+    let pending_folder = folder.join("pending");
+    fs::create_dir_all(&pending_folder)
+        .with_context(|| format!("cannot create {pending_folder:?}"))?;
+    let json = serde_json::to_string_pretty(&pending)?;
+    fs::write(&path, json).with_context(|| format!("cannot write file {path:?}"))?;
+    journal::record_in(&folder, "queued", &pending.id, &pending.author, None)?;
+    info!(id = pending.id, "queued post for approval");
+    Ok(pending)
+}
+
+fn load(folder: &Path, id: &str) -> Result<PendingPost> {
+    let path = pending_path(folder, id)?;
+    let json = match fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == ErrorKind::NotFound => anyhow::bail!("no queued post {id}"),
+        Err(err) => return Err(err).with_context(|| format!("cannot read file {path:?}")),
+    };
+    serde_json::from_str(&json).with_context(|| format!("cannot parse file {path:?}"))
+}
+
+fn remove(folder: &Path, id: &str) -> Result<()> {
+    let path = pending_path(folder, id)?;
+    fs::remove_file(&path).with_context(|| format!("cannot remove file {path:?}"))
+}
+
+/// How many posts are waiting for approval.
+///
+/// # Errors
+///
+/// Returns an error if the pending folder can't be read.
+pub fn depth(config: &Config) -> Result<usize> {
+    Ok(pending_ids(&approval_folder(config)?)?.len())
+}
+
+/// The ids of the queued posts, oldest first.
+fn pending_ids(folder: &Path) -> Result<Vec<String>> {
+    let pending_folder = folder.join("pending");
+    let entries = match fs::read_dir(&pending_folder) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("cannot read {pending_folder:?}")),
+    };
+    let mut ids = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+            ids.push(id.to_owned());
+        }
+    }
+    // the ids are timestamps, so this sorts the oldest first
+    ids.sort();
+    Ok(ids)
+}
+
+#[instrument(err)]
+fn list(folder: &Path) -> Result<()> {
+    for id in pending_ids(folder)? {
+        let pending = load(folder, &id)?;
+        println!(
+            "{} {} by {}: {}",
+            pending.id, pending.submitted_at, pending.author, pending.text
+        );
+    }
+    Ok(())
+}
+
+/// Publishes a queued post, or schedules it if it was queued by `schedule
+/// post`. Another account than the author's has to approve it.
+#[instrument(skip(client), err)]
+async fn approve(client: &Client, folder: &Path, args: ReviewArgs) -> Result<()> {
+    let pending = load(folder, &args.id)?;
+    let reviewer = login_acct(client, args.profile.as_deref()).await?;
+    ensure!(
+        reviewer != pending.author,
+        "posts can't be approved by their author ({reviewer}), use --profile with the login of \
+         another account"
+    );
+    let url = if pending.options.scheduled_at.is_some() {
+        schedule::publish(client, pending.text, pending.options).await?;
+        None
+    } else {
+        let status = post::publish(client, pending.text, pending.options).await?;
+        let url = status.url.unwrap_or(status.uri);
+        println!("{url}");
+        Some(url)
+    };
+    journal::record_in(folder, "approved", &pending.id, &reviewer, url.as_deref())?;
+    remove(folder, &pending.id)?;
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn reject(client: &Client, folder: &Path, args: ReviewArgs) -> Result<()> {
+    let pending = load(folder, &args.id)?;
+    let reviewer = login_acct(client, args.profile.as_deref()).await?;
+    journal::record_in(folder, "rejected", &pending.id, &reviewer, None)?;
+    remove(folder, &pending.id)?;
+    info!(id = pending.id, "rejected queued post");
+    Ok(())
+}
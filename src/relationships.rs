@@ -0,0 +1,150 @@
+//! Comparing the relationships of several of your own accounts.
+//!
+//! The accounts are the profiles that credentials are saved for, with
+//! `default` meaning the credentials saved without a profile name.
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand};
+use futures_util::future::try_join;
+use mastodon_async::prelude::Account;
+use tracing::{info, instrument};
+
+use crate::account::{full_acct, instance_domain};
+use crate::client::Client;
+use crate::follow::{self, FollowArgs};
+use crate::paging;
+use crate::SpikeMastodonBuilder;
+
+#[derive(Debug, Args)]
+pub struct RelationshipsArgs {
+    #[command(subcommand)]
+    command: RelationshipsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum RelationshipsCommand {
+    /// Show who is followed by one of two accounts but not the other
+    Overlap(OverlapArgs),
+}
+
+#[derive(Debug, Args)]
+struct OverlapArgs {
+    /// The two profiles to compare, e.g. `default,work`
+    #[arg(long, value_delimiter = ',', required = true, value_name = "PROFILES")]
+    accounts: Vec<String>,
+
+    /// Ask whether to follow each missing account from the other profile,
+    /// answering with y, n or q
+    #[arg(long)]
+    sync: bool,
+}
+
+/// One of your accounts and the accounts it follows, by their full address.
+#[derive(Debug)]
+struct Profile {
+    name: String,
+    client: Client,
+    following: BTreeSet<String>,
+}
+
+pub async fn run(client: &Client, args: RelationshipsArgs) -> Result<()> {
+    match args.command {
+        RelationshipsCommand::Overlap(args) => overlap(client, args).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn overlap(client: &Client, args: OverlapArgs) -> Result<()> {
+    ensure!(
+        args.accounts.len() == 2,
+        "--accounts takes exactly two profiles, e.g. default,work"
+    );
+    let (first, second) = try_join(
+        load_profile(client, &args.accounts[0]),
+        load_profile(client, &args.accounts[1]),
+    )
+    .await?;
+    for (from, to) in [(&first, &second), (&second, &first)] {
+        let missing: Vec<&String> = from.following.difference(&to.following).collect();
+        if missing.is_empty() {
+            continue;
+        }
+        println!(
+            "followed by {} but not by {} ({}):",
+            from.name,
+            to.name,
+            missing.len()
+        );
+        for acct in missing {
+            println!("  @{acct}");
+            if args.sync && !suggest_follow(to, acct).await? {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Logs in as a profile and pages through everyone that it follows.
+#[instrument(skip(client), err)]
+async fn load_profile(client: &Client, name: &str) -> Result<Profile> {
+    let mut builder = SpikeMastodonBuilder::new().low_bandwidth(client.low_bandwidth());
+    if name != "default" {
+        builder = builder.profile(name);
+    }
+    let client = builder
+        .build()
+        .with_context(|| format!("Couldn't log in as profile {name}"))?;
+    let me = client
+        .verify_credentials()
+        .await
+        .with_context(|| format!("Couldn't get the account of profile {name}"))?;
+    let accounts: Vec<Account> =
+        paging::collect_items(&client, &format!("v1/accounts/{}/following", me.id), true)
+            .await
+            .with_context(|| format!("Couldn't get the accounts that {name} follows"))?;
+    let domain = instance_domain(&client);
+    let following = accounts
+        .iter()
+        .map(|account| full_acct(account, &domain))
+        .collect();
+    info!(
+        name,
+        acct = me.acct,
+        count = accounts.len(),
+        "loaded following"
+    );
+    Ok(Profile {
+        name: name.to_owned(),
+        client,
+        following,
+    })
+}
+
+/// Asks whether to follow an account from a profile, and follows it if so.
+/// Returns false if the user wants to stop.
+async fn suggest_follow(profile: &Profile, acct: &str) -> Result<bool> {
+    let answer = {
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "  follow from {}? [y/n/q] ", profile.name)
+            .context("failed to write to stdout")?;
+        stdout.flush().context("failed to flush stdout")?;
+        let mut input = String::new();
+        io::stdin()
+            .lock()
+            .read_line(&mut input)
+            .context("failed to read input")?;
+        input.trim().to_lowercase()
+    };
+    match answer.as_str() {
+        "y" => {
+            follow::run(&profile.client, FollowArgs::new(acct.to_owned())).await?;
+            Ok(true)
+        }
+        "q" => Ok(false),
+        _ => Ok(true),
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use mastodon_async::prelude::Status;
+use tracing::instrument;
+
+use crate::client::Client;
+use crate::config::Config;
+use crate::post::{self, StatusOptions};
+use crate::resolve::resolve_status;
+
+#[derive(Debug, Args)]
+pub struct ReplyArgs {
+    /// The id or URL of the status to reply to
+    status: String,
+
+    /// The text of the reply. The author of the status and everyone it
+    /// mentions are mentioned at the start.
+    text: String,
+
+    #[command(flatten)]
+    options: StatusOptions,
+}
+
+/// Replies to a status the way the official clients do: mentioning everyone in
+/// the conversation and keeping the content warning.
+#[instrument(skip(client, config), err)]
+pub async fn run(client: &Client, config: &Config, args: ReplyArgs) -> Result<()> {
+    let ReplyArgs {
+        status,
+        text,
+        mut options,
+    } = args;
+    let parent = resolve_status(client, &status).await?;
+    let me = client
+        .verify_credentials()
+        .await
+        .context("Couldn't get account")?;
+
+    let text = format!("{}{text}", mentions(&parent, &me.acct));
+    options.in_reply_to_id = Some(parent.id.clone());
+    if options.cw.is_none() && !parent.spoiler_text.is_empty() {
+        options.cw = Some(parent.spoiler_text.clone());
+    }
+    post::send(client, config, text, options).await
+}
+
+/// The mentions to start a reply with: the author of the status followed by
+/// everyone it mentions, leaving out the replying account.
+fn mentions(parent: &Status, own_acct: &str) -> String {
+    let mut accts = vec![parent.account.acct.as_str()];
+    for mention in &parent.mentions {
+        if !accts.contains(&mention.acct.as_str()) {
+            accts.push(&mention.acct);
+        }
+    }
+    accts
+        .into_iter()
+        .filter(|&acct| acct != own_acct)
+        .map(|acct| format!("@{acct} "))
+        .collect()
+}
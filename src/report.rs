@@ -0,0 +1,100 @@
+//! Reporting accounts and their statuses to the moderators.
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::resolve::resolve_account;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// The account, e.g. `@someone@example.com` or a profile URL
+    account: String,
+
+    /// The id of a status by the account to include, can be repeated
+    #[arg(long = "status", value_name = "ID")]
+    statuses: Vec<String>,
+
+    /// Why the account is being reported, for the moderators
+    #[arg(long, default_value = "")]
+    comment: String,
+
+    /// What kind of problem it is [default: other]
+    #[arg(long, value_enum)]
+    category: Option<Category>,
+
+    /// The id of an instance rule that was broken, for `--category violation`,
+    /// can be repeated. See `instance rules`.
+    #[arg(long = "rule", value_name = "ID", requires = "category")]
+    rules: Vec<String>,
+
+    /// Also send the report to the moderators of the account's instance
+    #[arg(long)]
+    forward: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Category {
+    Spam,
+    Violation,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportForm<'a> {
+    account_id: &'a str,
+    status_ids: &'a [String],
+    comment: &'a str,
+    forward: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<Category>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    rule_ids: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct Report {
+    id: String,
+}
+
+impl ReportArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        if self.category.is_some() {
+            Some(Requirement::new("report categories", Version(3, 5, 0)))
+        } else {
+            None
+        }
+    }
+}
+
+/// Reports an account, with any of its statuses. Requires the `write:reports`
+/// scope.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: ReportArgs) -> Result<()> {
+    let account = resolve_account(client, &args.account).await?;
+    let account_id = account.id.to_string();
+    let form = ReportForm {
+        account_id: &account_id,
+        status_ids: &args.statuses,
+        comment: &args.comment,
+        forward: args.forward,
+        category: args.category,
+        rule_ids: &args.rules,
+    };
+    let report: Report = client
+        .post_json("v1/reports", &form)
+        .await
+        .with_context(|| format!("Couldn't report {}", account.acct))?;
+    info!(
+        id = report.id,
+        acct = account.acct,
+        statuses = args.statuses.len(),
+        forward = args.forward,
+        "reported"
+    );
+    println!("reported @{} (report {})", account.acct, report.id);
+    Ok(())
+}
@@ -0,0 +1,179 @@
+//! Resolving the ids and URLs given on the command line to this instance's
+//! local entities.
+//!
+//! Statuses and accounts on other instances have a different id on each
+//! instance, so a URL copied from a browser has to be looked up with a search
+//! that asks the instance to fetch the remote entity if it hasn't seen it yet.
+//! `resolve` does this on its own, printing the local id for use with other
+//! commands.
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use mastodon_async::prelude::{Account, Status, StatusId};
+use tracing::{debug, instrument};
+
+use crate::client::Client;
+use crate::output::print_data;
+use crate::scrub;
+use crate::search::SearchResults;
+use crate::storage;
+
+#[derive(Debug, Args)]
+pub struct ResolveArgs {
+    /// The URL of a status or profile on any instance, or an address like
+    /// `@someone@example.com`
+    url: String,
+
+    /// What the URL is of [default: whatever the instance finds]
+    #[arg(long = "type", value_enum)]
+    kind: Option<EntityType>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum EntityType {
+    Status,
+    Account,
+}
+
+/// Prints the type and local id of the status or account at the URL, followed
+/// by its local URL or address.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: ResolveArgs) -> Result<()> {
+    let kind = match args.kind {
+        Some(kind) => kind,
+        // addresses can only be accounts
+        None if !is_url(&args.url) => EntityType::Account,
+        None => return resolve_any(client, &args.url).await,
+    };
+    match kind {
+        EntityType::Status => print_status(&resolve_status(client, &args.url).await?),
+        EntityType::Account => print_account(&resolve_account(client, &args.url).await?),
+    }
+    Ok(())
+}
+
+/// Resolves a URL that could be of either a status or an account.
+async fn resolve_any(client: &Client, url: &str) -> Result<()> {
+    let results: SearchResults = client
+        .get(
+            "v2/search",
+            &[("q", url), ("resolve", "true"), ("limit", "1")],
+        )
+        .await
+        .with_context(|| format!("Couldn't resolve {url}"))?;
+    // a status URL also contains the profile URL of its author, so prefer
+    // statuses
+    if let Some(status) = results.statuses.first() {
+        print_status(status);
+    } else if let Some(account) = results.accounts.first() {
+        print_account(account);
+    } else {
+        bail!("nothing found at {url}");
+    }
+    Ok(())
+}
+
+/// Prints a status like [`crate::output::print_status`] in the structured
+/// formats, or its local id and URL in text.
+fn print_status(status: &Status) {
+    let status = &*scrub::status(status);
+    if print_data(status) {
+        return;
+    }
+    println!(
+        "status {} {}",
+        status.id,
+        status.url.as_deref().unwrap_or(&status.uri)
+    );
+}
+
+fn print_account(account: &Account) {
+    let account = &*scrub::account(account);
+    if print_data(account) {
+        return;
+    }
+    println!("account {} @{}", account.id, account.acct);
+}
+
+fn is_url(id_or_url: &str) -> bool {
+    id_or_url.starts_with("https://") || id_or_url.starts_with("http://")
+}
+
+/// Finds a status given either its local id or its URL on any instance.
+#[instrument(skip(client), err)]
+pub async fn resolve_status(client: &Client, id_or_url: &str) -> Result<Status> {
+    if !is_url(id_or_url) {
+        return client
+            .get_status(&StatusId::new(id_or_url))
+            .await
+            .with_context(|| format!("Couldn't get status {id_or_url}"));
+    }
+    let results: SearchResults = client
+        .get(
+            "v2/search",
+            &[
+                ("q", id_or_url),
+                ("type", "statuses"),
+                ("resolve", "true"),
+                ("limit", "1"),
+            ],
+        )
+        .await
+        .with_context(|| format!("Couldn't resolve {id_or_url}"))?;
+    let status = results
+        .statuses
+        .into_iter()
+        .next()
+        .with_context(|| format!("no status found at {id_or_url}"))?;
+    debug!(id = %status.id, "resolved status");
+    Ok(status)
+}
+
+/// Finds an account given either its address, e.g. `@someone@example.com`, or
+/// its profile URL. Addresses are looked up directly first, and otherwise, like
+/// URLs, searched for so that accounts on other instances are fetched if the
+/// instance hasn't seen them yet.
+#[instrument(skip(client), err)]
+pub async fn resolve_account(client: &Client, acct_or_url: &str) -> Result<Account> {
+    let query = acct_or_url.trim_start_matches('@');
+    if !is_url(query) {
+        match client
+            .get::<Account>("v1/accounts/lookup", &[("acct", query)])
+            .await
+        {
+            Ok(account) => return Ok(account),
+            Err(err) => debug!(?err, "lookup failed, searching instead"),
+        }
+    }
+    let results: SearchResults = client
+        .get(
+            "v2/search",
+            &[
+                ("q", query),
+                ("type", "accounts"),
+                ("resolve", "true"),
+                ("limit", "5"),
+            ],
+        )
+        .await
+        .with_context(|| format!("Couldn't resolve {acct_or_url}"))?;
+    // searching for an address also finds similar accounts, so only take an
+    // exact match. Local accounts don't include the domain in their acct, so
+    // they only match an address on this instance.
+    let query = query.to_lowercase();
+    let local_domain = storage::instance(&client.data.base).to_lowercase();
+    let local_query = query
+        .split_once('@')
+        .filter(|(_, domain)| *domain == local_domain)
+        .map(|(username, _)| username);
+    let account = results
+        .accounts
+        .into_iter()
+        .find(|account| {
+            let acct = account.acct.to_lowercase();
+            is_url(&query) || acct == query || local_query == Some(acct.as_str())
+        })
+        .with_context(|| format!("no account found for {acct_or_url}"))?;
+    debug!(id = %account.id, "resolved account");
+    Ok(account)
+}
@@ -0,0 +1,238 @@
+//! Removing old data from the account according to a policy file,
+//! `retention.toml` in the config folder:
+//!
+//! ```toml
+//! # delete my own statuses, except pinned ones and those with 10 or more
+//! # favourites
+//! [statuses]
+//! older_than = "1y"
+//! keep_favourited = 10
+//!
+//! # delete my own direct messages
+//! [direct_messages]
+//! older_than = "30d"
+//!
+//! # unfavourite and unbookmark statuses
+//! [favourites]
+//! older_than = "90d"
+//!
+//! [bookmarks]
+//! older_than = "30d"
+//! ```
+//!
+//! Categories that aren't in the file are left alone. Ages are the age of the
+//! status, as Mastodon doesn't say when something was favourited or
+//! bookmarked. Boosts are never removed.
+//!
+//! Everything removed is recorded in the [`journal`](crate::journal).
+//! Instances limit how quickly statuses can be deleted, so a first run over a
+//! long history may stop partway with a rate limit error; running it again
+//! later carries on.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::{Status, Visibility};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::config::config_folder;
+use crate::duration::{checked_before, parse_duration};
+use crate::mark::{self, Kind};
+use crate::{journal, paging};
+
+#[derive(Debug, Args)]
+pub struct RetentionArgs {
+    #[command(subcommand)]
+    command: RetentionCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum RetentionCommand {
+    /// Remove everything that the policy says is too old
+    Run(RunArgs),
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    /// The policy file [default: retention.toml in the config folder]
+    #[arg(long, value_name = "PATH")]
+    policy: Option<PathBuf>,
+
+    /// Only list what would be removed
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Policy {
+    statuses: Option<StatusRule>,
+    direct_messages: Option<Rule>,
+    favourites: Option<Rule>,
+    bookmarks: Option<Rule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    #[serde(deserialize_with = "deserialize_duration")]
+    older_than: Duration,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StatusRule {
+    #[serde(deserialize_with = "deserialize_duration")]
+    older_than: Duration,
+    /// Keep pinned statuses, which is the default.
+    #[serde(default = "keep_pinned")]
+    keep_pinned: bool,
+    /// Keep statuses with at least this many favourites.
+    keep_favourited: Option<u64>,
+}
+
+const fn keep_pinned() -> bool {
+    true
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_duration(&text).map_err(serde::de::Error::custom)
+}
+
+impl Policy {
+    fn load(path: Option<PathBuf>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => config_folder()?.join("retention.toml"),
+        };
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("cannot read file {path:?}"))?;
+        toml::from_str(&text).with_context(|| format!("cannot parse file {path:?}"))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Category {
+    Statuses,
+    DirectMessages,
+    Favourites,
+    Bookmarks,
+}
+
+impl Category {
+    /// The action recorded in the journal.
+    const fn action(self) -> &'static str {
+        match self {
+            Self::Statuses | Self::DirectMessages => "deleted",
+            Self::Favourites => "unfavourited",
+            Self::Bookmarks => "unbookmarked",
+        }
+    }
+
+    async fn remove(self, client: &Client, status: &Status) -> Result<()> {
+        let id = &status.id;
+        match self {
+            Self::Statuses | Self::DirectMessages => {
+                let _: Value = client.delete(&format!("v1/statuses/{id}")).await?;
+            }
+            Self::Favourites => {
+                let _: Status = client
+                    .post(&format!("v1/statuses/{id}/unfavourite"), &())
+                    .await?;
+            }
+            Self::Bookmarks => {
+                let _: Status = client
+                    .post(&format!("v1/statuses/{id}/unbookmark"), &())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether something created at a time is older than an age. Nothing is older
+/// than an age that reaches back further than a time can go.
+fn is_older(created_at: OffsetDateTime, now: OffsetDateTime, age: Duration) -> bool {
+    checked_before(now, age).map_or(false, |cutoff| created_at < cutoff)
+}
+
+pub async fn run(client: &Client, args: RetentionArgs) -> Result<()> {
+    match args.command {
+        RetentionCommand::Run(args) => run_policy(client, args).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn run_policy(client: &Client, args: RunArgs) -> Result<()> {
+    let policy = Policy::load(args.policy)?;
+    let now = OffsetDateTime::now_utc();
+    let mut removals = Vec::new();
+
+    if policy.statuses.is_some() || policy.direct_messages.is_some() {
+        let me = client
+            .verify_credentials()
+            .await
+            .context("Couldn't get account")?;
+        let statuses = paging::account_statuses(client, &me.id.to_string(), &[], true).await?;
+        for status in statuses {
+            if status.reblog.is_some() {
+                continue;
+            }
+            if status.visibility == Visibility::Direct {
+                if let Some(rule) = &policy.direct_messages {
+                    if is_older(status.created_at, now, rule.older_than) {
+                        removals.push((Category::DirectMessages, status));
+                    }
+                }
+            } else if let Some(rule) = &policy.statuses {
+                let pinned = rule.keep_pinned && status.pinned == Some(true);
+                let favourited = rule
+                    .keep_favourited
+                    .map_or(false, |count| status.favourites_count >= count);
+                if is_older(status.created_at, now, rule.older_than) && !pinned && !favourited {
+                    removals.push((Category::Statuses, status));
+                }
+            }
+        }
+    }
+    if let Some(rule) = &policy.favourites {
+        for status in mark::collect(client, Kind::Favourites).await? {
+            if is_older(status.created_at, now, rule.older_than) {
+                removals.push((Category::Favourites, status));
+            }
+        }
+    }
+    if let Some(rule) = &policy.bookmarks {
+        for status in mark::collect(client, Kind::Bookmarks).await? {
+            if is_older(status.created_at, now, rule.older_than) {
+                removals.push((Category::Bookmarks, status));
+            }
+        }
+    }
+
+    info!(count = removals.len(), dry_run = args.dry_run, "removals");
+    let by = journal::local_user();
+    for (category, status) in &removals {
+        let id = status.id.to_string();
+        let url = status.url.as_deref().unwrap_or(&status.uri);
+        if args.dry_run {
+            println!("would be {} {id} {url}", category.action());
+            continue;
+        }
+        category
+            .remove(client, status)
+            .await
+            .with_context(|| format!("Couldn't remove {category:?} {id}"))?;
+        journal::record(category.action(), &id, &by, Some(url))?;
+        println!("{} {id} {url}", category.action());
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Statuses that are posted by the instance at a later time.
+//!
+//! See <https://docs.joinmastodon.org/methods/scheduled_statuses/>
+
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand};
+use serde::Deserialize;
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::config::Config;
+use crate::output::format_time_text;
+use crate::post::{self, StatusOptions};
+use crate::timestamp::{format_timestamp, parse_timestamp};
+
+/// Mastodon rejects statuses scheduled less than 5 minutes in the future.
+const MIN_DELAY: Duration = Duration::minutes(5);
+
+#[derive(Debug, Args)]
+pub struct ScheduleArgs {
+    #[command(subcommand)]
+    command: ScheduleCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum ScheduleCommand {
+    /// Schedule a status to be posted later
+    Post(SchedulePostArgs),
+    /// List the scheduled statuses
+    List,
+    /// Cancel a scheduled status
+    Cancel {
+        /// The id of the scheduled status
+        id: String,
+    },
+}
+
+#[derive(Debug, Args)]
+struct SchedulePostArgs {
+    /// The text of the status
+    text: String,
+
+    /// When to post the status, e.g. `2024-06-01T09:00Z`
+    #[arg(long, value_name = "TIMESTAMP", value_parser = parse_timestamp)]
+    at: OffsetDateTime,
+
+    #[command(flatten)]
+    options: StatusOptions,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduledStatus {
+    id: String,
+    scheduled_at: String,
+    params: ScheduledParams,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduledParams {
+    text: String,
+}
+
+pub async fn run(client: &Client, config: &Config, args: ScheduleArgs) -> Result<()> {
+    match args.command {
+        ScheduleCommand::Post(args) => schedule(client, config, args).await,
+        ScheduleCommand::List => list(client).await,
+        ScheduleCommand::Cancel { id } => cancel(client, &id).await,
+    }
+}
+
+/// Schedules a status through [`post::send`], so that it waits for approval
+/// first when the config requires it.
+#[instrument(skip(client, config), err)]
+async fn schedule(client: &Client, config: &Config, args: SchedulePostArgs) -> Result<()> {
+    check_delay(args.at)?;
+    let mut options = args.options;
+    options.scheduled_at = Some(format_timestamp(args.at));
+    post::send(client, config, args.text, options).await
+}
+
+fn check_delay(at: OffsetDateTime) -> Result<()> {
+    ensure!(
+        at - OffsetDateTime::now_utc() >= MIN_DELAY,
+        "statuses must be scheduled at least 5 minutes in the future"
+    );
+    Ok(())
+}
+
+/// Schedules a status for the `scheduled_at` of its options, and prints the
+/// scheduled status.
+///
+/// # Errors
+///
+/// Returns an error if the time is less than 5 minutes away, e.g. because the
+/// status waited for approval too long, or the status can't be scheduled.
+#[instrument(skip(client, options), err)]
+pub async fn publish(client: &Client, text: String, options: StatusOptions) -> Result<()> {
+    let at = options
+        .scheduled_at
+        .clone()
+        .context("the status has no time to be posted at")?;
+    check_delay(parse_timestamp(&at).map_err(anyhow::Error::msg)?)?;
+    let (new_status, poll) = post::prepare(client, text, options).await?;
+    let mut body = post::json_body(&new_status, poll)?;
+    body["scheduled_at"] = Value::String(at);
+    let scheduled: ScheduledStatus = client
+        .post_json("v1/statuses", &body)
+        .await
+        .context("Couldn't schedule status")?;
+    info!(id = scheduled.id, "scheduled status");
+    print_scheduled(&scheduled);
+    Ok(())
+}
+
+#[instrument(skip_all, err)]
+async fn list(client: &Client) -> Result<()> {
+    let scheduled: Vec<ScheduledStatus> = client
+        .get("v1/scheduled_statuses", &[("limit", "40")])
+        .await
+        .context("Couldn't get scheduled statuses")?;
+    for scheduled in &scheduled {
+        print_scheduled(scheduled);
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn cancel(client: &Client, id: &str) -> Result<()> {
+    let _: Value = client
+        .delete(&format!("v1/scheduled_statuses/{id}"))
+        .await
+        .context("Couldn't cancel scheduled status")?;
+    info!(id, "cancelled scheduled status");
+    Ok(())
+}
+
+fn print_scheduled(scheduled: &ScheduledStatus) {
+    println!(
+        "{} {} {}",
+        scheduled.id,
+        format_time_text(&scheduled.scheduled_at),
+        scheduled.params.text
+    );
+}
@@ -0,0 +1,271 @@
+//! Pseudonymization of exported data.
+//!
+//! Exports contain the handles, avatars and links of everyone who shows up in
+//! a timeline. The [`Scrubber`] replaces third-party handles with stable
+//! pseudonyms (`user-1`, `user-2`, ...) and removes avatars and URLs so that
+//! the exported data can be shared publicly.
+//!
+//! `--scrub` turns on one scrubber for the whole run, so the same handle has
+//! the same pseudonym in everything the run prints, writes and saves: the
+//! structured output, `archive create`, `status archive`, `export`, the local
+//! storage and `config export`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+use mastodon_async::prelude::{Account, Status};
+
+use crate::notifications::Notification;
+
+static SCRUBBER: Mutex<Option<Scrubber>> = Mutex::new(None);
+
+/// Scrubs everything from here on, leaving the authenticated account alone
+/// once [`set_own_acct`] knows it.
+pub fn enable() {
+    *SCRUBBER.lock().unwrap_or_else(PoisonError::into_inner) = Some(Scrubber::default());
+}
+
+/// Sets the handle of the authenticated account when scrubbing.
+pub fn set_own_acct(acct: &str) {
+    if let Some(scrubber) = SCRUBBER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_mut()
+    {
+        scrubber.own_acct = Some(acct.to_owned());
+    }
+}
+
+pub fn is_enabled() -> bool {
+    SCRUBBER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_some()
+}
+
+/// Runs a function with the scrubber of the run, or returns `None` without
+/// `--scrub`.
+pub fn with<T>(scrub: impl FnOnce(&mut Scrubber) -> T) -> Option<T> {
+    SCRUBBER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_mut()
+        .map(scrub)
+}
+
+/// A scrubbed copy of a value with `--scrub`, or the value itself without it.
+fn scrubbed<T: ToOwned + ?Sized>(
+    value: &T,
+    scrub: impl FnOnce(&mut Scrubber, &mut T::Owned),
+) -> Cow<'_, T> {
+    with(|scrubber| {
+        let mut owned = value.to_owned();
+        scrub(scrubber, &mut owned);
+        owned
+    })
+    .map_or(Cow::Borrowed(value), Cow::Owned)
+}
+
+pub fn status(status: &Status) -> Cow<'_, Status> {
+    scrubbed(status, Scrubber::scrub_status)
+}
+
+pub fn statuses(statuses: &[Status]) -> Cow<'_, [Status]> {
+    scrubbed(statuses, |scrubber, statuses: &mut Vec<Status>| {
+        statuses
+            .iter_mut()
+            .for_each(|status| scrubber.scrub_status(status));
+    })
+}
+
+pub fn account(account: &Account) -> Cow<'_, Account> {
+    scrubbed(account, Scrubber::scrub_account)
+}
+
+pub fn accounts(accounts: &[Account]) -> Cow<'_, [Account]> {
+    scrubbed(accounts, |scrubber, accounts: &mut Vec<Account>| {
+        accounts
+            .iter_mut()
+            .for_each(|account| scrubber.scrub_account(account));
+    })
+}
+
+pub fn notification(notification: &Notification) -> Cow<'_, Notification> {
+    scrubbed(notification, Scrubber::scrub_notification)
+}
+
+pub fn notifications(notifications: &[Notification]) -> Cow<'_, [Notification]> {
+    scrubbed(
+        notifications,
+        |scrubber, notifications: &mut Vec<Notification>| {
+            notifications
+                .iter_mut()
+                .for_each(|notification| scrubber.scrub_notification(notification));
+        },
+    )
+}
+
+#[derive(Debug, Default)]
+pub struct Scrubber {
+    /// The handle of the authenticated account, which is left untouched.
+    own_acct: Option<String>,
+    /// Maps real handles to pseudonyms. Pseudonyms are assigned in order of
+    /// first appearance so that the same handle always maps to the same
+    /// pseudonym within an export.
+    pseudonyms: HashMap<String, String>,
+}
+
+impl Scrubber {
+    /// Returns the pseudonym for a handle, or the handle itself when it is the
+    /// authenticated account or already a pseudonym, so that scrubbing twice
+    /// changes nothing.
+    pub fn pseudonym(&mut self, acct: &str) -> String {
+        if self.own_acct.as_deref() == Some(acct)
+            || self.pseudonyms.values().any(|pseudonym| pseudonym == acct)
+        {
+            return acct.to_owned();
+        }
+        let next = self.pseudonyms.len() + 1;
+        self.pseudonyms
+            .entry(acct.to_owned())
+            .or_insert_with(|| format!("user-{next}"))
+            .clone()
+    }
+
+    pub fn scrub_account(&mut self, account: &mut Account) {
+        let pseudonym = self.pseudonym(&account.acct);
+        if pseudonym == account.acct {
+            return;
+        }
+        account.acct = pseudonym.clone();
+        account.username = pseudonym.clone();
+        account.display_name = pseudonym;
+        account.note.clear();
+        account.url.clear();
+        account.avatar.clear();
+        account.avatar_static.clear();
+        account.header.clear();
+        account.header_static.clear();
+    }
+
+    pub fn scrub_status(&mut self, status: &mut Status) {
+        // replace the mention links in the content before the mentions
+        // themselves are scrubbed, as they are found by their real URL and name
+        let mut pseudonyms = Vec::with_capacity(status.mentions.len());
+        for mention in &mut status.mentions {
+            let pseudonym = self.pseudonym(&mention.acct);
+            pseudonyms.push(MentionPseudonym {
+                url: std::mem::take(&mut mention.url),
+                names: [
+                    format!("@{}", mention.username).to_lowercase(),
+                    format!("@{}", mention.acct).to_lowercase(),
+                ],
+                pseudonym: pseudonym.clone(),
+            });
+            mention.acct = pseudonym.clone();
+            mention.username = pseudonym;
+        }
+        status.content = strip_links(&replace_mentions(&status.content, &pseudonyms));
+        self.scrub_account(&mut status.account);
+        status.uri.clear();
+        status.url = None;
+        status.card = None;
+        status.media_attachments.clear();
+        if let Some(reblog) = status.reblog.as_mut() {
+            self.scrub_status(reblog);
+        }
+    }
+
+    pub fn scrub_notification(&mut self, notification: &mut Notification) {
+        self.scrub_account(&mut notification.account);
+        if let Some(status) = notification.status.as_mut() {
+            self.scrub_status(status);
+        }
+    }
+}
+
+/// A mentioned account, as it appears in the content of a status.
+struct MentionPseudonym {
+    url: String,
+    /// `@username` and `@username@domain`, in lowercase.
+    names: [String; 2],
+    pseudonym: String,
+}
+
+/// Replaces the text of each mention link, `@username` or `@username@domain`,
+/// with the pseudonym of the account it links to, leaving the rest of the HTML
+/// alone. The links are matched by their `href`, or by their text when the
+/// URL differs.
+fn replace_mentions(html: &str, mentions: &[MentionPseudonym]) -> String {
+    let mut replaced = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<a ") {
+        let (before, link) = rest.split_at(start);
+        replaced.push_str(before);
+        let Some((tag_end, close)) = link.find('>').and_then(|tag_end| {
+            let close = link[tag_end..].find("</a>")? + tag_end;
+            Some((tag_end + 1, close))
+        }) else {
+            rest = link;
+            break;
+        };
+        let tag = &link[..tag_end];
+        let text = strip_tags(&link[tag_end..close]).to_lowercase();
+        let mention = mentions.iter().find(|mention| {
+            tag.contains("mention")
+                && ((!mention.url.is_empty() && href(tag) == Some(mention.url.as_str()))
+                    || mention.names.contains(&text))
+        });
+        match mention {
+            Some(mention) => {
+                replaced.push_str(tag);
+                replaced.push_str("@<span>");
+                replaced.push_str(&mention.pseudonym);
+                replaced.push_str("</span>");
+            }
+            None => replaced.push_str(&link[..close]),
+        }
+        replaced.push_str("</a>");
+        rest = &link[close + "</a>".len()..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+/// The target of the `href` attribute of an opening tag.
+fn href(tag: &str) -> Option<&str> {
+    let start = tag.find("href=\"")? + "href=\"".len();
+    let end = tag[start..].find('"')?;
+    Some(&tag[start..start + end])
+}
+
+/// The text of an HTML fragment, without its tags.
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Removes the target of every `href` attribute in an HTML fragment, keeping
+/// the link text.
+fn strip_links(html: &str) -> String {
+    const HREF: &str = "href=\"";
+    let mut scrubbed = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(HREF) {
+        let (before, after) = rest.split_at(start + HREF.len());
+        scrubbed.push_str(before);
+        rest = after.find('"').map_or("", |end| &after[end..]);
+    }
+    scrubbed.push_str(rest);
+    scrubbed
+}
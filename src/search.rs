@@ -0,0 +1,174 @@
+//! Searching for accounts, statuses and hashtags.
+
+mod local;
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use mastodon_async::prelude::{Account, Status};
+use serde::Deserialize;
+use tracing::{debug, instrument};
+
+use crate::client::Client;
+use crate::output::{format_time_text, is_structured, print_account, print_status};
+use crate::resolve::resolve_account;
+use crate::storage;
+use crate::tags::Tag;
+pub use local::remember;
+
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// What to search for, e.g. a name, `#hashtag`, `@someone@example.com` or
+    /// a URL
+    query: String,
+
+    /// Only search for one type of result
+    #[arg(long = "type", value_enum)]
+    kind: Option<SearchType>,
+
+    /// Ask the instance to fetch accounts and statuses it hasn't seen yet when
+    /// the query is an address or URL
+    #[arg(long)]
+    resolve: bool,
+
+    /// Only find statuses posted by this account, e.g. `@someone@example.com`
+    #[arg(long, value_name = "ACCOUNT")]
+    account: Option<String>,
+
+    /// Show at most this many results of each type [default: one page]
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Search the statuses this tool has shown before instead of asking the
+    /// instance. Needs the `local-search` feature.
+    #[arg(long, conflicts_with_all = ["kind", "resolve", "account"])]
+    local: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SearchType {
+    Accounts,
+    Statuses,
+    Hashtags,
+}
+
+impl SearchType {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Accounts => "accounts",
+            Self::Statuses => "statuses",
+            Self::Hashtags => "hashtags",
+        }
+    }
+}
+
+/// The results of a search, which are also how URLs are resolved.
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchResults {
+    #[serde(default)]
+    pub accounts: Vec<Account>,
+    #[serde(default)]
+    pub statuses: Vec<Status>,
+    #[serde(default)]
+    pub hashtags: Vec<Tag>,
+}
+
+/// Searches and prints the results grouped by type. Searching statuses needs an
+/// instance with full text search set up, otherwise only statuses you have
+/// interacted with are found.
+#[instrument(skip(client), err)]
+pub async fn run(client: &Client, args: SearchArgs) -> Result<()> {
+    if args.local {
+        let limit = match args.limit {
+            Some(limit) => limit,
+            None => client.page_limit().parse()?,
+        };
+        for hit in local::search(client, &args.query, limit)? {
+            println!(
+                "@{} {} {}",
+                hit.author,
+                format_time_text(&hit.created_at),
+                hit.url
+            );
+        }
+        return Ok(());
+    }
+    let account_id = match &args.account {
+        Some(acct) => Some(resolve_account(client, acct).await?.id.to_string()),
+        None => None,
+    };
+    let page_size: usize = client.page_limit().parse()?;
+    let mut results = SearchResults::default();
+    // each type is paged separately by the same offset
+    let mut offset = 0;
+    loop {
+        let page_limit = page_size.to_string();
+        let offset_param = offset.to_string();
+        let mut query = vec![
+            ("q", args.query.as_str()),
+            ("limit", page_limit.as_str()),
+            ("offset", offset_param.as_str()),
+        ];
+        if let Some(kind) = args.kind {
+            query.push(("type", kind.as_str()));
+        }
+        if args.resolve {
+            query.push(("resolve", "true"));
+        }
+        if let Some(account_id) = &account_id {
+            query.push(("account_id", account_id.as_str()));
+        }
+        let page: SearchResults = client
+            .get("v2/search", &query)
+            .await
+            .with_context(|| format!("Couldn't search for {:?}", args.query))?;
+        let longest = page
+            .accounts
+            .len()
+            .max(page.statuses.len())
+            .max(page.hashtags.len());
+        debug!(offset, longest, "loaded page of results");
+        results.accounts.extend(page.accounts);
+        results.statuses.extend(page.statuses);
+        results.hashtags.extend(page.hashtags);
+        offset += page_size;
+        let Some(limit) = args.limit else {
+            break;
+        };
+        if longest < page_size || offset >= limit {
+            break;
+        }
+    }
+    if let Some(limit) = args.limit {
+        results.accounts.truncate(limit);
+        results.statuses.truncate(limit);
+        results.hashtags.truncate(limit);
+    }
+    storage::remember_accounts(client, &results.accounts);
+    storage::remember(client, &results.statuses);
+    print_results(&results);
+    Ok(())
+}
+
+/// Prints each type of result under a heading, skipping the empty ones. JSON
+/// and CSV output only have the accounts and statuses, without headings.
+fn print_results(results: &SearchResults) {
+    let headings = !is_structured();
+    if !results.accounts.is_empty() {
+        if headings {
+            println!("accounts:");
+        }
+        results.accounts.iter().for_each(print_account);
+    }
+    if !results.statuses.is_empty() {
+        if headings {
+            println!("statuses:");
+        }
+        results.statuses.iter().for_each(print_status);
+    }
+    if !results.hashtags.is_empty() && headings {
+        println!("hashtags:");
+        for tag in &results.hashtags {
+            println!("#{} {}", tag.name, tag.url);
+        }
+    }
+}
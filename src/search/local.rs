@@ -0,0 +1,167 @@
+//! A full text index of the statuses that this tool has shown, for searching
+//! old statuses even when the instance doesn't have full text search set up.
+//!
+//! Statuses are added to the index in the `search-index` folder of the cache
+//! folder as the home timeline and account statuses are read, and the content,
+//! content warning and author of each are indexed. The index needs the
+//! `local-search` feature; without it, nothing is indexed.
+
+use mastodon_async::prelude::Status;
+use tracing::warn;
+
+use crate::client::Client;
+
+/// A status found in the index.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "local-search"), allow(dead_code))]
+pub struct Hit {
+    pub url: String,
+    pub author: String,
+    pub created_at: String,
+}
+
+/// Adds the statuses to the index, replacing any older copies of them. The
+/// index is only a convenience, so failing to update it is logged rather than
+/// stopping the command.
+pub fn remember(client: &Client, statuses: &[Status]) {
+    if statuses.is_empty() {
+        return;
+    }
+    if let Err(err) = index::add(client, statuses) {
+        warn!(?err, "couldn't add statuses to the local search index");
+    }
+}
+
+#[cfg(feature = "local-search")]
+pub use index::search;
+
+#[cfg(not(feature = "local-search"))]
+pub fn search(_client: &Client, _query: &str, _limit: usize) -> anyhow::Result<Vec<Hit>> {
+    anyhow::bail!("this was built without the local-search feature")
+}
+
+#[cfg(not(feature = "local-search"))]
+mod index {
+    use mastodon_async::prelude::Status;
+
+    use crate::client::Client;
+
+    #[allow(clippy::unnecessary_wraps)] // matches the feature's signature
+    pub fn add(_client: &Client, _statuses: &[Status]) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "local-search")]
+mod index {
+    use std::fs;
+
+    use anyhow::{Context, Result};
+    use mastodon_async::prelude::Status;
+    use tantivy::collector::TopDocs;
+    use tantivy::directory::MmapDirectory;
+    use tantivy::query::QueryParser;
+    use tantivy::schema::{Field, Schema, STORED, STRING, TEXT};
+    use tantivy::{doc, Document, Index, Term};
+    use tracing::{debug, instrument};
+
+    use super::Hit;
+    use crate::client::Client;
+    use crate::html;
+    use crate::timestamp::format_timestamp;
+
+    /// The memory used for adding documents, split between the indexing
+    /// threads. Each thread needs at least 15 MB.
+    const WRITER_MEMORY: usize = 50_000_000;
+
+    struct Fields {
+        id: Field,
+        url: Field,
+        author: Field,
+        content: Field,
+        cw: Field,
+        created_at: Field,
+    }
+
+    fn schema() -> (Schema, Fields) {
+        let mut builder = Schema::builder();
+        let fields = Fields {
+            id: builder.add_text_field("id", STRING | STORED),
+            url: builder.add_text_field("url", STORED),
+            author: builder.add_text_field("author", TEXT | STORED),
+            content: builder.add_text_field("content", TEXT),
+            cw: builder.add_text_field("cw", TEXT),
+            created_at: builder.add_text_field("created_at", STORED),
+        };
+        (builder.build(), fields)
+    }
+
+    fn open(client: &Client) -> Result<(Index, Fields)> {
+        let path = client.cache_dir().join("search-index");
+        fs::create_dir_all(&path).with_context(|| format!("cannot create {path:?}"))?;
+        let directory =
+            MmapDirectory::open(&path).with_context(|| format!("cannot open index {path:?}"))?;
+        let (schema, fields) = schema();
+        let index = Index::open_or_create(directory, schema)
+            .with_context(|| format!("cannot open index {path:?}"))?;
+        Ok((index, fields))
+    }
+
+    #[instrument(skip_all, fields(count = statuses.len()), err)]
+    pub fn add(client: &Client, statuses: &[Status]) -> Result<()> {
+        let (index, fields) = open(client)?;
+        let mut writer = index
+            .writer(WRITER_MEMORY)
+            .context("Couldn't lock the search index")?;
+        for status in statuses {
+            // boosts are indexed as the boosted status
+            let status = status.reblog.as_deref().unwrap_or(status);
+            let id = status.id.to_string();
+            writer.delete_term(Term::from_field_text(fields.id, &id));
+            writer.add_document(doc!(
+                fields.id => id,
+                fields.url => status.url.clone().unwrap_or_else(|| status.uri.clone()),
+                fields.author => status.account.acct.clone(),
+                fields.content => html::to_text(&status.content),
+                fields.cw => status.spoiler_text.clone(),
+                fields.created_at => format_timestamp(status.created_at),
+            ))?;
+        }
+        writer.commit().context("Couldn't save the search index")?;
+        debug!("indexed statuses");
+        Ok(())
+    }
+
+    /// Searches the index with tantivy's query syntax, e.g. `rust AND async`
+    /// or `author:someone`, returning the best matches first.
+    #[instrument(skip(client), err)]
+    pub fn search(client: &Client, query: &str, limit: usize) -> Result<Vec<Hit>> {
+        let (index, fields) = open(client)?;
+        let searcher = index
+            .reader()
+            .context("Couldn't read the search index")?
+            .searcher();
+        let parser = QueryParser::for_index(&index, vec![fields.content, fields.cw, fields.author]);
+        let parsed = parser
+            .parse_query(query)
+            .with_context(|| format!("{query:?} is not a valid query"))?;
+        let top = searcher.search(&parsed, &TopDocs::with_limit(limit))?;
+        top.into_iter()
+            .map(|(_score, address)| {
+                let document: Document = searcher.doc(address)?;
+                let text = |field| {
+                    document
+                        .get_first(field)
+                        .and_then(|value| value.as_text())
+                        .unwrap_or_default()
+                        .to_owned()
+                };
+                Ok(Hit {
+                    url: text(fields.url),
+                    author: text(fields.author),
+                    created_at: text(fields.created_at),
+                })
+            })
+            .collect()
+    }
+}
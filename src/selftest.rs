@@ -0,0 +1,150 @@
+//! A smoke test of the instance and the saved login, running the same kind of
+//! requests that this crate was first written to exercise.
+//!
+//! Every step is read only, except for `--post`, which posts a direct status
+//! that only the account itself can see and then deletes it again. When the
+//! config requires approval, the status is queued instead and left for a
+//! reviewer to reject.
+
+use std::future::Future;
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use mastodon_async::prelude::{Account, Status};
+use tracing::instrument;
+
+use crate::client::Client;
+use crate::config::Config;
+use crate::instance;
+use crate::post::{self, StatusOptions};
+use crate::queue;
+use crate::resolve::resolve_account;
+
+#[derive(Debug, Args)]
+pub struct SelftestArgs {
+    /// The account to resolve [default: your own account]
+    #[arg(long, value_name = "ACCT")]
+    account: Option<String>,
+
+    /// Also post a direct status to yourself and delete it
+    #[arg(long)]
+    post: bool,
+}
+
+impl SelftestArgs {
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        if self.post {
+            &["read:accounts", "read:statuses", "write:statuses"]
+        } else {
+            &["read:accounts", "read:statuses"]
+        }
+    }
+}
+
+/// The results printed so far.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub failed: usize,
+    pub passed: usize,
+}
+
+impl Report {
+    /// Runs a step and prints whether it passed and how long it took.
+    pub async fn step<T>(
+        &mut self,
+        name: &str,
+        step: impl Future<Output = Result<T>>,
+    ) -> Option<T> {
+        let started = Instant::now();
+        let result = step.await;
+        let elapsed = started.elapsed().as_millis();
+        match result {
+            Ok(value) => {
+                println!("pass {name} ({elapsed} ms)");
+                self.passed += 1;
+                Some(value)
+            }
+            Err(err) => {
+                println!("FAIL {name} ({elapsed} ms): {err:#}");
+                self.failed += 1;
+                None
+            }
+        }
+    }
+
+    pub fn skip(name: &str) {
+        println!("skip {name}");
+    }
+}
+
+#[instrument(skip(client, config), err)]
+pub async fn run(client: &Client, config: &Config, args: SelftestArgs) -> Result<()> {
+    let mut report = Report::default();
+    let me: Option<Account> = report
+        .step("verify credentials", async {
+            client
+                .verify_credentials()
+                .await
+                .context("Couldn't get account")
+        })
+        .await;
+    report
+        .step("fetch a page of the home timeline", async {
+            let page: Vec<Status> = client
+                .get("v1/timelines/home", &[("limit", client.page_limit())])
+                .await
+                .context("Couldn't get timeline")?;
+            Ok(page.len())
+        })
+        .await;
+    let acct = args
+        .account
+        .or_else(|| me.as_ref().map(|me| me.acct.clone()));
+    match acct {
+        Some(acct) => {
+            report
+                .step("resolve an account", resolve_account(client, &acct))
+                .await;
+        }
+        None => Report::skip("resolve an account"),
+    }
+    report
+        .step("check the streaming server", async {
+            let streaming_api = instance::streaming_api(client)
+                .await?
+                .context("the instance doesn't report a streaming server")?;
+            instance::streaming_health(client, &streaming_api).await
+        })
+        .await;
+    if args.post && config.require_approval {
+        report
+            .step("queue a direct status for approval", async {
+                let pending =
+                    queue::submit(client, config, TEXT.into(), StatusOptions::direct()).await?;
+                println!("queued {}, reject it with queue reject", pending.id);
+                Ok(())
+            })
+            .await;
+    } else if args.post {
+        report
+            .step("post and delete a direct status", post_and_delete(client))
+            .await;
+    }
+    if report.failed > 0 {
+        bail!("{} steps failed", report.failed);
+    }
+    Ok(())
+}
+
+/// The text of the status posted by `--post`.
+const TEXT: &str = "spike-mastodon selftest, deleted right away";
+
+async fn post_and_delete(client: &Client) -> Result<()> {
+    let status = post::publish(client, TEXT.into(), StatusOptions::direct()).await?;
+    let _: Status = client
+        .delete(&format!("v1/statuses/{}", status.id))
+        .await
+        .with_context(|| format!("Couldn't delete status {}", status.id))?;
+    Ok(())
+}
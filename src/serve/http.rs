@@ -0,0 +1,202 @@
+//! A REST API for local integrations (launchers, stream decks, etc.) that
+//! exposes a small, stable subset of the JSON-RPC methods:
+//!
+//! | Request                   | Method          | Body                 |
+//! |---------------------------|-----------------|----------------------|
+//! | `GET /v1/timelines/home`  | `timeline_home` |                      |
+//! | `GET /v1/notifications`   | `notifications` |                      |
+//! | `POST /v1/statuses`       | `post`          | `{"text": "hello"}`  |
+//!
+//! With `--metrics`, `GET /metrics` returns the [`metrics`] in the Prometheus
+//! text format.
+//!
+//! A web page open in the browser can send requests to localhost too, so
+//! every request needs the token written to `http-token` in the runtime
+//! folder when the API starts, as `Authorization: Bearer <token>`, and a
+//! `Host` of the loopback address and port the API listens on. The `Host`
+//! check stops pages on another domain that resolves to the loopback address
+//! from reading the API.
+//!
+//! ```shell
+//! curl -H "Authorization: Bearer $(cat /run/user/1000/mastodon-async/http-token)" \
+//!     http://127.0.0.1:8080/v1/timelines/home
+//! ```
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use axum::extract::State;
+use axum::http::header::{AUTHORIZATION, HOST};
+use axum::http::{HeaderName, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use rand_core::{OsRng, RngCore};
+use serde_json::Value;
+use tracing::{info, instrument, warn};
+
+use super::{call, private_runtime_folder, Daemon, RpcError};
+use crate::metrics;
+use crate::queue;
+
+/// What a request needs to be let through.
+struct Access {
+    /// The values of `Host` that name the API.
+    hosts: Vec<String>,
+    token: String,
+}
+
+#[instrument(skip(daemon), err)]
+pub async fn serve(daemon: Daemon, addr: SocketAddr, with_metrics: bool) -> Result<()> {
+    let token = new_token();
+    let path = save_token(&token)?;
+    info!(?path, "wrote the http api token");
+    let access = Arc::new(Access {
+        hosts: hosts(addr.port()),
+        token,
+    });
+    let mut app = Router::new()
+        .route("/v1/timelines/home", get(home_timeline))
+        .route("/v1/notifications", get(notifications))
+        .route("/v1/statuses", post(post_status));
+    if with_metrics {
+        app = app.route("/metrics", get(render_metrics));
+    }
+    let app = app
+        .layer(middleware::from_fn_with_state(access, check_access))
+        .with_state(daemon);
+    let server = axum::Server::try_bind(&addr).with_context(|| format!("cannot bind {addr}"))?;
+    info!(%addr, "listening for http requests");
+    server
+        .serve(app.into_make_service())
+        .await
+        .context("http server failed")
+}
+
+/// The names of the loopback address with the port, as clients send them in
+/// `Host`, which leaves out the default port.
+fn hosts(port: u16) -> Vec<String> {
+    let names = ["localhost", "127.0.0.1", "[::1]"];
+    let mut hosts: Vec<String> = names.iter().map(|name| format!("{name}:{port}")).collect();
+    if port == 80 {
+        hosts.extend(names.iter().map(|&name| name.to_owned()));
+    }
+    hosts
+}
+
+/// A random token for this run, as hex.
+fn new_token() -> String {
+    let mut bytes = [0; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Writes the token to `http-token` in the runtime folder, readable only by
+/// the user.
+fn save_token(token: &str) -> Result<PathBuf> {
+    let path = private_runtime_folder()?.join("http-token");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(&path)
+        .with_context(|| format!("cannot open file {path:?}"))?;
+    file.write_all(token.as_bytes())
+        .with_context(|| format!("cannot write file {path:?}"))?;
+    Ok(path)
+}
+
+/// Rejects requests for another `Host`, such as a DNS rebinding page, and
+/// requests without the token.
+async fn check_access<B>(
+    State(access): State<Arc<Access>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let header = |name: HeaderName| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    let host = header(HOST);
+    if !host.map_or(false, |host| {
+        access
+            .hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }) {
+        warn!(?host, "rejected http request for another host");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let token = header(AUTHORIZATION).and_then(|value| value.strip_prefix("Bearer "));
+    if !token.map_or(false, |token| same_token(token, &access.token)) {
+        warn!("rejected http request without the token");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+/// Compares tokens in a time that doesn't depend on where they differ.
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn home_timeline(State(daemon): State<Daemon>) -> Result<Json<Value>, HttpError> {
+    Ok(Json(call(&daemon, "timeline_home", Value::Null).await?))
+}
+
+async fn notifications(State(daemon): State<Daemon>) -> Result<Json<Value>, HttpError> {
+    Ok(Json(call(&daemon, "notifications", Value::Null).await?))
+}
+
+async fn post_status(
+    State(daemon): State<Daemon>,
+    Json(params): Json<Value>,
+) -> Result<Json<Value>, HttpError> {
+    Ok(Json(call(&daemon, "post", params).await?))
+}
+
+async fn render_metrics(State(daemon): State<Daemon>) -> String {
+    let depth = daemon
+        .config
+        .read()
+        .map_err(|_| anyhow!("config lock poisoned"))
+        .and_then(|config| queue::depth(&config));
+    match depth {
+        Ok(depth) => metrics::QUEUE_DEPTH.set(u64::try_from(depth).unwrap_or(u64::MAX)),
+        Err(err) => warn!(?err, "couldn't count the queued posts"),
+    }
+    metrics::render()
+}
+
+struct HttpError(RpcError);
+
+impl From<RpcError> for HttpError {
+    fn from(err: RpcError) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        let status = match self.0.code {
+            RpcError::INVALID_PARAMS => StatusCode::BAD_REQUEST,
+            RpcError::METHOD_NOT_FOUND => StatusCode::NOT_FOUND,
+            // everything else is a failure talking to the instance
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        (status, Json(self.0)).into_response()
+    }
+}
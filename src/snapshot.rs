@@ -0,0 +1,144 @@
+//! Self contained HTML snapshots of statuses, as evidence of what was posted
+//! in case it is later edited or deleted.
+//!
+//! A snapshot has the status with its whole thread and every media attachment
+//! embedded, so it can be opened without a network connection. The content of
+//! each status is sanitized, as it comes from whichever server it was posted
+//! on, and attachments too large to download are linked instead. Snapshots are
+//! saved to the `archive` folder in the data folder as
+//! `<status id>-<timestamp>.html`, alongside a `.sha256` file with the hash of
+//! the HTML.
+
+use std::fmt::Write;
+use std::fs;
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use mastodon_async::prelude::Status;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use tracing::{info, instrument, warn};
+
+use crate::client::Client;
+use crate::config::data_folder;
+use crate::html::{escape, sanitize};
+use crate::resolve::resolve_status;
+use crate::timestamp::format_timestamp;
+use crate::{scrub, thread};
+
+/// The parts of a media attachment that are embedded. The URL is missing
+/// while the attachment is still processing.
+#[derive(Debug, Deserialize)]
+struct Media {
+    url: Option<String>,
+    description: Option<String>,
+}
+
+/// Saves a snapshot of a status and prints the path and hash.
+#[instrument(skip(client), err)]
+pub async fn archive(client: &Client, id_or_url: &str) -> Result<()> {
+    let status = resolve_status(client, id_or_url).await?;
+    let context = thread::context(client, &status).await?;
+    let taken_at = OffsetDateTime::now_utc();
+    let status = scrub::status(&status);
+
+    let mut body = String::new();
+    for ancestor in scrub::statuses(&context.ancestors).iter() {
+        render_status(client, &mut body, ancestor, false).await?;
+    }
+    render_status(client, &mut body, &status, true).await?;
+    for descendant in scrub::statuses(&context.descendants).iter() {
+        render_status(client, &mut body, descendant, false).await?;
+    }
+    let url = status.url.as_deref().unwrap_or(&status.uri);
+    let html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <header>Snapshot of <a href=\"{url}\">{url}</a> taken at {taken_at}</header>\n\
+         {body}</body>\n</html>\n",
+        title = escape(&format!("@{}", status.account.acct)),
+        url = escape(url),
+        taken_at = format_timestamp(taken_at),
+    );
+
+    let hash = format!("{:x}", Sha256::digest(html.as_bytes()));
+    let folder = data_folder()?.join("archive");
+    fs::create_dir_all(&folder).with_context(|| format!("cannot create {folder:?}"))?;
+    let name = format!("{}-{}", status.id, taken_at.unix_timestamp());
+    let path = folder.join(format!("{name}.html"));
+    fs::write(&path, &html).with_context(|| format!("cannot write file {path:?}"))?;
+    let hash_path = folder.join(format!("{name}.sha256"));
+    fs::write(&hash_path, format!("{hash}  {name}.html\n"))
+        .with_context(|| format!("cannot write file {hash_path:?}"))?;
+    info!(?path, hash, "saved snapshot");
+    println!("{} {hash}", path.display());
+    Ok(())
+}
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:40em;margin:auto}\
+article{border-bottom:1px solid #ccc;padding:1em 0}\
+article.focus{background:#ffd}img,video{max-width:100%}";
+
+async fn render_status(
+    client: &Client,
+    html: &mut String,
+    status: &Status,
+    focus: bool,
+) -> Result<()> {
+    let class = if focus { " class=\"focus\"" } else { "" };
+    let url = status.url.as_deref().unwrap_or(&status.uri);
+    writeln!(
+        html,
+        "<article{class}>\n<p><strong>{name}</strong> @{acct} · \
+         <a href=\"{url}\">{created_at}</a></p>",
+        name = escape(&status.account.display_name),
+        acct = escape(&status.account.acct),
+        url = escape(url),
+        created_at = format_timestamp(status.created_at),
+    )?;
+    if !status.spoiler_text.is_empty() {
+        writeln!(html, "<p><em>CW: {}</em></p>", escape(&status.spoiler_text))?;
+    }
+    // the content comes from whichever server the status was posted on
+    writeln!(html, "{}", sanitize(&status.content))?;
+    let media: Vec<Media> =
+        serde_json::from_value(serde_json::to_value(&status.media_attachments)?)?;
+    for media in &media {
+        let description = media.description.as_deref().unwrap_or_default();
+        let Some(media_url) = &media.url else {
+            continue;
+        };
+        match client.download(media_url).await {
+            Ok((bytes, content_type)) => {
+                let content_type =
+                    content_type.unwrap_or_else(|| "application/octet-stream".to_owned());
+                let data = format!("data:{content_type};base64,{}", BASE64.encode(bytes));
+                let tag = if content_type.starts_with("video/") {
+                    format!("<video controls src=\"{data}\"></video>")
+                } else if content_type.starts_with("audio/") {
+                    format!("<audio controls src=\"{data}\"></audio>")
+                } else {
+                    format!("<img src=\"{data}\" alt=\"{}\">", escape(description))
+                };
+                writeln!(
+                    html,
+                    "<figure>{tag}<figcaption>{}</figcaption></figure>",
+                    escape(description)
+                )?;
+            }
+            Err(err) => {
+                // a snapshot without one attachment is better than none
+                warn!(?err, url = media_url, "couldn't embed media");
+                writeln!(
+                    html,
+                    "<p>Media not saved: <a href=\"{url}\">{url}</a></p>",
+                    url = escape(media_url)
+                )?;
+            }
+        }
+    }
+    writeln!(html, "</article>")?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! Splitting long text into a numbered thread of statuses.
+
+/// Splits the text into parts of at most `limit` characters including a
+/// ` (1/3)` style suffix, breaking between words where possible. Text that
+/// fits in a single status is returned as is, without a suffix.
+///
+/// Characters are counted one by one, which overestimates the length of long
+/// links as Mastodon counts every link as 23 characters.
+pub fn split_thread(text: &str, limit: usize) -> Vec<String> {
+    let text = text.trim();
+    if text.chars().count() <= limit {
+        return vec![text.to_owned()];
+    }
+    // the suffix gets longer with the number of parts, so try more digits
+    // until the parts fit
+    let mut digits = 1;
+    loop {
+        let suffix_len = " (/)".len() + 2 * digits;
+        let budget = limit.saturating_sub(suffix_len).max(1);
+        let parts = pack(text, budget);
+        if parts.len().to_string().len() <= digits {
+            let count = parts.len();
+            return parts
+                .into_iter()
+                .enumerate()
+                .map(|(index, part)| format!("{part} ({}/{count})", index + 1))
+                .collect();
+        }
+        digits += 1;
+    }
+}
+
+/// Packs words into parts of at most `budget` characters, keeping the line
+/// breaks within each part. Words longer than a whole part are broken up.
+fn pack(text: &str, budget: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut part_len = 0;
+    for (separator, word) in words(text) {
+        let separator_len = if part.is_empty() {
+            0
+        } else {
+            separator.chars().count()
+        };
+        let word_len = word.chars().count();
+        if part_len + separator_len + word_len <= budget {
+            if !part.is_empty() {
+                part.push_str(separator);
+            }
+            part.push_str(word);
+            part_len += separator_len + word_len;
+            continue;
+        }
+        if !part.is_empty() {
+            parts.push(std::mem::take(&mut part));
+            part_len = 0;
+        }
+        let mut chars: Vec<char> = word.chars().collect();
+        while chars.len() > budget {
+            parts.push(chars.drain(..budget).collect());
+        }
+        part = chars.into_iter().collect();
+        part_len = part.chars().count();
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+/// The words of the text, each with the whitespace before it.
+fn words(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let start = rest.find(|c: char| !c.is_whitespace())?;
+        let end = rest[start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |end| start + end);
+        let item = (&rest[..start], &rest[start..end]);
+        rest = &rest[end..];
+        Some(item)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_one_status_without_a_suffix() {
+        assert_eq!(split_thread("  Hello there \n", 500), ["Hello there"]);
+    }
+
+    #[test]
+    fn long_text_breaks_between_words() {
+        assert_eq!(
+            split_thread("one two three four", 14),
+            ["one two (1/3)", "three (2/3)", "four (3/3)"]
+        );
+    }
+
+    #[test]
+    fn words_longer_than_a_status_are_broken_up() {
+        assert_eq!(
+            split_thread("abcdefghij", 8),
+            ["ab (1/5)", "cd (2/5)", "ef (3/5)", "gh (4/5)", "ij (5/5)"]
+        );
+    }
+
+    #[test]
+    fn line_breaks_are_kept_within_a_part() {
+        assert_eq!(
+            split_thread("one\ntwo three four", 14),
+            ["one\ntwo (1/3)", "three (2/3)", "four (3/3)"]
+        );
+    }
+
+    #[test]
+    fn parts_fit_with_a_longer_suffix() {
+        let text = "word ".repeat(100);
+        let parts = split_thread(&text, 20);
+        assert!(parts.len() >= 10);
+        assert!(parts.iter().all(|part| part.chars().count() <= 20));
+        assert!(parts[0].ends_with(&format!(" (1/{})", parts.len())));
+    }
+}
@@ -0,0 +1,372 @@
+//! Managing individual statuses.
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::{Status, StatusId};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::instance;
+use crate::output::{format_time_text, print_status, render_html};
+use crate::resolve::{resolve_account, resolve_status};
+use crate::snapshot;
+use crate::storage::Storage;
+use crate::thread;
+use crate::timestamp::parse_timestamp;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    #[command(subcommand)]
+    command: StatusCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum StatusCommand {
+    /// Delete one of your statuses
+    Delete {
+        /// The id of the status
+        id: String,
+
+        /// Print the text, content warning and media of the deleted status so
+        /// that it can be posted again after editing. The media can be
+        /// attached again with `post --media-id`.
+        #[arg(long)]
+        redraft: bool,
+    },
+    /// Save a self contained HTML snapshot of a status and its thread, with a
+    /// hash of the snapshot
+    Archive {
+        /// The id or URL of the status
+        status: String,
+    },
+    /// Change the text of one of your statuses
+    Edit {
+        /// The id of the status
+        id: String,
+
+        /// The new text
+        text: String,
+
+        /// Change the content warning [default: keep the current one]
+        #[arg(long, value_name = "SPOILER TEXT")]
+        cw: Option<String>,
+    },
+    /// Show the previous revisions of an edited status
+    History {
+        /// The id of the status
+        id: String,
+    },
+    /// Pin one of your statuses to your profile
+    Pin {
+        /// The id of the status
+        id: String,
+    },
+    /// Unpin a status from your profile
+    Unpin {
+        /// The id of the status
+        id: String,
+    },
+    /// List the statuses pinned to an account's profile
+    Pinned {
+        /// The account, e.g. `@someone@example.com` or a profile URL
+        acct: String,
+    },
+    /// Translate a status using the instance's translation service
+    Translate {
+        /// The id or URL of the status
+        status: String,
+
+        /// The language to translate to, e.g. `de` [default: the account's
+        /// interface language]
+        #[arg(long, value_name = "CODE")]
+        to: Option<String>,
+    },
+    /// Show the whole thread that a status is part of, indented by reply
+    Thread {
+        /// The id or URL of the status
+        status: String,
+
+        /// Print the thread as JSON, with the replies to each status nested
+        /// under it
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// A revision of a status.
+#[derive(Debug, Deserialize)]
+struct StatusEdit {
+    content: String,
+    spoiler_text: String,
+    created_at: String,
+}
+
+/// The parts of a poll that an edit has to send back to keep it.
+#[derive(Debug, Deserialize)]
+struct EditedPoll {
+    expires_at: Option<String>,
+    expired: bool,
+    multiple: bool,
+    options: Vec<EditedPollOption>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EditedPollOption {
+    title: String,
+    /// Hidden until the poll closes when the poll hides its totals.
+    votes_count: Option<u64>,
+}
+
+/// The shortest poll that Mastodon accepts.
+const MIN_POLL_SECONDS: i64 = 300;
+
+impl EditedPoll {
+    /// The poll as the edit endpoint takes it. The same options keep their
+    /// votes, and the poll closes when it would have, or in five minutes if
+    /// that is sooner than Mastodon allows.
+    fn to_json(&self, id: &str) -> Result<Value> {
+        ensure!(
+            !self.expired,
+            "status {id} has a poll that has ended, and editing it would open the poll again"
+        );
+        let expires_at = self
+            .expires_at
+            .as_deref()
+            .with_context(|| format!("the poll of status {id} has no end"))?;
+        let expires_at = parse_timestamp(expires_at).map_err(anyhow::Error::msg)?;
+        let expires_in = (expires_at - OffsetDateTime::now_utc())
+            .whole_seconds()
+            .max(MIN_POLL_SECONDS);
+        let options: Vec<&str> = self
+            .options
+            .iter()
+            .map(|option| option.title.as_str())
+            .collect();
+        Ok(json!({
+            "options": options,
+            "expires_in": expires_in,
+            "multiple": self.multiple,
+            "hide_totals": self.options.iter().all(|option| option.votes_count.is_none()),
+        }))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Translation {
+    pub content: String,
+    #[serde(default)]
+    pub spoiler_text: String,
+    pub detected_source_language: String,
+    pub provider: String,
+}
+
+/// A deleted status, which includes the source text that it was posted with.
+#[derive(Debug, Deserialize)]
+struct DeletedStatus {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    spoiler_text: String,
+    #[serde(default)]
+    media_attachments: Vec<DeletedMedia>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeletedMedia {
+    id: String,
+    url: Option<String>,
+    description: Option<String>,
+}
+
+impl StatusArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            StatusCommand::Edit { .. } | StatusCommand::History { .. } => {
+                Some(Requirement::new("status edits", Version(3, 5, 0)))
+            }
+            StatusCommand::Translate { .. } => {
+                Some(Requirement::new("translation", Version(4, 0, 0)))
+            }
+            _ => None,
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            StatusCommand::Delete { .. } | StatusCommand::Edit { .. } => &["write:statuses"],
+            StatusCommand::Pin { .. } | StatusCommand::Unpin { .. } => &["write:accounts"],
+            StatusCommand::History { .. } => &["read:statuses"],
+            StatusCommand::Archive { .. }
+            | StatusCommand::Pinned { .. }
+            | StatusCommand::Translate { .. }
+            | StatusCommand::Thread { .. } => &["read:search", "read:statuses"],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: StatusArgs) -> Result<()> {
+    match args.command {
+        StatusCommand::Archive { status } => snapshot::archive(client, &status).await,
+        StatusCommand::Delete { id, redraft } => delete(client, &id, redraft).await,
+        StatusCommand::Edit { id, text, cw } => edit(client, &id, text, cw).await,
+        StatusCommand::History { id } => history(client, &id).await,
+        StatusCommand::Pin { id } => set_pinned(client, &id, true).await,
+        StatusCommand::Unpin { id } => set_pinned(client, &id, false).await,
+        StatusCommand::Pinned { acct } => pinned(client, &acct).await,
+        StatusCommand::Translate { status, to } => translate(client, &status, to).await,
+        StatusCommand::Thread { status, json } => thread::show(client, &status, json).await,
+    }
+}
+
+/// Runs the commands that work from the storage, for `--offline`.
+pub fn run_offline(storage: &Storage, args: StatusArgs) -> Result<()> {
+    match args.command {
+        StatusCommand::Thread { status, json } => thread::show_offline(storage, &status, json),
+        _ => bail!("only status thread works offline"),
+    }
+}
+
+/// Edits the text of a status. Everything else that the edit endpoint would
+/// otherwise reset is sent back unchanged, including the poll, which the
+/// endpoint removes when it is left out.
+#[instrument(skip(client), err)]
+async fn edit(client: &Client, id: &str, text: String, cw: Option<String>) -> Result<()> {
+    let current = client
+        .get_status(&StatusId::new(id))
+        .await
+        .with_context(|| format!("Couldn't get status {id}"))?;
+    let media_ids: Vec<String> = current
+        .media_attachments
+        .iter()
+        .map(|media| media.id.to_string())
+        .collect();
+    let mut body = json!({
+        "status": text,
+        "spoiler_text": cw.unwrap_or(current.spoiler_text),
+        "sensitive": current.sensitive,
+        "language": current.language,
+        "media_ids": media_ids,
+    });
+    if let Some(poll) = &current.poll {
+        let poll: EditedPoll = serde_json::to_value(poll)
+            .and_then(serde_json::from_value)
+            .with_context(|| format!("Couldn't read the poll of status {id}"))?;
+        body["poll"] = poll.to_json(id)?;
+    }
+    let status: Status = client
+        .put_json(&format!("v1/statuses/{id}"), &body)
+        .await
+        .with_context(|| format!("Couldn't edit status {id}"))?;
+    info!(id, "edited status");
+    println!("{}", status.url.as_deref().unwrap_or(&status.uri));
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn translate(client: &Client, id_or_url: &str, to: Option<String>) -> Result<()> {
+    ensure!(
+        instance::translation(client).await?.enabled,
+        "this instance doesn't have translation set up"
+    );
+    let status = resolve_status(client, id_or_url).await?;
+    let translation = translation(client, &status, to.as_deref()).await?;
+    println!(
+        "translated from {} by {}",
+        translation.detected_source_language, translation.provider
+    );
+    if !translation.spoiler_text.is_empty() {
+        println!("cw: {}", translation.spoiler_text);
+    }
+    println!("{}", render_html(&translation.content));
+    Ok(())
+}
+
+/// Translates a status to a language, or to the account's interface language.
+pub async fn translation(
+    client: &Client,
+    status: &Status,
+    to: Option<&str>,
+) -> Result<Translation> {
+    let form: Vec<_> = to.iter().map(|lang| ("lang", *lang)).collect();
+    client
+        .post(&format!("v1/statuses/{}/translate", status.id), &form)
+        .await
+        .with_context(|| format!("Couldn't translate status {}", status.id))
+}
+
+#[instrument(skip(client), err)]
+async fn history(client: &Client, id: &str) -> Result<()> {
+    let edits: Vec<StatusEdit> = client
+        .get(&format!("v1/statuses/{id}/history"), &())
+        .await
+        .with_context(|| format!("Couldn't get the history of status {id}"))?;
+    // the history is oldest first and includes the current revision
+    for edit in &edits {
+        println!("{}", format_time_text(&edit.created_at));
+        if !edit.spoiler_text.is_empty() {
+            println!("cw: {}", edit.spoiler_text);
+        }
+        println!("{}\n", render_html(&edit.content));
+    }
+    Ok(())
+}
+
+/// Pins or unpins a status. Requires the `write:accounts` scope.
+#[instrument(skip(client), err)]
+async fn set_pinned(client: &Client, id: &str, pin: bool) -> Result<()> {
+    let action = if pin { "pin" } else { "unpin" };
+    let status: Status = client
+        .post(&format!("v1/statuses/{id}/{action}"), &())
+        .await
+        .with_context(|| format!("Couldn't {action} status {id}"))?;
+    info!(id, action, "done");
+    println!("{}", status.url.as_deref().unwrap_or(&status.uri));
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn pinned(client: &Client, acct: &str) -> Result<()> {
+    let account = resolve_account(client, acct).await?;
+    let statuses: Vec<Status> = client
+        .get(
+            &format!("v1/accounts/{}/statuses", account.id),
+            &[("pinned", "true")],
+        )
+        .await
+        .with_context(|| format!("Couldn't get the pinned statuses of {acct}"))?;
+    for status in &statuses {
+        print_status(status);
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn delete(client: &Client, id: &str, redraft: bool) -> Result<()> {
+    let deleted: DeletedStatus = client
+        .delete(&format!("v1/statuses/{id}"))
+        .await
+        .with_context(|| format!("Couldn't delete status {id}"))?;
+    info!(id, "deleted status");
+    if redraft {
+        print_redraft(&deleted);
+    }
+    Ok(())
+}
+
+fn print_redraft(deleted: &DeletedStatus) {
+    println!("{}", deleted.text);
+    if !deleted.spoiler_text.is_empty() {
+        println!("cw: {}", deleted.spoiler_text);
+    }
+    for media in &deleted.media_attachments {
+        let url = media.url.as_deref().unwrap_or("(no url)");
+        match &media.description {
+            Some(description) => println!("media: {} {url} alt: {description}", media.id),
+            None => println!("media: {} {url}", media.id),
+        }
+    }
+}
@@ -0,0 +1,469 @@
+//! A local copy of every status and account that this tool has fetched, in an
+//! SQLite database in the data folder.
+//!
+//! Timelines, threads, searches and account statuses are saved as they are
+//! read, keyed by the instance and the id on that instance, so that a status
+//! seen twice is stored once with its latest counts. Each row keeps the JSON
+//! that the instance sent, so anything can be read back later without knowing
+//! which fields will be needed. Boosts are stored along with the statuses they
+//! boost, and accounts along with their statuses. `sync` also keeps how far it
+//! got through each timeline here, see [`crate::sync`].
+//!
+//! Statuses read from a timeline are also recorded as being in it, so that
+//! `--offline` can show the timeline again from here, see [`crate::offline`].
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use mastodon_async::prelude::{Account, Status};
+use rusqlite::{params, Connection, OptionalExtension, Params};
+use serde::de::DeserializeOwned;
+use time::OffsetDateTime;
+use tracing::{debug, instrument, warn};
+
+use crate::client::Client;
+use crate::config::data_folder;
+use crate::scrub;
+use crate::search;
+use crate::timestamp::format_timestamp;
+
+/// The changes to the schema, in order. The database records how many it has
+/// had in `user_version`, so only new ones are applied when it is opened.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE statuses (
+        instance TEXT NOT NULL,
+        id TEXT NOT NULL,
+        account_id TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        fetched_at TEXT NOT NULL,
+        json TEXT NOT NULL,
+        PRIMARY KEY (instance, id)
+    );
+    CREATE INDEX statuses_by_account ON statuses (instance, account_id, created_at);
+    CREATE INDEX statuses_by_time ON statuses (instance, created_at);
+    CREATE TABLE accounts (
+        instance TEXT NOT NULL,
+        id TEXT NOT NULL,
+        acct TEXT NOT NULL,
+        fetched_at TEXT NOT NULL,
+        json TEXT NOT NULL,
+        PRIMARY KEY (instance, id)
+    );
+    CREATE INDEX accounts_by_acct ON accounts (instance, acct);
+",
+    "
+    CREATE TABLE sync_positions (
+        instance TEXT NOT NULL,
+        timeline TEXT NOT NULL,
+        last_id TEXT NOT NULL,
+        synced_at TEXT NOT NULL,
+        PRIMARY KEY (instance, timeline)
+    );
+",
+    "
+    CREATE TABLE timeline_statuses (
+        instance TEXT NOT NULL,
+        timeline TEXT NOT NULL,
+        status_id TEXT NOT NULL,
+        PRIMARY KEY (instance, timeline, status_id)
+    );
+",
+];
+
+const INSERT_ACCOUNT: &str = "
+    INSERT OR REPLACE INTO accounts (instance, id, acct, fetched_at, json)
+    VALUES (?1, ?2, ?3, ?4, ?5)";
+
+/// The start of the queries for saved statuses, selecting the columns that
+/// [`Storage::query`] reads.
+const SAVED_STATUS: &str = "SELECT json, fetched_at FROM statuses";
+
+/// A status or account read back from the database, with when it was fetched.
+pub struct Saved<T> {
+    pub item: T,
+    pub fetched_at: String,
+}
+
+/// The database, for the statuses and accounts of one instance.
+pub struct Storage {
+    connection: Connection,
+    instance: String,
+}
+
+impl Storage {
+    /// Opens the database for the instance of the client, creating it or
+    /// updating its schema if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened or updated.
+    #[instrument(skip(client), err)]
+    pub fn open(client: &Client) -> Result<Self> {
+        let path = path()?;
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder).with_context(|| format!("cannot create {folder:?}"))?;
+        }
+        let mut connection = Connection::open(&path)
+            .with_context(|| format!("Couldn't open the database {path:?}"))?;
+        migrate(&mut connection).with_context(|| format!("Couldn't update {path:?}"))?;
+        Ok(Self {
+            connection,
+            instance: instance(&client.data.base),
+        })
+    }
+
+    /// Saves statuses, the statuses they boost and the accounts that posted
+    /// them, replacing any older copies. With `--scrub`, the scrubbed statuses
+    /// are saved instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the statuses can't be saved.
+    #[instrument(skip_all, fields(count = statuses.len()), err)]
+    pub fn save_statuses(&mut self, statuses: &[Status]) -> Result<()> {
+        let statuses = &*scrub::statuses(statuses);
+        let fetched_at = format_timestamp(OffsetDateTime::now_utc());
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert_status = transaction.prepare_cached(
+                "INSERT OR REPLACE INTO statuses
+                    (instance, id, account_id, created_at, fetched_at, json)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            let mut insert_account = transaction.prepare_cached(INSERT_ACCOUNT)?;
+            let boosted = statuses
+                .iter()
+                .filter_map(|status| status.reblog.as_deref());
+            for status in statuses.iter().chain(boosted) {
+                insert_status.execute(params![
+                    self.instance,
+                    status.id.to_string(),
+                    status.account.id.to_string(),
+                    format_timestamp(status.created_at),
+                    fetched_at,
+                    serde_json::to_string(status)?,
+                ])?;
+                insert_account.execute(params![
+                    self.instance,
+                    status.account.id.to_string(),
+                    status.account.acct,
+                    fetched_at,
+                    serde_json::to_string(&status.account)?,
+                ])?;
+            }
+        }
+        transaction.commit().context("Couldn't save statuses")?;
+        debug!("saved statuses");
+        Ok(())
+    }
+
+    /// Saves statuses like [`Storage::save_statuses`], and records that they
+    /// are in a timeline, e.g. `home` or `tag:rust`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the statuses can't be saved.
+    pub fn save_timeline(&mut self, timeline: &str, statuses: &[Status]) -> Result<()> {
+        self.save_statuses(statuses)?;
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT OR IGNORE INTO timeline_statuses (instance, timeline, status_id)
+                VALUES (?1, ?2, ?3)",
+            )?;
+            for status in statuses {
+                insert.execute(params![self.instance, timeline, status.id.to_string()])?;
+            }
+        }
+        transaction
+            .commit()
+            .with_context(|| format!("Couldn't save the {timeline} timeline"))
+    }
+
+    /// The statuses saved from a timeline, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be read.
+    pub fn timeline(&self, timeline: &str, limit: usize) -> Result<Vec<Saved<Status>>> {
+        self.query(
+            &format!(
+                "{SAVED_STATUS} JOIN timeline_statuses
+                    ON timeline_statuses.instance = statuses.instance
+                    AND timeline_statuses.status_id = statuses.id
+                WHERE statuses.instance = ?1 AND timeline = ?2
+                ORDER BY created_at DESC LIMIT ?3"
+            ),
+            params![self.instance, timeline, limit],
+        )
+    }
+
+    /// A saved status, by its id on this instance, its URI or its URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be read.
+    pub fn status(&self, id_or_url: &str) -> Result<Option<Saved<Status>>> {
+        let statuses = self.query(
+            &format!(
+                "{SAVED_STATUS} WHERE instance = ?1 AND (id = ?2
+                    OR json_extract(json, '$.uri') = ?2 OR json_extract(json, '$.url') = ?2)
+                LIMIT 1"
+            ),
+            params![self.instance, id_or_url],
+        )?;
+        Ok(statuses.into_iter().next())
+    }
+
+    /// The saved replies to a status, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be read.
+    pub fn replies(&self, id: &str) -> Result<Vec<Saved<Status>>> {
+        self.query(
+            &format!(
+                "{SAVED_STATUS} WHERE instance = ?1
+                    AND json_extract(json, '$.in_reply_to_id') = ?2
+                ORDER BY created_at"
+            ),
+            params![self.instance, id],
+        )
+    }
+
+    /// The saved statuses of an account, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be read.
+    pub fn account_statuses(&self, account_id: &str) -> Result<Vec<Saved<Status>>> {
+        self.query(
+            &format!(
+                "{SAVED_STATUS} WHERE instance = ?1 AND account_id = ?2
+                ORDER BY created_at DESC"
+            ),
+            params![self.instance, account_id],
+        )
+    }
+
+    /// A saved account, by its handle, e.g. `@someone@example.com`, or its
+    /// profile URL. Accounts on this instance are saved without the domain.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be read.
+    pub fn account(&self, acct_or_url: &str) -> Result<Option<Saved<Account>>> {
+        let acct = acct_or_url.trim_start_matches('@');
+        let local = acct
+            .strip_suffix(&format!("@{}", self.instance))
+            .unwrap_or(acct);
+        let accounts = self.query(
+            "SELECT json, fetched_at FROM accounts WHERE instance = ?1
+                AND (acct = ?2 OR acct = ?3 OR json_extract(json, '$.url') = ?4)
+            LIMIT 1",
+            params![self.instance, acct, local, acct_or_url],
+        )?;
+        Ok(accounts.into_iter().next())
+    }
+
+    /// Reads the `json` and `fetched_at` columns of the rows of a query.
+    fn query<T: DeserializeOwned>(&self, sql: &str, params: impl Params) -> Result<Vec<Saved<T>>> {
+        let mut statement = self.connection.prepare_cached(sql)?;
+        let rows = statement.query_map(params, |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut saved = Vec::new();
+        for row in rows {
+            let (json, fetched_at): (String, String) = row?;
+            saved.push(Saved {
+                item: serde_json::from_str(&json).context("Couldn't read a saved entity")?,
+                fetched_at,
+            });
+        }
+        Ok(saved)
+    }
+
+    /// Saves accounts, replacing any older copies, scrubbed with `--scrub`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the accounts can't be saved.
+    #[instrument(skip_all, fields(count = accounts.len()), err)]
+    pub fn save_accounts(&mut self, accounts: &[Account]) -> Result<()> {
+        let accounts = &*scrub::accounts(accounts);
+        let fetched_at = format_timestamp(OffsetDateTime::now_utc());
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(INSERT_ACCOUNT)?;
+            for account in accounts {
+                insert.execute(params![
+                    self.instance,
+                    account.id.to_string(),
+                    account.acct,
+                    fetched_at,
+                    serde_json::to_string(account)?,
+                ])?;
+            }
+        }
+        transaction.commit().context("Couldn't save accounts")?;
+        debug!("saved accounts");
+        Ok(())
+    }
+
+    /// The id of the newest status synced from a timeline, e.g. `home`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be read.
+    pub fn sync_position(&self, timeline: &str) -> Result<Option<String>> {
+        self.connection
+            .query_row(
+                "SELECT last_id FROM sync_positions WHERE instance = ?1 AND timeline = ?2",
+                params![self.instance, timeline],
+                |row| row.get(0),
+            )
+            .optional()
+            .with_context(|| format!("Couldn't read the sync position of {timeline}"))
+    }
+
+    /// Saves the id of the newest status synced from a timeline.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the position can't be saved.
+    pub fn set_sync_position(&self, timeline: &str, last_id: &str) -> Result<()> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO sync_positions (instance, timeline, last_id, synced_at)
+                VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    self.instance,
+                    timeline,
+                    last_id,
+                    format_timestamp(OffsetDateTime::now_utc())
+                ],
+            )
+            .with_context(|| format!("Couldn't save the sync position of {timeline}"))?;
+        Ok(())
+    }
+}
+
+/// Saves statuses that were fetched to the database and adds them to the local
+/// search index. Both are only a convenience, so failing to update them is
+/// logged rather than stopping the command.
+pub fn remember(client: &Client, statuses: &[Status]) {
+    if statuses.is_empty() {
+        return;
+    }
+    if let Err(err) = Storage::open(client).and_then(|mut storage| storage.save_statuses(statuses))
+    {
+        warn!(?err, "couldn't save statuses");
+    }
+    search::remember(client, &scrub::statuses(statuses));
+}
+
+/// Saves statuses that were read from a timeline like [`remember`], also
+/// recording that they are in it.
+pub fn remember_timeline(client: &Client, timeline: &str, statuses: &[Status]) {
+    if statuses.is_empty() {
+        return;
+    }
+    if let Err(err) =
+        Storage::open(client).and_then(|mut storage| storage.save_timeline(timeline, statuses))
+    {
+        warn!(?err, timeline, "couldn't save statuses");
+    }
+    search::remember(client, &scrub::statuses(statuses));
+}
+
+/// Saves accounts that were fetched to the database, logging any failure.
+pub fn remember_accounts(client: &Client, accounts: &[Account]) {
+    if accounts.is_empty() {
+        return;
+    }
+    if let Err(err) = Storage::open(client).and_then(|mut storage| storage.save_accounts(accounts))
+    {
+        warn!(?err, "couldn't save accounts");
+    }
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(data_folder()?.join("storage.sqlite3"))
+}
+
+/// The key of an instance in the database, its host, e.g. `mastodon.social`
+/// for `https://mastodon.social/`.
+pub fn instance(base: &str) -> String {
+    let base = base.trim_end_matches('/');
+    base.strip_prefix("https://")
+        .or_else(|| base.strip_prefix("http://"))
+        .unwrap_or(base)
+        .to_owned()
+}
+
+fn migrate(connection: &mut Connection) -> Result<()> {
+    let current: u32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let transaction = connection.transaction()?;
+    for (version, migration) in (1..)
+        .zip(MIGRATIONS)
+        .filter(|(version, _)| *version > current)
+    {
+        debug!(version, "updating the database schema");
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", version)?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_version(connection: &Connection) -> Result<usize> {
+        Ok(connection.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    fn tables(connection: &Connection) -> Result<Vec<String>> {
+        let mut statement = connection
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")?;
+        let names = statement.query_map([], |row| row.get(0))?;
+        Ok(names.collect::<rusqlite::Result<_>>()?)
+    }
+
+    #[test]
+    fn migrate_creates_every_table() -> Result<()> {
+        let mut connection = Connection::open_in_memory()?;
+        migrate(&mut connection)?;
+        assert_eq!(user_version(&connection)?, MIGRATIONS.len());
+        assert_eq!(
+            tables(&connection)?,
+            [
+                "accounts",
+                "statuses",
+                "sync_positions",
+                "timeline_statuses"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_only_applies_new_migrations() -> Result<()> {
+        let mut connection = Connection::open_in_memory()?;
+        connection.execute_batch(MIGRATIONS[0])?;
+        connection.pragma_update(None, "user_version", 1)?;
+        // applying the first migration again would fail, as its tables exist
+        migrate(&mut connection)?;
+        migrate(&mut connection)?;
+        assert_eq!(user_version(&connection)?, MIGRATIONS.len());
+        Ok(())
+    }
+
+    #[test]
+    fn instance_is_the_host() {
+        assert_eq!(instance("https://mastodon.social/"), "mastodon.social");
+        assert_eq!(instance("http://localhost:3000"), "localhost:3000");
+        assert_eq!(instance("example.com"), "example.com");
+    }
+}
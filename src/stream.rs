@@ -0,0 +1,240 @@
+//! Following the streaming API, which pushes new statuses and notifications as
+//! they happen.
+//!
+//! The stream is read as server-sent events over plain HTTP, and reconnected
+//! whenever it drops, waiting longer each time it keeps failing. It isn't
+//! reconnected when the instance rejects the login. With `--desktop-notify`, mentions, follows and direct
+//! messages also pop up as desktop notifications, which can be turned off by
+//! type in the config file:
+//!
+//! ```toml
+//! [desktop_notifications]
+//! follow = false
+//! ```
+//!
+//! Desktop notifications are only available when built with the
+//! `desktop-notify` feature.
+
+use std::time::Duration;
+
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::{Status, Visibility};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, warn};
+
+use crate::client::{error_status, Client};
+use crate::config::Config;
+use crate::instance;
+use crate::metrics;
+use crate::notifications::{Notification, NotificationRules};
+use crate::output::{print_notification, print_status};
+
+/// How long to wait before reconnecting a stream that dropped. This doubles
+/// each time reconnecting fails, up to [`MAX_RECONNECT_DELAY`].
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// The longest wait between reconnecting attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Args)]
+pub struct StreamArgs {
+    #[command(subcommand)]
+    command: StreamCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum StreamCommand {
+    /// Print your home timeline and notifications as they arrive
+    User {
+        /// Also show desktop notifications for mentions, follows and direct
+        /// messages
+        #[arg(long)]
+        desktop_notify: bool,
+    },
+}
+
+/// The `[desktop_notifications]` section of the config file. Every type is
+/// shown unless it is turned off.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DesktopNotifyConfig {
+    pub mention: bool,
+    pub follow: bool,
+    pub direct: bool,
+}
+
+impl Default for DesktopNotifyConfig {
+    fn default() -> Self {
+        Self {
+            mention: true,
+            follow: true,
+            direct: true,
+        }
+    }
+}
+
+pub async fn run(client: &Client, config: &Config, args: StreamArgs) -> Result<()> {
+    match args.command {
+        StreamCommand::User { desktop_notify } => {
+            let desktop = desktop_notify.then_some(&config.desktop_notifications);
+            user(client, desktop).await
+        }
+    }
+}
+
+#[instrument(skip_all, err)]
+async fn user(client: &Client, desktop: Option<&DesktopNotifyConfig>) -> Result<()> {
+    ensure!(
+        desktop.is_none() || cfg!(feature = "desktop-notify"),
+        "this was built without the desktop-notify feature"
+    );
+    let url = stream_url(client, "v1/streaming/user").await?;
+    let rules = NotificationRules::load()?;
+    let mut delay = RECONNECT_DELAY;
+    loop {
+        match follow_stream(client, &url, &rules, desktop).await {
+            Ok(()) => {
+                info!("stream ended, reconnecting");
+                delay = RECONNECT_DELAY;
+            }
+            Err(err) => {
+                // reconnecting won't help until the login is fixed
+                if let Some(status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)) =
+                    error_status(&err)
+                {
+                    return Err(err.context(format!("the instance rejected the login ({status})")));
+                }
+                warn!(?err, ?delay, "stream failed, reconnecting");
+            }
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        metrics::STREAM_RECONNECTS.inc();
+    }
+}
+
+/// The URL of a streaming endpoint on the streaming server, which may not be on
+/// the same host as the rest of the API.
+async fn stream_url(client: &Client, path: &str) -> Result<String> {
+    let base = match instance::streaming_api(client).await? {
+        Some(streaming_api) => streaming_api
+            .replacen("wss://", "https://", 1)
+            .replacen("ws://", "http://", 1),
+        None => client.data.base.to_string(),
+    };
+    Ok(format!("{}/api/{path}", base.trim_end_matches('/')))
+}
+
+/// Reads events from a stream until it ends.
+async fn follow_stream(
+    client: &Client,
+    url: &str,
+    rules: &NotificationRules,
+    desktop: Option<&DesktopNotifyConfig>,
+) -> Result<()> {
+    let mut response = client.open_stream(url).await?;
+    info!(url, "connected to stream");
+    // chunks can end partway through a character, so only decode whole events
+    let mut buffer = Vec::new();
+    while let Some(chunk) = response.chunk().await.context("stream failed")? {
+        buffer.extend_from_slice(&chunk);
+        // events are separated by a blank line
+        while let Some(end) = buffer.windows(2).position(|pair| pair == b"\n\n") {
+            let event: Vec<u8> = buffer.drain(..end + 2).collect();
+            if let Some((name, data)) = parse_event(&String::from_utf8_lossy(&event)) {
+                if let Err(err) = handle_event(&name, &data, rules, desktop) {
+                    warn!(?err, name, "couldn't handle event");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses the name and data of a server-sent event. Comments, which the server
+/// sends to keep the connection open, have neither.
+fn parse_event(event: &str) -> Option<(String, String)> {
+    let mut name = None;
+    let mut data = Vec::new();
+    for line in event.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            name = Some(value.trim().to_owned());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.trim_start());
+        }
+    }
+    Some((name?, data.join("\n")))
+}
+
+fn handle_event(
+    name: &str,
+    data: &str,
+    rules: &NotificationRules,
+    desktop: Option<&DesktopNotifyConfig>,
+) -> Result<()> {
+    match name {
+        "update" => {
+            let status: Status = serde_json::from_str(data)?;
+            print_status(&status);
+        }
+        "notification" => {
+            let notification: Notification = serde_json::from_str(data)?;
+            if rules.hides(&notification) {
+                debug!(id = %notification.id, "hiding notification matching the rules");
+                return Ok(());
+            }
+            print_notification(&notification);
+            if let Some(desktop) = desktop {
+                notify_desktop(desktop, &notification);
+            }
+        }
+        name => debug!(name, "ignoring event"),
+    }
+    Ok(())
+}
+
+/// Shows a desktop notification for the notification, if its type is turned
+/// on. Mentions in direct messages count as direct messages.
+fn notify_desktop(desktop: &DesktopNotifyConfig, notification: &Notification) {
+    let acct = &notification.account.acct;
+    let direct = notification
+        .status
+        .as_ref()
+        .map_or(false, |status| status.visibility == Visibility::Direct);
+    let summary = match notification.kind.as_str() {
+        "mention" if direct && desktop.direct => format!("Direct message from @{acct}"),
+        "mention" if !direct && desktop.mention => format!("@{acct} mentioned you"),
+        "follow" if desktop.follow => format!("@{acct} followed you"),
+        _ => return,
+    };
+    let body = notification
+        .status
+        .as_ref()
+        .map_or_else(String::new, |status| {
+            status.url.clone().unwrap_or_else(|| status.uri.clone())
+        });
+    // showing a notification can wait on the desktop's notification service
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = show_desktop_notification(&summary, &body) {
+            warn!(?err, "couldn't show desktop notification");
+        }
+    });
+}
+
+#[cfg(feature = "desktop-notify")]
+fn show_desktop_notification(summary: &str, body: &str) -> Result<()> {
+    notify_rust::Notification::new()
+        .appname("spike-mastodon")
+        .summary(summary)
+        .body(body)
+        .show()
+        .context("Couldn't show desktop notification")?;
+    Ok(())
+}
+
+#[cfg(not(feature = "desktop-notify"))]
+fn show_desktop_notification(_summary: &str, _body: &str) -> Result<()> {
+    anyhow::bail!("this was built without the desktop-notify feature")
+}
@@ -0,0 +1,67 @@
+//! Accounts that the instance suggests following.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Account;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, instrument};
+
+use crate::client::Client;
+use crate::output::print_account;
+use crate::resolve::resolve_account;
+
+#[derive(Debug, Args)]
+pub struct SuggestionsArgs {
+    #[command(subcommand)]
+    command: SuggestionsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum SuggestionsCommand {
+    /// List the suggested accounts, with why each one is suggested
+    List,
+    /// Stop suggesting an account
+    Dismiss {
+        /// The account, e.g. `@someone@example.com` or a profile URL
+        account: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Suggestion {
+    /// Why the account is suggested, e.g. `staff` or `past_interactions`.
+    source: String,
+    account: Account,
+}
+
+pub async fn run(client: &Client, args: SuggestionsArgs) -> Result<()> {
+    match args.command {
+        SuggestionsCommand::List => list(client).await,
+        SuggestionsCommand::Dismiss { account } => dismiss(client, &account).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn list(client: &Client) -> Result<()> {
+    let suggestions: Vec<Suggestion> = client
+        .get("v2/suggestions", &[("limit", client.page_limit())])
+        .await
+        .context("Couldn't get suggestions")?;
+    for suggestion in &suggestions {
+        print_account(&suggestion.account);
+        println!("  {}", suggestion.source.replace('_', " "));
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn dismiss(client: &Client, acct_or_url: &str) -> Result<()> {
+    let account = resolve_account(client, acct_or_url).await?;
+    let _: Value = client
+        .delete(&format!("v1/suggestions/{}", account.id))
+        .await
+        .with_context(|| format!("Couldn't dismiss the suggestion of {}", account.acct))?;
+    info!(acct = account.acct, "dismissed suggestion");
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Keeping a local mirror of a timeline in [`crate::storage`], e.g. from a
+//! cron job.
+//!
+//! Each run fetches only the statuses newer than the newest one stored by the
+//! last run, oldest page first, saving the position after every page so that
+//! an interrupted run loses nothing. The first run stores the latest page.
+//!
+//! With `--media`, the attachments are downloaded too, to `media/` in the data
+//! folder. Boosts are stored as the statuses they boost, and the files by their
+//! content, see [`crate::media_store`], so an image boosted by everyone you
+//! follow is only kept once.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Status;
+use tracing::{debug, info, instrument, warn};
+
+use crate::client::Client;
+use crate::config::data_folder;
+use crate::media_store::{Counts, MediaStore};
+use crate::scrub;
+use crate::search;
+use crate::storage::Storage;
+
+#[derive(Debug, Args)]
+pub struct SyncArgs {
+    #[command(subcommand)]
+    timeline: SyncTimeline,
+
+    /// Also download the media attached to the statuses
+    #[arg(long, global = true)]
+    media: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum SyncTimeline {
+    /// Store the statuses added to the home timeline since the last sync
+    Home,
+}
+
+pub async fn run(client: &Client, args: SyncArgs) -> Result<()> {
+    match args.timeline {
+        SyncTimeline::Home => sync(client, "home", "v1/timelines/home", args.media).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn sync(client: &Client, timeline: &str, path: &str, media: bool) -> Result<()> {
+    let mut storage = Storage::open(client)?;
+    let mut media = match (media, client.low_bandwidth()) {
+        (false, _) => None,
+        (true, true) => {
+            warn!("not downloading media with --low-bandwidth");
+            None
+        }
+        (true, false) => Some(MediaStore::open(&data_folder()?.join("media"))?),
+    };
+    let mut counts = Counts::default();
+    let mut min_id = storage.sync_position(timeline)?;
+    let mut count = 0;
+    loop {
+        let mut query = vec![("limit", client.page_limit())];
+        if let Some(min_id) = &min_id {
+            query.push(("min_id", min_id.as_str()));
+        }
+        let page: Vec<Status> = client
+            .get(path, &query)
+            .await
+            .with_context(|| format!("Couldn't get the {timeline} timeline"))?;
+        // pages are newest first
+        let Some(newest) = page.first() else {
+            break;
+        };
+        let newest = newest.id.to_string();
+        // scrubbed statuses have no media to download
+        let page = scrub::statuses(&page);
+        storage.save_timeline(timeline, &page)?;
+        search::remember(client, &page);
+        if let Some(media) = &mut media {
+            for status in page.iter() {
+                let status = status.reblog.as_deref().unwrap_or(status);
+                media.store_status(client, status, &mut counts).await?;
+            }
+            media.save()?;
+        }
+        storage.set_sync_position(timeline, &newest)?;
+        count += page.len();
+        debug!(count = page.len(), %newest, "synced page");
+        // without a position there is nothing to page forward from
+        if min_id.is_none() {
+            break;
+        }
+        min_id = Some(newest);
+    }
+    info!(
+        timeline,
+        count,
+        media.downloaded = counts.downloaded,
+        media.deduplicated = counts.deduplicated,
+        media.failed = counts.failed,
+        "synced"
+    );
+    Ok(())
+}
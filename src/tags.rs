@@ -0,0 +1,314 @@
+//! Hashtags, and following them so that their statuses show up in the home
+//! timeline.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Status;
+use serde::Deserialize;
+use time::OffsetDateTime;
+use tracing::{debug, info, instrument};
+
+use crate::client::Client;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct TagsArgs {
+    #[command(subcommand)]
+    command: TagsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum TagsCommand {
+    /// Follow a hashtag, e.g. `rust` or `#rust`
+    Follow {
+        /// The hashtag, with or without the `#`
+        name: String,
+    },
+    /// Stop following a hashtag
+    Unfollow {
+        /// The hashtag, with or without the `#`
+        name: String,
+    },
+    /// List the followed hashtags
+    Followed {
+        /// List every followed hashtag instead of the first page
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show how much a hashtag has been used each day of the last week
+    Show {
+        /// The hashtag, with or without the `#`
+        name: String,
+    },
+    /// Report on the recent statuses with a hashtag: statuses per day, the
+    /// accounts posting most and the hashtags used alongside it
+    Report(ReportArgs),
+}
+
+#[derive(Debug, Args)]
+struct ReportArgs {
+    /// The hashtag, with or without the `#`
+    name: String,
+
+    /// How many days to look back
+    #[arg(long, default_value_t = 30)]
+    days: u16,
+
+    /// Stop after this many statuses, for busy hashtags
+    #[arg(long, default_value_t = 1000)]
+    max_statuses: usize,
+}
+
+/// How many of the top posters and co-occurring hashtags to show.
+const TOP: usize = 10;
+
+/// A hashtag, as returned by searches and trends.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub url: String,
+    /// The daily use of the hashtag, newest day first. Only trends and the tag
+    /// endpoint include it.
+    #[serde(default)]
+    pub history: Vec<History>,
+    /// Whether you follow the hashtag, when logged in.
+    pub following: Option<bool>,
+}
+
+/// The use of a hashtag or link on one day. The counts are strings in the API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct History {
+    /// The start of the day, as a unix timestamp.
+    pub day: String,
+    pub uses: String,
+    pub accounts: String,
+}
+
+impl History {
+    pub fn uses(&self) -> u64 {
+        self.uses.parse().unwrap_or_default()
+    }
+
+    pub fn accounts(&self) -> u64 {
+        self.accounts.parse().unwrap_or_default()
+    }
+
+    /// The day as a date, e.g. `2024-06-01`.
+    pub fn date(&self) -> Option<time::Date> {
+        let day = self.day.parse().ok()?;
+        OffsetDateTime::from_unix_timestamp(day)
+            .ok()
+            .map(OffsetDateTime::date)
+    }
+}
+
+impl TagsArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            TagsCommand::Follow { .. }
+            | TagsCommand::Unfollow { .. }
+            | TagsCommand::Followed { .. } => {
+                Some(Requirement::new("followed hashtags", Version(4, 0, 0)))
+            }
+            TagsCommand::Show { .. } | TagsCommand::Report(_) => None,
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match self.command {
+            TagsCommand::Follow { .. } | TagsCommand::Unfollow { .. } => &["write:follows"],
+            TagsCommand::Followed { .. } => &["read:follows"],
+            // hashtags are public
+            TagsCommand::Show { .. } | TagsCommand::Report(_) => &[],
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: TagsArgs) -> Result<()> {
+    match args.command {
+        TagsCommand::Follow { name } => follow(client, &name, true).await,
+        TagsCommand::Unfollow { name } => follow(client, &name, false).await,
+        TagsCommand::Followed { all } => followed(client, all).await,
+        TagsCommand::Show { name } => show(client, &name).await,
+        TagsCommand::Report(args) => report(client, args).await,
+    }
+}
+
+/// The name of a hashtag without the `#`, which isn't part of the name in the
+/// API.
+pub fn tag_name(name: &str) -> &str {
+    name.trim().trim_start_matches('#')
+}
+
+#[instrument(skip(client), err)]
+async fn follow(client: &Client, name: &str, following: bool) -> Result<()> {
+    let name = tag_name(name);
+    let action = if following { "follow" } else { "unfollow" };
+    let tag: Tag = client
+        .post(&format!("v1/tags/{name}/{action}"), &())
+        .await
+        .with_context(|| format!("Couldn't {action} #{name}"))?;
+    info!(name = tag.name, following, "updated followed hashtag");
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn followed(client: &Client, all: bool) -> Result<()> {
+    let (mut tags, mut next) = client
+        .get_page::<Tag>("v1/followed_tags", &[("limit", client.page_limit())])
+        .await
+        .context("Couldn't get followed hashtags")?;
+    while let Some(url) = next.filter(|_| all) {
+        let (page, page_next) = client
+            .get_next_page::<Tag>(&url)
+            .await
+            .context("Couldn't get followed hashtags")?;
+        debug!(count = page.len(), "loaded next page");
+        tags.extend(page);
+        next = page_next;
+    }
+    for tag in &tags {
+        println!("#{} {}", tag.name, tag.url);
+    }
+    Ok(())
+}
+
+/// Prints the uses and accounts of each day, newest first, followed by the
+/// total uses.
+#[instrument(skip(client), err)]
+async fn show(client: &Client, name: &str) -> Result<()> {
+    let tag = get_tag(client, name).await?;
+    println!("#{} {}", tag.name, tag.url);
+    if let Some(following) = tag.following {
+        println!("following: {following}");
+    }
+    println!("{:10}  {:>5}  {:>8}", "day", "uses", "accounts");
+    for day in &tag.history {
+        let date = day
+            .date()
+            .map_or_else(|| day.day.clone(), |date| date.to_string());
+        println!("{date:10}  {:>5}  {:>8}", day.uses(), day.accounts());
+    }
+    let uses: u64 = tag.history.iter().map(History::uses).sum();
+    println!("{uses} uses in {} days", tag.history.len());
+    Ok(())
+}
+
+/// Gets a hashtag with its history.
+pub async fn get_tag(client: &Client, name: &str) -> Result<Tag> {
+    let name = tag_name(name);
+    client
+        .get(&format!("v1/tags/{name}"), &())
+        .await
+        .with_context(|| format!("Couldn't get #{name}"))
+}
+
+#[instrument(skip(client), err)]
+async fn report(client: &Client, args: ReportArgs) -> Result<()> {
+    let tag = get_tag(client, &args.name).await?;
+    let since = OffsetDateTime::now_utc() - time::Duration::days(args.days.into());
+    let statuses = recent_statuses(client, &tag.name, since, args.max_statuses).await?;
+
+    println!("#{} over the last {} days", tag.name, args.days);
+    println!("{} statuses", statuses.len());
+    // the instance only keeps the history of the last week
+    let uses: u64 = tag.history.iter().map(History::uses).sum();
+    println!(
+        "{uses} uses across the instance in the last {} days",
+        tag.history.len()
+    );
+    if let Some(oldest) = statuses
+        .last()
+        .filter(|_| statuses.len() >= args.max_statuses)
+    {
+        println!(
+            "stopped at {} statuses, the oldest from {}",
+            args.max_statuses,
+            oldest.created_at.date()
+        );
+    }
+
+    println!("\nstatuses per day:");
+    let mut per_day = BTreeMap::new();
+    for status in &statuses {
+        *per_day.entry(status.created_at.date()).or_insert(0_usize) += 1;
+    }
+    for (date, count) in per_day.iter().rev() {
+        println!("  {date}  {count:>5}");
+    }
+
+    println!("\ntop posters:");
+    let posters = statuses
+        .iter()
+        .map(|status| status.account.acct.to_lowercase());
+    for (acct, count) in top(posters) {
+        println!("  {count:>5}  @{acct}");
+    }
+
+    println!("\nused with:");
+    let co_occurring = top(statuses.iter().flat_map(|status| {
+        status
+            .tags
+            .iter()
+            .map(|other| other.name.to_lowercase())
+            .filter(|other| !other.eq_ignore_ascii_case(&tag.name))
+    }));
+    for (name, count) in co_occurring {
+        println!("  {count:>5}  #{name}");
+    }
+    Ok(())
+}
+
+/// Gets the statuses with the hashtag posted since the given time, newest
+/// first, paging with `max_id` until they are older or there are `max` of them.
+async fn recent_statuses(
+    client: &Client,
+    name: &str,
+    since: OffsetDateTime,
+    max: usize,
+) -> Result<Vec<Status>> {
+    let path = format!("v1/timelines/tag/{name}");
+    let mut statuses = Vec::new();
+    let mut max_id: Option<String> = None;
+    loop {
+        let mut query = vec![("limit", client.page_limit())];
+        if let Some(max_id) = &max_id {
+            query.push(("max_id", max_id.as_str()));
+        }
+        let page: Vec<Status> = client
+            .get(&path, &query)
+            .await
+            .with_context(|| format!("Couldn't get statuses with #{name}"))?;
+        let Some(oldest) = page.last() else {
+            break;
+        };
+        max_id = Some(oldest.id.to_string());
+        let done = oldest.created_at < since;
+        debug!(count = page.len(), ?max_id, "loaded older page");
+        statuses.extend(page.into_iter().filter(|status| status.created_at >= since));
+        if statuses.len() >= max {
+            statuses.truncate(max);
+            break;
+        }
+        if done {
+            break;
+        }
+    }
+    Ok(statuses)
+}
+
+/// The most common values with their counts, most common first.
+fn top(values: impl Iterator<Item = String>) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(a_value, a_count), (b_value, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_value.cmp(b_value))
+    });
+    counts.truncate(TOP);
+    counts
+}
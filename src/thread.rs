@@ -0,0 +1,434 @@
+//! Showing a status in the context of the conversation it is part of.
+//!
+//! The context endpoint leaves out statuses that the instance can't show, for
+//! example when a reply's parent was deleted or is from a blocked account. The
+//! parents that are missing are fetched together in a single request, rather
+//! than one request each, so a deep thread still costs three requests at most:
+//! the status, its context and the missing parents.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use mastodon_async::prelude::Status;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, instrument, warn};
+
+use crate::client::Client;
+use crate::offline;
+use crate::resolve::resolve_status;
+use crate::storage::{self, Storage};
+
+/// The statuses before and after a status in its thread.
+#[derive(Debug, Deserialize)]
+pub struct ThreadContext {
+    pub ancestors: Vec<Status>,
+    pub descendants: Vec<Status>,
+}
+
+/// The statuses of a thread arranged as a tree of replies.
+struct Thread {
+    statuses: Vec<Status>,
+    /// The index of the status that the thread was requested for.
+    focus: usize,
+    /// The indexes of the statuses that aren't a reply to anything in the
+    /// thread, usually just the first status.
+    roots: Vec<usize>,
+    /// The indexes of the replies to each status, by status id.
+    replies: HashMap<String, Vec<usize>>,
+}
+
+impl Thread {
+    fn new(statuses: Vec<Status>, focus: usize) -> Self {
+        let ids: HashSet<String> = statuses
+            .iter()
+            .map(|status| status.id.to_string())
+            .collect();
+        let mut roots = Vec::new();
+        let mut replies: HashMap<String, Vec<usize>> = HashMap::new();
+        // the context is in thread order, so the replies stay in that order
+        for (index, status) in statuses.iter().enumerate() {
+            match status.in_reply_to_id.as_ref().map(ToString::to_string) {
+                Some(parent) if ids.contains(&parent) => {
+                    replies.entry(parent).or_default().push(index);
+                }
+                _ => roots.push(index),
+            }
+        }
+        Self {
+            statuses,
+            focus,
+            roots,
+            replies,
+        }
+    }
+
+    fn replies(&self, index: usize) -> &[usize] {
+        self.replies
+            .get(&self.statuses[index].id.to_string())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Prints each status indented under the status it replies to, marking the
+    /// requested status with `*`.
+    fn print(&self) {
+        for &root in &self.roots {
+            self.print_node(root, 0);
+        }
+    }
+
+    fn print_node(&self, index: usize, depth: usize) {
+        let status = &self.statuses[index];
+        let marker = if index == self.focus { '*' } else { ' ' };
+        let indent = "  ".repeat(depth);
+        println!("{marker} {indent}@{} {}", status.account.acct, status.uri);
+        for &reply in self.replies(index) {
+            self.print_node(reply, depth + 1);
+        }
+    }
+
+    /// The thread as nested `{"status": ..., "replies": [...]}` objects.
+    fn to_json(&self) -> Result<Value> {
+        let roots = self
+            .roots
+            .iter()
+            .map(|&root| self.node_json(root))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Value::Array(roots))
+    }
+
+    fn node_json(&self, index: usize) -> Result<Value> {
+        let replies = self
+            .replies(index)
+            .iter()
+            .map(|&reply| self.node_json(reply))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(json!({
+            "status": serde_json::to_value(&self.statuses[index])?,
+            "replies": replies,
+        }))
+    }
+}
+
+/// Prints the whole thread that a status is part of.
+#[instrument(skip(client), err)]
+pub async fn show(client: &Client, id_or_url: &str, as_json: bool) -> Result<()> {
+    print(&load(client, id_or_url).await?, as_json)
+}
+
+/// Fetches the statuses before and after a status in its thread, as far as
+/// the instance can show them.
+pub async fn context(client: &Client, status: &Status) -> Result<ThreadContext> {
+    client
+        .get(&format!("v1/statuses/{}/context", status.id), &())
+        .await
+        .with_context(|| format!("Couldn't get the thread of status {}", status.id))
+}
+
+/// Fetches the thread that a status is part of, including the parents that
+/// the context leaves out.
+async fn load(client: &Client, id_or_url: &str) -> Result<Thread> {
+    let status = resolve_status(client, id_or_url).await?;
+    let context = context(client, &status).await?;
+    debug!(
+        ancestors = context.ancestors.len(),
+        descendants = context.descendants.len(),
+        "loaded thread"
+    );
+    let mut focus = context.ancestors.len();
+    let mut statuses = context.ancestors;
+    statuses.push(status);
+    statuses.extend(context.descendants);
+
+    let hydrated = hydrate(client, &missing_parents(&statuses)).await;
+    // the missing parents aren't replies to anything in the thread, so they
+    // go first to be shown as roots
+    focus += hydrated.len();
+    statuses.splice(0..0, hydrated);
+    storage::remember(client, &statuses);
+    Ok(Thread::new(statuses, focus))
+}
+
+/// Prints the thread of a status from the saved statuses, for `--offline`.
+/// Parents are followed up as far as they were saved, and replies down.
+#[instrument(skip(storage), err)]
+pub fn show_offline(storage: &Storage, id_or_url: &str, as_json: bool) -> Result<()> {
+    let status = storage
+        .status(id_or_url)?
+        .with_context(|| format!("status {id_or_url} isn't saved"))?;
+    let mut ancestors = Vec::new();
+    let mut parent = status.item.in_reply_to_id.as_ref().map(ToString::to_string);
+    while let Some(id) = parent {
+        let Some(saved) = storage.status(&id)? else {
+            break;
+        };
+        parent = saved.item.in_reply_to_id.as_ref().map(ToString::to_string);
+        ancestors.push(saved);
+    }
+    ancestors.reverse();
+    let focus = ancestors.len();
+    let mut saved = ancestors;
+    saved.push(status);
+    // the thread is arranged by parent, so the replies only need to come
+    // after the status they reply to
+    let mut next = focus;
+    while next < saved.len() {
+        let id = saved[next].item.id.to_string();
+        saved.extend(storage.replies(&id)?);
+        next += 1;
+    }
+    offline::print_freshness("statuses", &saved);
+    let statuses = saved.into_iter().map(|saved| saved.item).collect();
+    print(&Thread::new(statuses, focus), as_json)
+}
+
+fn print(thread: &Thread, as_json: bool) -> Result<()> {
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&thread.to_json()?)?);
+    } else {
+        thread.print();
+    }
+    Ok(())
+}
+
+/// The ids of the statuses that are replied to in the thread but aren't in it.
+fn missing_parents(statuses: &[Status]) -> Vec<String> {
+    let ids: HashSet<String> = statuses
+        .iter()
+        .map(|status| status.id.to_string())
+        .collect();
+    let mut missing: Vec<String> = statuses
+        .iter()
+        .filter_map(|status| status.in_reply_to_id.as_ref().map(ToString::to_string))
+        .filter(|parent| !ids.contains(parent))
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+    missing
+}
+
+/// Fetches the statuses with the given ids in one request. Statuses that the
+/// instance still can't show are left out, and instances older than Mastodon
+/// 4.3 don't support this at all, so failures only disable the hydration.
+async fn hydrate(client: &Client, ids: &[String]) -> Vec<Status> {
+    if ids.is_empty() {
+        return Vec::new();
+    }
+    match client
+        .get::<Vec<Status>>("v1/statuses", &hydrate_query(ids))
+        .await
+    {
+        Ok(statuses) => {
+            debug!(
+                missing = ids.len(),
+                found = statuses.len(),
+                "hydrated missing parents"
+            );
+            statuses
+        }
+        Err(err) => {
+            warn!(?err, "couldn't get the missing parents of the thread");
+            Vec::new()
+        }
+    }
+}
+
+/// The query of the one request for the statuses with the given ids.
+fn hydrate_query(ids: &[String]) -> Vec<(&str, &str)> {
+    ids.iter().map(|id| ("id[]", id.as_str())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use mastodon_async::Data;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::SpikeMastodonBuilder;
+
+    /// A status as the instance sends it, replying to `parent` if given.
+    fn status_json(id: &str, parent: Option<&str>) -> Value {
+        let account = json!({
+            "id": "1",
+            "username": "alice",
+            "acct": "alice",
+            "display_name": "Alice",
+            "locked": false,
+            "bot": false,
+            "discoverable": true,
+            "group": false,
+            "created_at": "2024-01-01T00:00:00.000Z",
+            "note": "",
+            "url": "https://example.com/@alice",
+            "avatar": "https://example.com/avatar.png",
+            "avatar_static": "https://example.com/avatar.png",
+            "header": "https://example.com/header.png",
+            "header_static": "https://example.com/header.png",
+            "followers_count": 0,
+            "following_count": 0,
+            "statuses_count": 0,
+            "last_status_at": null,
+            "emojis": [],
+            "fields": [],
+        });
+        json!({
+            "id": id,
+            "uri": format!("https://example.com/users/alice/statuses/{id}"),
+            "url": format!("https://example.com/@alice/{id}"),
+            "account": account,
+            "in_reply_to_id": parent,
+            "in_reply_to_account_id": parent.map(|_| "1"),
+            "reblog": null,
+            "content": "<p>hello</p>",
+            "created_at": "2024-06-01T12:00:00.000Z",
+            "edited_at": null,
+            "emojis": [],
+            "replies_count": 0,
+            "reblogs_count": 0,
+            "favourites_count": 0,
+            "reblogged": false,
+            "favourited": false,
+            "bookmarked": false,
+            "muted": false,
+            "pinned": false,
+            "sensitive": false,
+            "spoiler_text": "",
+            "visibility": "public",
+            "media_attachments": [],
+            "mentions": [],
+            "tags": [],
+            "card": null,
+            "application": null,
+            "language": "en",
+            "poll": null,
+            "filtered": [],
+        })
+    }
+
+    fn status(id: &str, parent: Option<&str>) -> serde_json::Result<Status> {
+        serde_json::from_value(status_json(id, parent))
+    }
+
+    /// A client for an instance at the mock server.
+    fn client(server: &MockServer) -> Result<Client> {
+        SpikeMastodonBuilder::new()
+            .credentials(Data {
+                base: server.uri().into(),
+                token: "token".into(),
+                ..Data::default()
+            })
+            .cache_dir(std::env::temp_dir())
+            .build()
+    }
+
+    /// Serves status 3, whose context only has its parent 2 because the
+    /// grandparent 1 is missing, and 1 when it is asked for by id.
+    async fn thread_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/statuses/3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(status_json("3", Some("2"))))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/statuses/3/context"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "ancestors": [status_json("2", Some("1"))],
+                "descendants": [],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/statuses"))
+            .and(query_param("id[]", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([status_json("1", None)])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[test]
+    fn replies_follow_their_parent_in_thread_order() -> Result<()> {
+        // 1 has the replies 2 and 3, and 4 replies to 2
+        let thread = Thread::new(
+            vec![
+                status("1", None)?,
+                status("2", Some("1"))?,
+                status("3", Some("1"))?,
+                status("4", Some("2"))?,
+            ],
+            1,
+        );
+        assert_eq!(thread.roots, [0]);
+        assert_eq!(thread.replies(0), [1, 2]);
+        assert_eq!(thread.replies(1), [3]);
+        assert!(thread.replies(2).is_empty());
+        assert!(thread.replies(3).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn replies_to_statuses_outside_the_thread_are_roots() -> Result<()> {
+        let thread = Thread::new(vec![status("2", Some("1"))?, status("3", Some("2"))?], 1);
+        assert_eq!(thread.roots, [0]);
+        assert_eq!(thread.replies(0), [1]);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_parents_are_the_parents_outside_the_thread() -> Result<()> {
+        let statuses = [
+            status("2", Some("1"))?,
+            status("3", Some("2"))?,
+            status("5", Some("4"))?,
+        ];
+        assert_eq!(missing_parents(&statuses), ["1", "4"]);
+        Ok(())
+    }
+
+    #[test]
+    fn complete_threads_have_no_missing_parents() -> Result<()> {
+        let statuses = [status("1", None)?, status("2", Some("1"))?];
+        assert!(missing_parents(&statuses).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_parents_are_fetched_in_one_request() -> Result<()> {
+        // 3 and 4 reply to the same missing status, which is only asked for once
+        let statuses = [
+            status("3", Some("1"))?,
+            status("4", Some("1"))?,
+            status("5", Some("2"))?,
+        ];
+        let missing = missing_parents(&statuses);
+        assert_eq!(missing, ["1", "2"]);
+        assert_eq!(hydrate_query(&missing), [("id[]", "1"), ("id[]", "2")]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hydrated_parents_are_shown_first() -> Result<()> {
+        let server = thread_server().await;
+        let thread = load(&client(&server)?, "3").await?;
+        assert_eq!(thread.statuses[thread.focus].id.to_string(), "3");
+        assert_eq!(thread.roots, [0]);
+        assert_eq!(thread.statuses[0].id.to_string(), "1");
+        assert_eq!(thread.replies(0), [1]);
+        assert_eq!(thread.replies(1), [2]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn a_thread_with_missing_parents_takes_three_requests() -> Result<()> {
+        let server = thread_server().await;
+        load(&client(&server)?, "3").await?;
+        let requests = server.received_requests().await.unwrap_or_default();
+        assert_eq!(requests.len(), 3);
+        Ok(())
+    }
+}
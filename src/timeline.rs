@@ -0,0 +1,375 @@
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand};
+use isolang::Language;
+use mastodon_async::page::Page;
+use mastodon_async::prelude::Status;
+use mastodon_async::Mastodon;
+use tracing::{debug, info, instrument, warn};
+
+use crate::client::Client;
+use crate::config::Config;
+use crate::dedup::SeenStatuses;
+use crate::domain::DomainLimits;
+use crate::instance;
+use crate::markers;
+use crate::offline;
+use crate::output::{self, render_html};
+use crate::plugin::Plugins;
+use crate::post::parse_language;
+use crate::preview::Previews;
+use crate::scrub;
+use crate::status;
+use crate::storage::{self, Storage};
+use crate::tags;
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct TimelineArgs {
+    #[command(subcommand)]
+    timeline: Timeline,
+
+    /// Show the images attached to statuses in terminals that can draw them
+    /// (kitty, iTerm2, WezTerm and sixel terminals), or a blurred placeholder
+    /// in other terminals and with `--low-bandwidth`
+    #[arg(long, global = true)]
+    preview_images: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Timeline {
+    /// Show the home timeline
+    Home(HomeArgs),
+    /// Show the latest statuses with a hashtag
+    Tag {
+        /// The hashtag, with or without the `#`
+        name: String,
+        /// Only statuses from this instance
+        #[arg(long)]
+        local: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+struct HomeArgs {
+    /// Show everything since the last read position saved on the server, then
+    /// advance it
+    #[arg(long)]
+    since_last_read: bool,
+
+    /// Only show statuses in a language, e.g. `de`, each followed by its
+    /// translation, for practicing the language
+    #[arg(
+        long,
+        value_name = "CODE",
+        value_parser = parse_language,
+        conflicts_with = "since_last_read"
+    )]
+    learn: Option<Language>,
+}
+
+impl TimelineArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match &self.timeline {
+            Timeline::Home(args) if args.learn.is_some() => {
+                Some(Requirement::new("translation", Version(4, 0, 0)))
+            }
+            Timeline::Home(args) if args.since_last_read => {
+                Some(Requirement::new("markers", Version(3, 0, 0)))
+            }
+            Timeline::Home(_) | Timeline::Tag { .. } => None,
+        }
+    }
+
+    pub const fn scopes(&self) -> &'static [&'static str] {
+        match &self.timeline {
+            // saving the read position
+            Timeline::Home(args) if args.since_last_read => &["read:statuses", "write:statuses"],
+            Timeline::Home(_) | Timeline::Tag { .. } => &["read:statuses"],
+        }
+    }
+}
+
+pub async fn run(client: &Client, config: &Config, args: TimelineArgs) -> Result<()> {
+    let mut plugins = Plugins::load(&config.plugins)?;
+    let limits = DomainLimits::load()?;
+    let previews = Previews::new(args.preview_images, client.low_bandwidth());
+    match args.timeline {
+        Timeline::Home(args) => home(client, &mut plugins, &limits, &previews, args).await,
+        Timeline::Tag { name, local } => {
+            tag(client, &mut plugins, &limits, &previews, &name, local).await
+        }
+    }
+}
+
+/// Shows the statuses saved the last time a timeline was read, for
+/// `--offline`. Previews and plugins are left out, but domain limits still
+/// apply.
+pub fn run_offline(storage: &Storage, args: TimelineArgs) -> Result<()> {
+    let saved = match args.timeline {
+        Timeline::Home(args) => {
+            ensure!(
+                !args.since_last_read && args.learn.is_none(),
+                "--since-last-read and --learn need the instance"
+            );
+            storage.timeline("home", offline::PAGE)?
+        }
+        Timeline::Tag { name, local } => {
+            let name = tags::tag_name(&name);
+            let mut saved = storage.timeline(&format!("tag:{name}"), offline::PAGE)?;
+            if local {
+                // local accounts don't have a domain in their acct
+                saved.retain(|saved| !saved.item.account.acct.contains('@'));
+            }
+            saved
+        }
+    };
+    offline::print_freshness("statuses", &saved);
+    let statuses = saved.into_iter().map(|saved| saved.item).collect();
+    for status in &DomainLimits::load()?.retain(statuses) {
+        output::print_status(status);
+    }
+    Ok(())
+}
+
+/// The web interface of the instance, for naming feeds.
+fn web_url(client: &Client, path: &str) -> String {
+    format!("{}/{path}", client.data.base.trim_end_matches('/'))
+}
+
+#[instrument(skip(client, plugins, limits, previews), err)]
+async fn tag(
+    client: &Client,
+    plugins: &mut Plugins,
+    limits: &DomainLimits,
+    previews: &Previews,
+    name: &str,
+    local: bool,
+) -> Result<()> {
+    let name = tags::tag_name(name);
+    output::set_feed(format!("#{name}"), web_url(client, &format!("tags/{name}")));
+    let mut query = vec![("limit", client.page_limit())];
+    if local {
+        query.push(("local", "true"));
+    }
+    let statuses: Vec<Status> = client
+        .get(&format!("v1/timelines/tag/{name}"), &query)
+        .await
+        .with_context(|| format!("Couldn't get statuses with #{name}"))?;
+    storage::remember_timeline(client, &format!("tag:{name}"), &statuses);
+    for status in &plugins.apply(limits.retain(statuses))? {
+        previews.print_status(client, status).await;
+    }
+    Ok(())
+}
+
+#[instrument(name = "home", skip(client, plugins, limits, previews), err)]
+async fn home(
+    client: &Client,
+    plugins: &mut Plugins,
+    limits: &DomainLimits,
+    previews: &Previews,
+    args: HomeArgs,
+) -> Result<()> {
+    output::set_feed("Home timeline".to_owned(), web_url(client, "home"));
+    if args.since_last_read {
+        return catch_up(client, plugins, limits, previews).await;
+    }
+    let statuses: Vec<Status> = client
+        .get("v1/timelines/home", &[("limit", client.page_limit())])
+        .await
+        .context("Couldn't get timeline")?;
+    storage::remember_timeline(client, "home", &statuses);
+    let statuses = plugins.apply(limits.retain(statuses))?;
+    if let Some(language) = args.learn {
+        return learn(client, previews, &statuses, language).await;
+    }
+    for status in &statuses {
+        previews.print_status(client, status).await;
+    }
+    Ok(())
+}
+
+/// Prints the statuses in a language, each followed by its translation to the
+/// account's interface language, and hides the rest.
+#[instrument(skip(client, previews, statuses), err)]
+async fn learn(
+    client: &Client,
+    previews: &Previews,
+    statuses: &[Status],
+    language: Language,
+) -> Result<()> {
+    ensure!(
+        instance::translation(client).await?.enabled,
+        "this instance doesn't have translation set up"
+    );
+    for status in statuses {
+        // boosts don't have a language of their own
+        let original = status.reblog.as_deref().unwrap_or(status);
+        if original.language != Some(language) {
+            continue;
+        }
+        previews.print_status(client, status).await;
+        match status::translation(client, original, None).await {
+            Ok(translation) => println!("{}\n", render_html(&translation.content)),
+            Err(err) => warn!(?err, id = %original.id, "couldn't translate status"),
+        }
+    }
+    Ok(())
+}
+
+/// Prints every status newer than the home marker, oldest first, and then moves
+/// the marker to the newest status printed.
+#[instrument(skip_all, err)]
+async fn catch_up(
+    client: &Client,
+    plugins: &mut Plugins,
+    limits: &DomainLimits,
+    previews: &Previews,
+) -> Result<()> {
+    let marker = markers::get(client, markers::HOME).await?;
+    let Some(marker) = marker else {
+        warn!("no home marker saved yet, showing the latest page instead");
+        let statuses: Vec<Status> = client
+            .get("v1/timelines/home", &[("limit", client.page_limit())])
+            .await
+            .context("Couldn't get timeline")?;
+        let statuses = statuses.into_iter().rev().collect();
+        return print_and_mark_read(client, plugins, limits, previews, statuses).await;
+    };
+
+    // `min_id` returns the page immediately newer than the given id, so keep
+    // moving it forward to the newest status of the previous page until there
+    // is nothing left
+    let mut seen = SeenStatuses::new();
+    let mut statuses = Vec::new();
+    let mut min_id = marker.last_read_id;
+    loop {
+        let page: Vec<Status> = client
+            .get(
+                "v1/timelines/home",
+                &[("min_id", min_id.as_str()), ("limit", client.page_limit())],
+            )
+            .await
+            .context("Couldn't get timeline")?;
+        let Some(newest) = page.first() else {
+            break;
+        };
+        min_id = newest.id.to_string();
+        debug!(count = page.len(), %min_id, "loaded newer page");
+        statuses.extend(seen.retain_unseen(page).into_iter().rev());
+    }
+    print_and_mark_read(client, plugins, limits, previews, statuses).await
+}
+
+/// Prints statuses that are in oldest first order, and saves the last one as
+/// the read position of the home timeline.
+async fn print_and_mark_read(
+    client: &Client,
+    plugins: &mut Plugins,
+    limits: &DomainLimits,
+    previews: &Previews,
+    statuses: Vec<Status>,
+) -> Result<()> {
+    info!(count = statuses.len(), "statuses since last read");
+    storage::remember_timeline(client, "home", &statuses);
+    // statuses hidden by limits or plugins still count as read
+    let newest = statuses.last().map(|status| status.id.to_string());
+    for status in &plugins.apply(limits.retain(statuses))? {
+        previews.print_status(client, status).await;
+    }
+    if let Some(newest) = newest {
+        markers::set(client, markers::HOME, &newest).await?;
+    }
+    Ok(())
+}
+
+#[instrument(name = "home", skip_all, err)]
+pub async fn show_timeline(client: &Mastodon) -> Result<()> {
+    // statuses can show up on both sides of a page boundary, so only log each
+    // one the first time we see it
+    let mut seen = SeenStatuses::new();
+    let mut timeline = load_home_timeline(client, &mut seen).await?;
+    // log the initial page links
+    log_page_links(&timeline);
+
+    // intentionally load the previous page while we're at the first page to
+    // check that the behavior of the page object doesn't dead-end at the
+    // beginning. This should not fail, but it also should not update the page
+    // links
+    load_prev_page(&mut timeline, &mut seen).await?;
+    // this should log the same as the initial page links
+    log_page_links(&timeline);
+
+    // moving to the next page should load the next page and update the page
+    // links
+    load_next_page(&mut timeline, &mut seen).await?;
+    // this should log two different links
+    log_page_links(&timeline);
+
+    // this should move back to the initial page
+    load_prev_page(&mut timeline, &mut seen).await?;
+    // this should log the same as the initial page links
+    log_page_links(&timeline);
+
+    Ok(())
+}
+
+#[instrument(name = "initial", skip_all, err)]
+async fn load_home_timeline(client: &Mastodon, seen: &mut SeenStatuses) -> Result<Page<Status>> {
+    let timeline = client
+        .get_home_timeline()
+        .await
+        .context("Couldn't get timeline")?;
+    info!("loaded initial page of home timeline");
+    log_statuses(&seen.retain_unseen(timeline.initial_items.clone()));
+    Ok(timeline)
+}
+
+#[instrument(name = "next_page", skip_all, err)]
+async fn load_next_page(timeline: &mut Page<Status>, seen: &mut SeenStatuses) -> Result<()> {
+    let url = timeline.next.clone().context("no next page")?;
+    let page = timeline
+        .next_page()
+        .await
+        .context("Couldn't get next page")?;
+    info!(%url, "loaded next page");
+    log_page_items(page, seen);
+    Ok(())
+}
+
+#[instrument(name = "prev_page", skip_all, err)]
+async fn load_prev_page(timeline: &mut Page<Status>, seen: &mut SeenStatuses) -> Result<()> {
+    let url = timeline.prev.clone().context("no prev page")?;
+    let page = timeline
+        .prev_page()
+        .await
+        .context("Couldn't get prev page")?;
+    info!(%url, "loaded prev page");
+    log_page_items(page, seen);
+    Ok(())
+}
+
+fn log_page_items(page: Option<Vec<Status>>, seen: &mut SeenStatuses) {
+    page.map_or_else(
+        || warn!("the page loaded successfully, but there is no data"),
+        |items| log_statuses(&seen.retain_unseen(items)),
+    );
+}
+
+/// Logs who posted each status and its URI, scrubbed with `--scrub`.
+fn log_statuses(statuses: &[Status]) {
+    for status in scrub::statuses(statuses).iter() {
+        debug!(acct = status.account.acct, uri = %status.uri);
+    }
+}
+
+/// This exists because there was an issue with the way that the previous and
+/// next pages were loaded when going to the previous page at the beginning or
+/// the next page at the end.
+fn log_page_links(page: &Page<Status>) {
+    debug!(
+        prev = page.prev.as_ref().map_or("None", |u| u.as_str()),
+        next = page.next.as_ref().map_or("None", |u| u.as_str()),
+        "page links"
+    );
+}
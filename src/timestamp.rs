@@ -0,0 +1,25 @@
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Parses an RFC 3339 timestamp such as `2024-06-01T09:00:00Z`. The seconds
+/// can be left out, e.g. `2024-06-01T09:00Z` or `2024-06-01T09:00+02:00`.
+pub fn parse_timestamp(text: &str) -> Result<OffsetDateTime, String> {
+    OffsetDateTime::parse(text, &Rfc3339)
+        .or_else(|err| {
+            // `YYYY-MM-DDTHH:MM` is 16 characters, so insert the seconds there
+            match (text.get(..16), text.get(16..)) {
+                (Some(minutes), Some(offset)) if !offset.starts_with(':') => {
+                    OffsetDateTime::parse(&format!("{minutes}:00{offset}"), &Rfc3339)
+                }
+                _ => Err(err),
+            }
+        })
+        .map_err(|err| format!("{text} is not a timestamp like 2024-06-01T09:00Z: {err}"))
+}
+
+pub fn format_timestamp(timestamp: OffsetDateTime) -> String {
+    // formatting as RFC 3339 only fails for years outside 0-9999
+    timestamp
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| timestamp.to_string())
+}
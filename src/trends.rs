@@ -0,0 +1,154 @@
+//! What is trending on the instance.
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use mastodon_async::prelude::Status;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tracing::{debug, instrument};
+
+use crate::client::Client;
+use crate::output::sparkline;
+use crate::tags::{History, Tag};
+use crate::version::{Requirement, Version};
+
+#[derive(Debug, Args)]
+pub struct TrendsArgs {
+    #[command(subcommand)]
+    command: TrendsCommand,
+
+    /// Show at most this many trends
+    #[arg(long, global = true, default_value_t = 10)]
+    limit: usize,
+}
+
+#[derive(Debug, Subcommand)]
+enum TrendsCommand {
+    /// Hashtags used by more and more accounts, with their use over the last
+    /// week
+    Tags,
+    /// Statuses getting more boosts and favourites than usual
+    Statuses,
+    /// Links shared by more and more accounts, with their shares over the last
+    /// week
+    Links,
+}
+
+/// A link that is being shared, with a preview card.
+#[derive(Debug, Deserialize)]
+struct Link {
+    url: String,
+    title: String,
+    #[serde(default)]
+    history: Vec<History>,
+}
+
+impl TrendsArgs {
+    pub const fn requirement(&self) -> Option<Requirement> {
+        match self.command {
+            TrendsCommand::Tags => None,
+            TrendsCommand::Statuses | TrendsCommand::Links => Some(Requirement::new(
+                "trending statuses and links",
+                Version(3, 5, 0),
+            )),
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: TrendsArgs) -> Result<()> {
+    match args.command {
+        TrendsCommand::Tags => tags(client, args.limit).await,
+        TrendsCommand::Statuses => statuses(client, args.limit).await,
+        TrendsCommand::Links => links(client, args.limit).await,
+    }
+}
+
+#[instrument(skip(client), err)]
+async fn tags(client: &Client, limit: usize) -> Result<()> {
+    let tags: Vec<Tag> = fetch(client, "v1/trends/tags", 20, limit).await?;
+    let width = tags.iter().map(|tag| tag.name.len() + 1).max().unwrap_or(0);
+    println!("{:width$}  {:>5}  {:>8}  week", "tag", "uses", "accounts");
+    for tag in &tags {
+        let (uses, accounts) = today(&tag.history);
+        println!(
+            "{:width$}  {uses:>5}  {accounts:>8}  {}  {}",
+            format!("#{}", tag.name),
+            history_sparkline(&tag.history),
+            tag.url
+        );
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn statuses(client: &Client, limit: usize) -> Result<()> {
+    let statuses: Vec<Status> = fetch(client, "v1/trends/statuses", 40, limit).await?;
+    println!("{:>7}  {:>6}  status", "boosts", "favs");
+    for status in &statuses {
+        println!(
+            "{:>7}  {:>6}  @{} {}",
+            status.reblogs_count,
+            status.favourites_count,
+            status.account.acct,
+            status.url.as_deref().unwrap_or(&status.uri)
+        );
+    }
+    Ok(())
+}
+
+#[instrument(skip(client), err)]
+async fn links(client: &Client, limit: usize) -> Result<()> {
+    let links: Vec<Link> = fetch(client, "v1/trends/links", 20, limit).await?;
+    println!("{:>6}  {:>8}  week     link", "shares", "accounts");
+    for link in &links {
+        let (uses, accounts) = today(&link.history);
+        println!(
+            "{uses:>6}  {accounts:>8}  {}  {} {}",
+            history_sparkline(&link.history),
+            link.title.trim(),
+            link.url
+        );
+    }
+    Ok(())
+}
+
+/// Gets up to `limit` trends, paging with `offset` as trends have no ids.
+/// Each endpoint has its own largest page size.
+async fn fetch<T: DeserializeOwned>(
+    client: &Client,
+    path: &str,
+    max_page_size: usize,
+    limit: usize,
+) -> Result<Vec<T>> {
+    let mut trends = Vec::new();
+    while trends.len() < limit {
+        let page_limit = max_page_size.min(limit - trends.len()).to_string();
+        let offset = trends.len().to_string();
+        let page: Vec<T> = client
+            .get(
+                path,
+                &[("limit", page_limit.as_str()), ("offset", offset.as_str())],
+            )
+            .await
+            .with_context(|| format!("Couldn't get {path}"))?;
+        debug!(count = page.len(), %offset, "loaded page of trends");
+        if page.is_empty() {
+            break;
+        }
+        trends.extend(page);
+    }
+    Ok(trends)
+}
+
+/// The uses and accounts of the newest day.
+fn today(history: &[History]) -> (u64, u64) {
+    history
+        .first()
+        .map_or((0, 0), |day| (day.uses(), day.accounts()))
+}
+
+/// A sparkline of the daily uses, oldest day first.
+fn history_sparkline(history: &[History]) -> String {
+    let uses: Vec<u64> = history.iter().rev().map(History::uses).collect();
+    sparkline(&uses)
+}
@@ -0,0 +1,123 @@
+//! Checking that the instance is new enough for a command.
+//!
+//! Running a command against an instance that doesn't support it usually fails
+//! with a 404 or a response that can't be parsed, which doesn't say what is
+//! wrong. Commands that need a newer Mastodon than most instances run say so,
+//! and a structured warning is logged before running them against older
+//! instances. The command is still run, as forks of Mastodon and other servers
+//! report versions that don't match the features they have.
+
+use std::fmt;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tracing::{debug, instrument, warn};
+
+use crate::client::Client;
+
+/// A Mastodon version, e.g. `4.2.1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u32, pub u32, pub u32);
+
+impl Version {
+    /// Parses the version reported by an instance, which can have a suffix,
+    /// e.g. `4.2.1+glitch` or `2.7.2 (compatible; Pleroma 2.5.0)`.
+    fn parse(text: &str) -> Option<Self> {
+        let version = text
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?;
+        let mut numbers = version.split('.').map(str::parse::<u32>);
+        let major = numbers.next()?.ok()?;
+        let minor = numbers.next().and_then(Result::ok).unwrap_or_default();
+        let patch = numbers.next().and_then(Result::ok).unwrap_or_default();
+        Some(Self(major, minor, patch))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// The oldest Mastodon version with a feature that a command uses.
+#[derive(Debug, Clone, Copy)]
+pub struct Requirement {
+    pub feature: &'static str,
+    pub version: Version,
+}
+
+impl Requirement {
+    pub const fn new(feature: &'static str, version: Version) -> Self {
+        Self { feature, version }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Instance {
+    version: String,
+}
+
+/// Warns if the instance is older than the requirement. Failing to find out
+/// the version is only logged, as the command may well work anyway.
+#[instrument(skip(client))]
+pub async fn check(client: &Client, requirement: Requirement) {
+    let version = match server_version(client).await {
+        Ok(version) => version,
+        Err(err) => {
+            debug!(?err, "couldn't get the server version");
+            return;
+        }
+    };
+    if version < requirement.version {
+        warn!(
+            feature = requirement.feature,
+            required = %requirement.version,
+            server = %version,
+            "the instance is too old for this command, which will probably fail"
+        );
+    }
+}
+
+async fn server_version(client: &Client) -> Result<Version> {
+    let instance: Instance = client
+        .get_cached("v1/instance")
+        .await
+        .context("Couldn't get instance")?;
+    Version::parse(&instance.version)
+        .with_context(|| format!("{} is not a version", instance.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_parsed() {
+        assert_eq!(Version::parse("4.2.1"), Some(Version(4, 2, 1)));
+        assert_eq!(Version::parse("3.5"), Some(Version(3, 5, 0)));
+        assert_eq!(Version::parse("4"), Some(Version(4, 0, 0)));
+    }
+
+    #[test]
+    fn suffixes_are_ignored() {
+        assert_eq!(Version::parse("4.2.1+glitch"), Some(Version(4, 2, 1)));
+        assert_eq!(Version::parse("4.3.0-beta.1"), Some(Version(4, 3, 0)));
+        assert_eq!(
+            Version::parse("2.7.2 (compatible; Pleroma 2.5.0)"),
+            Some(Version(2, 7, 2))
+        );
+    }
+
+    #[test]
+    fn text_without_a_version_is_not_parsed() {
+        assert_eq!(Version::parse(""), None);
+        assert_eq!(Version::parse("Pleroma"), None);
+    }
+
+    #[test]
+    fn versions_compare_by_number() {
+        assert!(Version(4, 10, 0) > Version(4, 9, 9));
+        assert!(Version(3, 1, 0) < Version(4, 0, 0));
+    }
+}
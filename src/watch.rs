@@ -0,0 +1,46 @@
+//! Watching files for changes, so that long running processes can pick up
+//! edits without restarting.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tracing::{debug, warn};
+
+/// Watches a folder and sends the name of each of the given files when it
+/// changes. The folder is watched rather than the files so that files which
+/// are saved by replacing them (as many editors do) or created later are still
+/// seen.
+///
+/// Changes are only sent while the returned watcher is alive.
+pub fn watch_files(
+    folder: &Path,
+    names: &'static [&'static str],
+) -> Result<(RecommendedWatcher, UnboundedReceiver<&'static str>)> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                warn!(?err, "file watch error");
+                return;
+            }
+        };
+        let changed = event
+            .paths
+            .iter()
+            .filter_map(|path| path.file_name()?.to_str())
+            .filter_map(|changed| names.iter().find(|&&name| name == changed));
+        for name in changed {
+            debug!(name, kind = ?event.kind, "file changed");
+            // the receiver only goes away when the process is shutting down
+            let _ = sender.send(*name);
+        }
+    })
+    .context("cannot create file watcher")?;
+    watcher
+        .watch(folder, RecursiveMode::NonRecursive)
+        .with_context(|| format!("cannot watch {folder:?}"))?;
+    Ok((watcher, receiver))
+}